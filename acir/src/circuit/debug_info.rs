@@ -0,0 +1,146 @@
+//! Mapping from the opcodes of a [`Circuit`][super::Circuit] back to the source code they were compiled from.
+//!
+//! [`DebugInfo`] is kept alongside a circuit rather than inside it so that it can be shipped separately
+//! and doesn't affect the serialized circuit.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{transform_opcode_locations, OpcodeLabel, OpcodeLocation};
+
+/// Identifies a source file within [`DebugInfo::files`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FileId(pub u32);
+
+/// A range of source code, with lines and columns counted from 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub file: FileId,
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DebugInfo {
+    /// Path of each source file referenced by `locations`.
+    pub files: BTreeMap<FileId, String>,
+    /// The source code which produced the opcode or Brillig instruction at each location.
+    ///
+    /// Each location maps to a call stack of spans, ordered from the outermost call to the expression
+    /// which produced the opcode.
+    #[serde(with = "location_pairs")]
+    pub locations: BTreeMap<OpcodeLocation, Vec<SourceSpan>>,
+}
+
+impl DebugInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the call stack of source spans which produced the opcode or Brillig instruction at `location`.
+    pub fn source_spans(&self, location: &OpcodeLocation) -> Option<&[SourceSpan]> {
+        self.locations.get(location).map(Vec::as_slice)
+    }
+
+    /// Returns the path of the source file containing `span`.
+    pub fn file_path(&self, span: &SourceSpan) -> Option<&str> {
+        self.files.get(&span.file).map(String::as_str)
+    }
+
+    /// Updates the locations after the circuit has been transformed, given the label of each opcode
+    /// of the transformed circuit.
+    ///
+    /// Each opcode inherits the source spans of the original opcode which it was derived from. The spans
+    /// of opcodes which were removed are dropped.
+    pub fn update_acir(&mut self, opcode_labels: &[OpcodeLabel]) {
+        let locations: Vec<_> = std::mem::take(&mut self.locations).into_iter().collect();
+        self.locations =
+            transform_opcode_locations(&locations, opcode_labels).into_iter().collect();
+    }
+}
+
+/// Serializes a map keyed by [`OpcodeLocation`] as a list of pairs, as formats such as JSON only
+/// allow maps with string keys.
+mod location_pairs {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::circuit::OpcodeLocation;
+
+    pub(super) fn serialize<S: Serializer, T: Serialize>(
+        map: &BTreeMap<OpcodeLocation, T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<OpcodeLocation, T>, D::Error> {
+        let pairs = Vec::<(OpcodeLocation, T)>::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{DebugInfo, FileId, SourceSpan};
+    use crate::circuit::{OpcodeLabel, OpcodeLocation};
+
+    fn span(line: u32) -> SourceSpan {
+        SourceSpan {
+            file: FileId(0),
+            start_line: line,
+            start_column: 5,
+            end_line: line,
+            end_column: 9,
+        }
+    }
+
+    fn debug_info() -> DebugInfo {
+        DebugInfo {
+            files: BTreeMap::from([(FileId(0), "src/main.nr".to_owned())]),
+            locations: BTreeMap::from([
+                (OpcodeLocation::Acir(0), vec![span(1)]),
+                (OpcodeLocation::Acir(1), vec![span(2), span(7)]),
+                (OpcodeLocation::Brillig { acir_index: 2, brillig_index: 3 }, vec![span(3)]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn follows_opcodes_through_transformations() {
+        let mut debug_info = debug_info();
+        // The first opcode is removed, the second split in two and the third moved to the end.
+        debug_info.update_acir(&[
+            OpcodeLabel::Resolved(1),
+            OpcodeLabel::Unresolved,
+            OpcodeLabel::Resolved(1),
+            OpcodeLabel::Resolved(2),
+        ]);
+
+        assert_eq!(
+            debug_info.locations,
+            BTreeMap::from([
+                (OpcodeLocation::Acir(0), vec![span(2), span(7)]),
+                (OpcodeLocation::Acir(2), vec![span(2), span(7)]),
+                (OpcodeLocation::Brillig { acir_index: 3, brillig_index: 3 }, vec![span(3)]),
+            ])
+        );
+        let spans = debug_info.source_spans(&OpcodeLocation::Acir(2)).unwrap();
+        assert_eq!(debug_info.file_path(&spans[0]), Some("src/main.nr"));
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let debug_info = debug_info();
+        let json = serde_json::to_string(&debug_info).unwrap();
+        assert_eq!(serde_json::from_str::<DebugInfo>(&json).unwrap(), debug_info);
+    }
+}
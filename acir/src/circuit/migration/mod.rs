@@ -0,0 +1,349 @@
+//! Upgrades circuits which were serialized by older versions of ACIR.
+//!
+//! Circuits written before the [versioned container format][super::serialization] was
+//! introduced carry no header, so their shape is detected by attempting to decode the payload
+//! as each historical shape in turn, newest first. Circuits with a header are decoded using the
+//! shape associated with their format version.
+//!
+//! Once decoded, a circuit is upgraded one step at a time until it reaches the current shape.
+
+mod v0_13;
+mod v0_16;
+
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+use thiserror::Error;
+
+use super::{
+    serialization::{Header, FORMAT_VERSION, MAGIC},
+    Circuit, CircuitSerializationError,
+};
+
+/// Serialized shape of a [`Circuit`].
+///
+/// Where a headerless circuit is encoded identically under several shapes, the newest is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaVersion {
+    /// Headerless circuit written by ACIR 0.13 to 0.15.
+    Acir0_13,
+    /// Headerless circuit written by ACIR 0.16 to 0.17.
+    Acir0_16,
+    /// Circuit written using the versioned container format.
+    Versioned(u32),
+}
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("serialized circuit does not match any known version of ACIR")]
+    UnrecognizedSchema,
+
+    #[error("cannot migrate {opcode_name} opcode: {reason}")]
+    InvalidOpcode { opcode_name: &'static str, reason: String },
+}
+
+/// Returns the shape which `bytes` were serialized with.
+pub fn detect_schema_version(bytes: &[u8]) -> Result<SchemaVersion, CircuitSerializationError> {
+    decode(bytes).map(|(version, _)| version)
+}
+
+/// Decodes a serialized circuit of any supported shape, upgrading it to the current [`Circuit`].
+pub(super) fn decode(bytes: &[u8]) -> Result<(SchemaVersion, Circuit), CircuitSerializationError> {
+    if !bytes.starts_with(&MAGIC) {
+        return decode_headerless(bytes);
+    }
+
+    let mut reader = bytes;
+    let header = Header::read(&mut reader)?;
+    header.validate(reader)?;
+    let buf = inflate(reader)?;
+    let circuit = decode_versioned(header.version, &buf)?;
+    Ok((SchemaVersion::Versioned(header.version), circuit))
+}
+
+fn decode_versioned(version: u32, buf: &[u8]) -> Result<Circuit, CircuitSerializationError> {
+    match version {
+        1 => Ok(rmp_serde::from_slice::<v0_16::Circuit>(buf)?.upgrade()),
+        // Format version 2 differs only in foreign call results carrying no error, which is read as `None`.
+        2 | FORMAT_VERSION => Ok(rmp_serde::from_slice(buf)?),
+        found => {
+            Err(CircuitSerializationError::UnsupportedVersion { found, expected: FORMAT_VERSION })
+        }
+    }
+}
+
+fn decode_headerless(bytes: &[u8]) -> Result<(SchemaVersion, Circuit), CircuitSerializationError> {
+    // Anything which doesn't inflate can't be a circuit written by any version of ACIR.
+    let buf = inflate(bytes).map_err(|_| CircuitSerializationError::InvalidMagic)?;
+
+    // ACIR 0.16 and 0.17 wrote the same shape as format version 1.
+    if let Ok(circuit) = rmp_serde::from_slice::<v0_16::Circuit>(&buf) {
+        return Ok((SchemaVersion::Acir0_16, circuit.upgrade()));
+    }
+    if let Ok(circuit) = rmp_serde::from_slice::<v0_13::Circuit>(&buf) {
+        return Ok((SchemaVersion::Acir0_13, circuit.upgrade()?.upgrade()));
+    }
+    Err(MigrationError::UnrecognizedSchema.into())
+}
+
+fn inflate(payload: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut deflater = DeflateDecoder::new(payload);
+    let mut buf = Vec::new();
+    deflater.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::io::Write;
+
+    use brillig_vm::{ForeignCallOutput, ForeignCallResult, RegisterIndex, RegisterOrMemory};
+    use flate2::{write::DeflateEncoder, Compression};
+    use serde::Serialize;
+
+    use super::{decode, detect_schema_version, v0_13, v0_16, MigrationError, SchemaVersion};
+    use crate::{
+        circuit::{
+            brillig::{Brillig, BrilligInputs, BrilligOutputs},
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            serialization::{Header, FORMAT_VERSION},
+            Circuit, CircuitSerializationError, Opcode, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    /// Serializes `circuit` the way ACIR did before the versioned container format was introduced.
+    fn serialize_headerless<T: Serialize>(circuit: &T) -> Vec<u8> {
+        let buf = rmp_serde::to_vec(circuit).unwrap();
+        let mut deflater = DeflateEncoder::new(Vec::new(), Compression::best());
+        deflater.write_all(&buf).unwrap();
+        deflater.finish().unwrap()
+    }
+
+    /// Checks that `bytes` are detected as `expected_version` and upgrade to `expected`, and that
+    /// the upgraded circuit survives a round trip through the current format.
+    fn assert_migrates(bytes: &[u8], expected_version: SchemaVersion, expected: Circuit) {
+        assert_eq!(detect_schema_version(bytes).unwrap(), expected_version);
+        let circuit = Circuit::read(bytes).unwrap();
+        assert_eq!(circuit, expected);
+
+        let mut current_bytes = Vec::new();
+        circuit.write(&mut current_bytes).unwrap();
+        assert_eq!(
+            detect_schema_version(&current_bytes).unwrap(),
+            SchemaVersion::Versioned(FORMAT_VERSION)
+        );
+        assert_eq!(Circuit::read(&*current_bytes).unwrap(), expected);
+    }
+
+    fn public_inputs(witnesses: &[u32]) -> PublicInputs {
+        PublicInputs(witnesses.iter().copied().map(Witness).collect::<BTreeSet<_>>())
+    }
+
+    fn legacy_circuit(opcodes: Vec<v0_13::Opcode>) -> v0_13::Circuit {
+        v0_13::Circuit {
+            current_witness_index: 4,
+            opcodes,
+            public_parameters: public_inputs(&[1]),
+            return_values: public_inputs(&[4]),
+        }
+    }
+
+    fn current_circuit(opcodes: Vec<Opcode>) -> Circuit {
+        Circuit {
+            current_witness_index: 4,
+            opcodes,
+            public_parameters: public_inputs(&[1]),
+            return_values: public_inputs(&[4]),
+            assert_messages: Vec::new(),
+        }
+    }
+
+    fn range_input(witness: u32) -> FunctionInput {
+        FunctionInput { witness: Witness(witness), num_bits: FieldElement::max_num_bits() }
+    }
+
+    #[test]
+    fn reads_headerless_0_16_circuit() {
+        let circuit = current_circuit(vec![
+            Opcode::Arithmetic(Expression::from(Witness(1))),
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Pedersen {
+                inputs: vec![range_input(1)],
+                domain_separator: 0,
+                outputs: (Witness(2), Witness(3)),
+            }),
+        ]);
+        let legacy = v0_16::Circuit {
+            current_witness_index: circuit.current_witness_index,
+            opcodes: circuit.opcodes.clone(),
+            public_parameters: circuit.public_parameters.clone(),
+            return_values: circuit.return_values.clone(),
+        };
+        let bytes = serialize_headerless(&legacy);
+        assert_migrates(&bytes, SchemaVersion::Acir0_16, circuit);
+    }
+
+    #[test]
+    fn migrates_format_version_1() {
+        let circuit = current_circuit(vec![Opcode::Arithmetic(Expression::from(Witness(1)))]);
+        let legacy = v0_16::Circuit {
+            current_witness_index: circuit.current_witness_index,
+            opcodes: circuit.opcodes.clone(),
+            public_parameters: circuit.public_parameters.clone(),
+            return_values: circuit.return_values.clone(),
+        };
+
+        let payload = serialize_headerless(&legacy);
+        let mut bytes = Vec::new();
+        Header { version: 1, ..Header::new(&payload) }.write(&mut bytes).unwrap();
+        bytes.extend(payload);
+        assert_migrates(&bytes, SchemaVersion::Versioned(1), circuit);
+    }
+
+    #[test]
+    fn reads_format_version_2_foreign_call_results() {
+        /// Shape of [`ForeignCallResult`] written by format version 2.
+        #[derive(Serialize)]
+        struct ForeignCallResultV2 {
+            values: Vec<ForeignCallOutput>,
+        }
+
+        let values = vec![ForeignCallOutput::Single(FieldElement::one().into())];
+        let buf = rmp_serde::to_vec(&ForeignCallResultV2 { values: values.clone() }).unwrap();
+        let result: ForeignCallResult = rmp_serde::from_slice(&buf).unwrap();
+        assert_eq!(result, ForeignCallResult { values, error: None });
+
+        let circuit = current_circuit(vec![Opcode::Arithmetic(Expression::from(Witness(1)))]);
+        let payload = serialize_headerless(&circuit);
+        let mut bytes = Vec::new();
+        Header { version: 2, ..Header::new(&payload) }.write(&mut bytes).unwrap();
+        bytes.extend(payload);
+        assert_migrates(&bytes, SchemaVersion::Versioned(2), circuit);
+    }
+
+    #[test]
+    fn migrates_0_13_black_box_outputs() {
+        let legacy = legacy_circuit(vec![
+            v0_13::Opcode::BlackBoxFuncCall(v0_13::BlackBoxFuncCall::Pedersen {
+                inputs: vec![range_input(1)],
+                domain_separator: 0,
+                outputs: vec![Witness(2), Witness(3)],
+            }),
+            v0_13::Opcode::BlackBoxFuncCall(v0_13::BlackBoxFuncCall::FixedBaseScalarMul {
+                input: range_input(1),
+                outputs: vec![Witness(3), Witness(4)],
+            }),
+        ]);
+        let expected = current_circuit(vec![
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Pedersen {
+                inputs: vec![range_input(1)],
+                domain_separator: 0,
+                outputs: (Witness(2), Witness(3)),
+            }),
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::FixedBaseScalarMul {
+                input: range_input(1),
+                outputs: (Witness(3), Witness(4)),
+            }),
+        ]);
+        assert_eq!(legacy.clone().upgrade().unwrap().upgrade(), expected);
+        // A two element vector is encoded identically to a pair, so these opcodes can't be told
+        // apart from the newer shape and are read as such.
+        assert_migrates(&serialize_headerless(&legacy), SchemaVersion::Acir0_16, expected);
+    }
+
+    #[test]
+    fn migrates_0_13_oracle_to_brillig() {
+        let legacy = legacy_circuit(vec![v0_13::Opcode::Oracle(v0_13::OracleData {
+            name: "get_value".to_owned(),
+            inputs: vec![Expression::from(Witness(1)), Expression::from(Witness(2))],
+            input_values: vec![FieldElement::one(), FieldElement::zero()],
+            outputs: vec![Witness(3)],
+            output_values: vec![FieldElement::from(5u128)],
+        })]);
+        let expected = current_circuit(vec![Opcode::Brillig(Brillig {
+            inputs: vec![
+                BrilligInputs::Single(Expression::from(Witness(1))),
+                BrilligInputs::Single(Expression::from(Witness(2))),
+            ],
+            outputs: vec![BrilligOutputs::Simple(Witness(3))],
+            foreign_call_results: vec![ForeignCallResult {
+                values: vec![ForeignCallOutput::Single(FieldElement::from(5u128).into())],
+                error: None,
+            }],
+            bytecode: vec![brillig_vm::Opcode::ForeignCall {
+                function: "get_value".to_owned(),
+                destinations: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+                inputs: vec![
+                    RegisterOrMemory::RegisterIndex(RegisterIndex::from(0)),
+                    RegisterOrMemory::RegisterIndex(RegisterIndex::from(1)),
+                ],
+            }],
+            predicate: None,
+        })]);
+        assert_migrates(&serialize_headerless(&legacy), SchemaVersion::Acir0_13, expected);
+    }
+
+    #[test]
+    fn migrates_0_13_brillig_foreign_call_results() {
+        let bytecode = vec![brillig_vm::Opcode::ForeignCall {
+            function: "get_values".to_owned(),
+            destinations: vec![
+                RegisterOrMemory::RegisterIndex(RegisterIndex::from(0)),
+                RegisterOrMemory::RegisterIndex(RegisterIndex::from(1)),
+            ],
+            inputs: vec![],
+        }];
+        let legacy = legacy_circuit(vec![v0_13::Opcode::Brillig(v0_13::Brillig {
+            inputs: vec![],
+            outputs: vec![BrilligOutputs::Simple(Witness(1)), BrilligOutputs::Simple(Witness(2))],
+            foreign_call_results: vec![v0_13::LegacyForeignCallResult {
+                values: vec![FieldElement::one().into(), FieldElement::from(2u128).into()],
+            }],
+            bytecode: bytecode.clone(),
+            predicate: Some(Expression::one()),
+        })]);
+        let expected = current_circuit(vec![Opcode::Brillig(Brillig {
+            inputs: vec![],
+            outputs: vec![BrilligOutputs::Simple(Witness(1)), BrilligOutputs::Simple(Witness(2))],
+            foreign_call_results: vec![ForeignCallResult {
+                values: vec![
+                    ForeignCallOutput::Single(FieldElement::one().into()),
+                    ForeignCallOutput::Single(FieldElement::from(2u128).into()),
+                ],
+                error: None,
+            }],
+            bytecode,
+            predicate: Some(Expression::one()),
+        })]);
+        assert_migrates(&serialize_headerless(&legacy), SchemaVersion::Acir0_13, expected);
+    }
+
+    #[test]
+    fn rejects_0_13_pedersen_with_wrong_number_of_outputs() {
+        let legacy = legacy_circuit(vec![v0_13::Opcode::BlackBoxFuncCall(
+            v0_13::BlackBoxFuncCall::Pedersen {
+                inputs: vec![range_input(1)],
+                domain_separator: 0,
+                outputs: vec![Witness(2), Witness(3), Witness(4)],
+            },
+        )]);
+        assert!(matches!(
+            decode(&serialize_headerless(&legacy)),
+            Err(CircuitSerializationError::Migration(MigrationError::InvalidOpcode {
+                opcode_name: "pedersen",
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn rejects_unrecognized_headerless_payload() {
+        let bytes = serialize_headerless(&("not", "a", "circuit"));
+        assert!(matches!(
+            decode(&bytes),
+            Err(CircuitSerializationError::Migration(MigrationError::UnrecognizedSchema))
+        ));
+    }
+}
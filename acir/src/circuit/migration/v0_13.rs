@@ -0,0 +1,333 @@
+//! Serialized shape of [`Circuit`][crate::circuit::Circuit] written by `acir` 0.13 to 0.15.
+//!
+//! This differs from the current shape in that:
+//! - There is an `Oracle` opcode which has since been replaced by Brillig foreign calls.
+//! - `Pedersen` and `FixedBaseScalarMul` store their outputs in a vector rather than a pair.
+//! - Each Brillig foreign call result is a flat list of values.
+
+use brillig_vm::{ForeignCallOutput, ForeignCallResult, RegisterIndex, RegisterOrMemory, Value};
+use serde::{Deserialize, Serialize};
+
+use super::{v0_16, MigrationError};
+use crate::{
+    circuit::{
+        brillig::{Brillig as CurrentBrillig, BrilligInputs, BrilligOutputs},
+        directives::Directive,
+        opcodes::{
+            BlackBoxFuncCall as CurrentBlackBoxFuncCall, FunctionInput, MemoryBlock,
+            Opcode as CurrentOpcode,
+        },
+        PublicInputs,
+    },
+    native_types::{Expression, Witness},
+    FieldElement,
+};
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Circuit {
+    pub(crate) current_witness_index: u32,
+    pub(crate) opcodes: Vec<Opcode>,
+    pub(crate) public_parameters: PublicInputs,
+    pub(crate) return_values: PublicInputs,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Opcode {
+    Arithmetic(Expression),
+    BlackBoxFuncCall(BlackBoxFuncCall),
+    Directive(Directive),
+    Block(MemoryBlock),
+    ROM(MemoryBlock),
+    RAM(MemoryBlock),
+    Oracle(OracleData),
+    Brillig(Brillig),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct OracleData {
+    /// Name of the oracle
+    pub(crate) name: String,
+    /// Inputs
+    pub(crate) inputs: Vec<Expression>,
+    /// Input values - they are progressively computed by the pwg
+    pub(crate) input_values: Vec<FieldElement>,
+    /// Output witness
+    pub(crate) outputs: Vec<Witness>,
+    /// Output values - they are computed by the (external) oracle once the inputs are known
+    pub(crate) output_values: Vec<FieldElement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Brillig {
+    pub(crate) inputs: Vec<BrilligInputs>,
+    pub(crate) outputs: Vec<BrilligOutputs>,
+    pub(crate) foreign_call_results: Vec<LegacyForeignCallResult>,
+    pub(crate) bytecode: Vec<brillig_vm::Opcode>,
+    pub(crate) predicate: Option<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LegacyForeignCallResult {
+    pub(crate) values: Vec<Value>,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum BlackBoxFuncCall {
+    AND {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: Witness,
+    },
+    XOR {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: Witness,
+    },
+    RANGE {
+        input: FunctionInput,
+    },
+    SHA256 {
+        inputs: Vec<FunctionInput>,
+        outputs: Vec<Witness>,
+    },
+    Blake2s {
+        inputs: Vec<FunctionInput>,
+        outputs: Vec<Witness>,
+    },
+    SchnorrVerify {
+        public_key_x: FunctionInput,
+        public_key_y: FunctionInput,
+        signature: Vec<FunctionInput>,
+        message: Vec<FunctionInput>,
+        output: Witness,
+    },
+    Pedersen {
+        inputs: Vec<FunctionInput>,
+        domain_separator: u32,
+        outputs: Vec<Witness>,
+    },
+    HashToField128Security {
+        inputs: Vec<FunctionInput>,
+        output: Witness,
+    },
+    EcdsaSecp256k1 {
+        public_key_x: Vec<FunctionInput>,
+        public_key_y: Vec<FunctionInput>,
+        signature: Vec<FunctionInput>,
+        hashed_message: Vec<FunctionInput>,
+        output: Witness,
+    },
+    FixedBaseScalarMul {
+        input: FunctionInput,
+        outputs: Vec<Witness>,
+    },
+    Keccak256 {
+        inputs: Vec<FunctionInput>,
+        outputs: Vec<Witness>,
+    },
+    Keccak256VariableLength {
+        inputs: Vec<FunctionInput>,
+        var_message_size: FunctionInput,
+        outputs: Vec<Witness>,
+    },
+    RecursiveAggregation {
+        verification_key: Vec<FunctionInput>,
+        proof: Vec<FunctionInput>,
+        public_inputs: Vec<FunctionInput>,
+        key_hash: FunctionInput,
+        input_aggregation_object: Option<Vec<FunctionInput>>,
+        output_aggregation_object: Vec<Witness>,
+    },
+}
+
+impl Circuit {
+    /// Upgrades the circuit to the shape used by format version 1.
+    pub(crate) fn upgrade(self) -> Result<v0_16::Circuit, MigrationError> {
+        let opcodes =
+            self.opcodes.into_iter().map(Opcode::upgrade).collect::<Result<Vec<_>, _>>()?;
+        Ok(v0_16::Circuit {
+            current_witness_index: self.current_witness_index,
+            opcodes,
+            public_parameters: self.public_parameters,
+            return_values: self.return_values,
+        })
+    }
+}
+
+impl Opcode {
+    fn upgrade(self) -> Result<CurrentOpcode, MigrationError> {
+        let opcode = match self {
+            Opcode::Arithmetic(expr) => CurrentOpcode::Arithmetic(expr),
+            Opcode::BlackBoxFuncCall(call) => CurrentOpcode::BlackBoxFuncCall(call.upgrade()?),
+            Opcode::Directive(directive) => CurrentOpcode::Directive(directive),
+            Opcode::Block(block) => CurrentOpcode::Block(block),
+            Opcode::ROM(block) => CurrentOpcode::ROM(block),
+            Opcode::RAM(block) => CurrentOpcode::RAM(block),
+            Opcode::Oracle(oracle) => CurrentOpcode::Brillig(oracle.upgrade()),
+            Opcode::Brillig(brillig) => CurrentOpcode::Brillig(brillig.upgrade()),
+        };
+        Ok(opcode)
+    }
+}
+
+impl OracleData {
+    /// Oracles are replaced with a Brillig opcode which performs a single foreign call.
+    ///
+    /// Each input is loaded into a register, starting from register 0, and the foreign call
+    /// writes its outputs into registers starting from register 0 which are then read back out.
+    fn upgrade(self) -> CurrentBrillig {
+        let input_registers = (0..self.inputs.len())
+            .map(|index| RegisterOrMemory::RegisterIndex(RegisterIndex::from(index)))
+            .collect();
+        let output_registers = (0..self.outputs.len())
+            .map(|index| RegisterOrMemory::RegisterIndex(RegisterIndex::from(index)))
+            .collect();
+
+        // If the oracle had already been resolved then we carry over its result.
+        let foreign_call_results = if self.output_values.is_empty() {
+            Vec::new()
+        } else {
+            let values = self
+                .output_values
+                .into_iter()
+                .map(|value| ForeignCallOutput::Single(value.into()))
+                .collect();
+            vec![ForeignCallResult { values, error: None }]
+        };
+
+        CurrentBrillig {
+            inputs: self.inputs.into_iter().map(BrilligInputs::Single).collect(),
+            outputs: self.outputs.into_iter().map(BrilligOutputs::Simple).collect(),
+            foreign_call_results,
+            bytecode: vec![brillig_vm::Opcode::ForeignCall {
+                function: self.name,
+                destinations: output_registers,
+                inputs: input_registers,
+            }],
+            predicate: None,
+        }
+    }
+}
+
+impl Brillig {
+    fn upgrade(self) -> CurrentBrillig {
+        let foreign_call_results = self
+            .foreign_call_results
+            .into_iter()
+            .map(|result| ForeignCallResult {
+                values: result.values.into_iter().map(ForeignCallOutput::Single).collect(),
+                error: None,
+            })
+            .collect();
+        CurrentBrillig {
+            inputs: self.inputs,
+            outputs: self.outputs,
+            foreign_call_results,
+            bytecode: self.bytecode,
+            predicate: self.predicate,
+        }
+    }
+}
+
+impl BlackBoxFuncCall {
+    fn upgrade(self) -> Result<CurrentBlackBoxFuncCall, MigrationError> {
+        let call = match self {
+            BlackBoxFuncCall::AND { lhs, rhs, output } => {
+                CurrentBlackBoxFuncCall::AND { lhs, rhs, output }
+            }
+            BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+                CurrentBlackBoxFuncCall::XOR { lhs, rhs, output }
+            }
+            BlackBoxFuncCall::RANGE { input } => CurrentBlackBoxFuncCall::RANGE { input },
+            BlackBoxFuncCall::SHA256 { inputs, outputs } => {
+                CurrentBlackBoxFuncCall::SHA256 { inputs, outputs }
+            }
+            BlackBoxFuncCall::Blake2s { inputs, outputs } => {
+                CurrentBlackBoxFuncCall::Blake2s { inputs, outputs }
+            }
+            BlackBoxFuncCall::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                signature,
+                message,
+                output,
+            } => CurrentBlackBoxFuncCall::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                signature,
+                message,
+                output,
+            },
+            BlackBoxFuncCall::Pedersen { inputs, domain_separator, outputs } => {
+                CurrentBlackBoxFuncCall::Pedersen {
+                    inputs,
+                    domain_separator,
+                    outputs: into_pair("pedersen", outputs)?,
+                }
+            }
+            BlackBoxFuncCall::HashToField128Security { inputs, output } => {
+                CurrentBlackBoxFuncCall::HashToField128Security { inputs, output }
+            }
+            BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            } => CurrentBlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            },
+            BlackBoxFuncCall::FixedBaseScalarMul { input, outputs } => {
+                CurrentBlackBoxFuncCall::FixedBaseScalarMul {
+                    input,
+                    outputs: into_pair("fixed_base_scalar_mul", outputs)?,
+                }
+            }
+            BlackBoxFuncCall::Keccak256 { inputs, outputs } => {
+                CurrentBlackBoxFuncCall::Keccak256 { inputs, outputs }
+            }
+            BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs } => {
+                CurrentBlackBoxFuncCall::Keccak256VariableLength {
+                    inputs,
+                    var_message_size,
+                    outputs,
+                }
+            }
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            } => CurrentBlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            },
+        };
+        Ok(call)
+    }
+}
+
+fn into_pair(
+    opcode_name: &'static str,
+    outputs: Vec<Witness>,
+) -> Result<(Witness, Witness), MigrationError> {
+    match outputs[..] {
+        [first, second] => Ok((first, second)),
+        _ => Err(MigrationError::InvalidOpcode {
+            opcode_name,
+            reason: format!("expected 2 outputs but found {}", outputs.len()),
+        }),
+    }
+}
@@ -0,0 +1,28 @@
+//! Serialized shape of [`Circuit`][crate::circuit::Circuit] written by `acir` 0.16 and 0.17, and by
+//! format version 1 of the versioned container format.
+//!
+//! This differs from the current shape in that circuits carry no assertion messages.
+
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{Circuit as CurrentCircuit, Opcode, PublicInputs};
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Circuit {
+    pub(crate) current_witness_index: u32,
+    pub(crate) opcodes: Vec<Opcode>,
+    pub(crate) public_parameters: PublicInputs,
+    pub(crate) return_values: PublicInputs,
+}
+
+impl Circuit {
+    pub(crate) fn upgrade(self) -> CurrentCircuit {
+        CurrentCircuit {
+            current_witness_index: self.current_witness_index,
+            opcodes: self.opcodes,
+            public_parameters: self.public_parameters,
+            return_values: self.return_values,
+            assert_messages: Vec::new(),
+        }
+    }
+}
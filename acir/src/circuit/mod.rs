@@ -1,14 +1,20 @@
 pub mod black_box_functions;
 pub mod brillig;
+pub mod debug_info;
 pub mod directives;
+pub mod migration;
 pub mod opcodes;
+pub mod parser;
+pub mod serialization;
 
-use crate::native_types::Witness;
+use crate::native_types::{Witness, WitnessRemapping};
 pub use opcodes::Opcode;
+pub use parser::ParseError;
+pub use serialization::CircuitSerializationError;
 
-use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use flate2::{write::DeflateEncoder, Compression};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::prelude::*;
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -25,6 +31,12 @@ pub struct Circuit {
     pub public_parameters: PublicInputs,
     /// The set of public inputs calculated within the circuit.
     pub return_values: PublicInputs,
+    /// Messages to report when the opcode at a given location fails, e.g. the message of a Noir `assert`.
+    ///
+    /// Locations refer to the position of opcodes within this circuit so are updated whenever opcodes are
+    /// added, removed or reordered.
+    #[serde(default)]
+    pub assert_messages: Vec<(OpcodeLocation, String)>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
@@ -36,11 +48,74 @@ pub enum OpcodeLabel {
     Resolved(u64),
 }
 
+/// The location of an opcode within a [`Circuit`], or of an instruction within one of its Brillig opcodes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OpcodeLocation {
+    /// The opcode at the given index of [`Circuit::opcodes`].
+    Acir(usize),
+    /// The instruction at `brillig_index` in the bytecode of the Brillig opcode at `acir_index`.
+    Brillig { acir_index: usize, brillig_index: usize },
+}
+
+impl OpcodeLocation {
+    /// Returns the index of the opcode within [`Circuit::opcodes`].
+    pub fn acir_index(&self) -> usize {
+        match self {
+            OpcodeLocation::Acir(acir_index) | OpcodeLocation::Brillig { acir_index, .. } => {
+                *acir_index
+            }
+        }
+    }
+
+    /// Returns the same location within the opcode which has been moved to `acir_index`.
+    pub fn with_acir_index(self, acir_index: usize) -> Self {
+        match self {
+            OpcodeLocation::Acir(_) => OpcodeLocation::Acir(acir_index),
+            OpcodeLocation::Brillig { brillig_index, .. } => {
+                OpcodeLocation::Brillig { acir_index, brillig_index }
+            }
+        }
+    }
+}
+
+/// Moves values attached to the opcodes of a circuit, such as its [assertion messages][Circuit::assert_messages],
+/// onto the opcodes of the transformed circuit whose labels are `opcode_labels`.
+///
+/// Each value is attached to every opcode derived from the opcode which it was originally attached to.
+/// Values of opcodes which were removed are dropped.
+pub fn transform_opcode_locations<T: Clone>(
+    values: &[(OpcodeLocation, T)],
+    opcode_labels: &[OpcodeLabel],
+) -> Vec<(OpcodeLocation, T)> {
+    let mut new_indices: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (new_index, label) in opcode_labels.iter().enumerate() {
+        if let OpcodeLabel::Resolved(old_index) = label {
+            new_indices.entry(*old_index as usize).or_default().push(new_index);
+        }
+    }
+
+    let mut transformed = Vec::new();
+    for (location, value) in values {
+        for new_index in new_indices.get(&location.acir_index()).into_iter().flatten() {
+            transformed.push((location.with_acir_index(*new_index), value.clone()));
+        }
+    }
+    transformed
+}
+
 impl Circuit {
     pub fn num_vars(&self) -> u32 {
         self.current_witness_index + 1
     }
 
+    /// Returns the message to report if the opcode at `location` fails.
+    pub fn get_assert_message(&self, location: OpcodeLocation) -> Option<&str> {
+        self.assert_messages
+            .iter()
+            .find(|(message_location, _)| *message_location == location)
+            .map(|(_, message)| message.as_str())
+    }
+
     /// Returns all public inputs. This includes those provided as parameters to the circuit and those
     /// computed as return values.
     pub fn public_inputs(&self) -> PublicInputs {
@@ -49,21 +124,88 @@ impl Circuit {
         PublicInputs(public_inputs)
     }
 
-    pub fn write<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
-        let buf = rmp_serde::to_vec(&self).unwrap();
-        let mut deflater = DeflateEncoder::new(writer, Compression::best());
-        deflater.write_all(&buf).unwrap();
+    /// Serializes the circuit into `writer` using the [versioned container format][serialization].
+    pub fn write<W: std::io::Write>(&self, mut writer: W) -> Result<(), CircuitSerializationError> {
+        let buf = rmp_serde::to_vec(&self)?;
+        let mut deflater = DeflateEncoder::new(Vec::new(), Compression::best());
+        deflater.write_all(&buf)?;
+        let payload = deflater.finish()?;
 
+        serialization::Header::new(&payload).write(&mut writer)?;
+        writer.write_all(&payload)?;
         Ok(())
     }
-    pub fn read<R: std::io::Read>(reader: R) -> std::io::Result<Self> {
-        let mut deflater = DeflateDecoder::new(reader);
-        let mut buf_d = Vec::new();
-        deflater.read_to_end(&mut buf_d).unwrap();
-        let circuit = rmp_serde::from_slice(buf_d.as_slice()).unwrap();
+
+    /// Deserializes a circuit which was written using [`Circuit::write`].
+    ///
+    /// Circuits written by older versions of ACIR are [migrated][migration] to the current shape.
+    ///
+    /// Returns an error if the data is corrupted, was written by an incompatible version of ACIR
+    /// or was compiled against a different field than the one ACIR is compiled with.
+    pub fn read<R: std::io::Read>(mut reader: R) -> Result<Self, CircuitSerializationError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let (_, circuit) = migration::decode(&bytes)?;
         Ok(circuit)
     }
 
+    /// Returns the witnesses which are either public inputs or are used by an opcode of the circuit.
+    pub fn used_witnesses(&self) -> BTreeSet<Witness> {
+        let mut used_witnesses = self.public_inputs().0;
+        for opcode in &self.opcodes {
+            used_witnesses.extend(opcode.witnesses());
+        }
+        used_witnesses
+    }
+
+    /// Renumbers the witnesses of the circuit so that they are contiguous, starting from 1.
+    ///
+    /// Witnesses keep their relative order. Afterwards `current_witness_index` is equal to the number
+    /// of witnesses which are used by the circuit.
+    ///
+    /// Returns the renumbered circuit along with the mapping between the old and new witnesses.
+    pub fn compact_witnesses(self) -> (Circuit, WitnessRemapping) {
+        let remapping: WitnessRemapping = self
+            .used_witnesses()
+            .into_iter()
+            .enumerate()
+            .map(|(index, witness)| (witness, Witness(index as u32 + 1)))
+            .collect();
+
+        let rename = |witness: &mut Witness| {
+            *witness = remapping.to_new(*witness).expect("all used witnesses are remapped");
+        };
+        let opcodes = self
+            .opcodes
+            .into_iter()
+            .map(|mut opcode| {
+                opcode.for_each_witness_mut(&mut |witness| rename(witness));
+                opcode
+            })
+            .collect();
+        let rename_public_inputs = |public_inputs: PublicInputs| {
+            PublicInputs(
+                public_inputs
+                    .0
+                    .into_iter()
+                    .map(|mut witness| {
+                        rename(&mut witness);
+                        witness
+                    })
+                    .collect(),
+            )
+        };
+
+        let circuit = Circuit {
+            current_witness_index: remapping.len() as u32,
+            opcodes,
+            public_parameters: rename_public_inputs(self.public_parameters),
+            return_values: rename_public_inputs(self.return_values),
+            assert_messages: self.assert_messages,
+        };
+        (circuit, remapping)
+    }
+
     /// Initial list of labels attached to opcodes.
     pub fn initial_opcode_labels(&self) -> Vec<OpcodeLabel> {
         (0..self.opcodes.len()).map(|label| OpcodeLabel::Resolved(label as u64)).collect()
@@ -94,6 +236,22 @@ impl std::fmt::Display for Circuit {
         write!(f, "return value indices : ")?;
         write_public_inputs(f, &self.return_values)?;
 
+        if !self.assert_messages.is_empty() {
+            write!(f, "assert messages : [")?;
+            for (index, (location, message)) in self.assert_messages.iter().enumerate() {
+                if index != 0 {
+                    write!(f, ", ")?;
+                }
+                match location {
+                    OpcodeLocation::Acir(acir_index) => write!(f, "({acir_index}, {message:?})")?,
+                    OpcodeLocation::Brillig { acir_index, brillig_index } => {
+                        write!(f, "({acir_index}.{brillig_index}, {message:?})")?;
+                    }
+                }
+            }
+            writeln!(f, "]")?;
+        }
+
         for opcode in &self.opcodes {
             writeln!(f, "{opcode}")?
         }
@@ -103,7 +261,13 @@ impl std::fmt::Display for Circuit {
 
 impl std::fmt::Debug for Circuit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+        f.debug_struct("Circuit")
+            .field("current_witness_index", &self.current_witness_index)
+            .field("opcodes", &self.opcodes)
+            .field("public_parameters", &self.public_parameters)
+            .field("return_values", &self.return_values)
+            .field("assert_messages", &self.assert_messages)
+            .finish()
     }
 }
 
@@ -127,9 +291,10 @@ mod tests {
 
     use super::{
         opcodes::{BlackBoxFuncCall, FunctionInput},
-        Circuit, Opcode, PublicInputs,
+        serialization::{CircuitSerializationError, FORMAT_VERSION},
+        Circuit, Opcode, OpcodeLocation, PublicInputs,
     };
-    use crate::native_types::Witness;
+    use crate::native_types::{Witness, WitnessRemapping};
     use acir_field::FieldElement;
 
     fn directive_opcode() -> Opcode {
@@ -158,6 +323,7 @@ mod tests {
             opcodes: vec![and_opcode(), range_opcode(), directive_opcode()],
             public_parameters: PublicInputs(BTreeSet::from_iter(vec![Witness(2), Witness(12)])),
             return_values: PublicInputs(BTreeSet::from_iter(vec![Witness(4), Witness(12)])),
+            assert_messages: vec![(OpcodeLocation::Acir(1), "out of range".to_owned())],
         };
 
         fn read_write(circuit: Circuit) -> (Circuit, Circuit) {
@@ -171,6 +337,126 @@ mod tests {
         assert_eq!(circ, got_circ)
     }
 
+    fn serialized_circuit() -> Vec<u8> {
+        let circuit = Circuit {
+            current_witness_index: 5,
+            opcodes: vec![and_opcode(), range_opcode(), directive_opcode()],
+            public_parameters: PublicInputs(BTreeSet::from_iter(vec![Witness(2)])),
+            return_values: PublicInputs(BTreeSet::from_iter(vec![Witness(4)])),
+            assert_messages: Vec::new(),
+        };
+        let mut bytes = Vec::new();
+        circuit.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn rejects_missing_magic() {
+        let mut bytes = serialized_circuit();
+        bytes[0] = b'X';
+        assert!(matches!(Circuit::read(&*bytes), Err(CircuitSerializationError::InvalidMagic)));
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let mut bytes = serialized_circuit();
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Circuit::read(&*bytes),
+            Err(CircuitSerializationError::UnsupportedVersion { found, expected })
+                if found == FORMAT_VERSION + 1 && expected == FORMAT_VERSION
+        ));
+    }
+
+    #[test]
+    fn rejects_other_field() {
+        let mut bytes = serialized_circuit();
+        // Flip the field identifier between BN254 (0) and BLS12-381 (1).
+        bytes[8] ^= 1;
+        assert!(matches!(
+            Circuit::read(&*bytes),
+            Err(CircuitSerializationError::FieldMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_corrupted_payload() {
+        let mut bytes = serialized_circuit();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(
+            Circuit::read(&*bytes),
+            Err(CircuitSerializationError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let bytes = serialized_circuit();
+        assert!(matches!(Circuit::read(&bytes[..6]), Err(CircuitSerializationError::Io(_))));
+    }
+
+    #[test]
+    fn compacts_witnesses_across_opcodes() {
+        use super::{
+            brillig::{Brillig, BrilligInputs, BrilligOutputs},
+            directives::Directive,
+            opcodes::{BlockId, MemOp, MemoryBlock},
+        };
+        use crate::native_types::Expression;
+
+        let invert = |x, result| {
+            Opcode::Directive(Directive::Invert { x: Witness(x), result: Witness(result) })
+        };
+        let brillig = |input, output| {
+            Opcode::Brillig(Brillig {
+                inputs: vec![BrilligInputs::Single(Witness(input).into())],
+                outputs: vec![BrilligOutputs::Simple(Witness(output))],
+                foreign_call_results: Vec::new(),
+                bytecode: Vec::new(),
+                predicate: None,
+            })
+        };
+        let block = |value| {
+            Opcode::Block(MemoryBlock {
+                id: BlockId(0),
+                len: 1,
+                trace: vec![MemOp {
+                    operation: Expression::one(),
+                    index: Expression::zero(),
+                    value: Witness(value).into(),
+                }],
+            })
+        };
+
+        let circuit = Circuit {
+            current_witness_index: 20,
+            opcodes: vec![and_opcode(), invert(5, 9), brillig(9, 12), block(12)],
+            public_parameters: PublicInputs(BTreeSet::from([Witness(2)])),
+            return_values: PublicInputs(BTreeSet::from([Witness(15)])),
+            assert_messages: Vec::new(),
+        };
+
+        let (compacted, remapping) = circuit.compact_witnesses();
+        let expected_remapping: WitnessRemapping =
+            [(1, 1), (2, 2), (3, 3), (5, 4), (9, 5), (12, 6), (15, 7)]
+                .into_iter()
+                .map(|(old, new)| (Witness(old), Witness(new)))
+                .collect();
+        assert_eq!(remapping, expected_remapping);
+        assert_eq!(remapping.to_old(Witness(4)), Some(Witness(5)));
+        assert_eq!(
+            compacted,
+            Circuit {
+                current_witness_index: 7,
+                opcodes: vec![and_opcode(), invert(4, 5), brillig(5, 6), block(6)],
+                public_parameters: PublicInputs(BTreeSet::from([Witness(2)])),
+                return_values: PublicInputs(BTreeSet::from([Witness(7)])),
+                assert_messages: Vec::new(),
+            }
+        );
+    }
+
     #[test]
     fn test_serialize() {
         let circuit = Circuit {
@@ -186,6 +472,7 @@ mod tests {
             ],
             public_parameters: PublicInputs(BTreeSet::from_iter(vec![Witness(2)])),
             return_values: PublicInputs(BTreeSet::from_iter(vec![Witness(2)])),
+            assert_messages: Vec::new(),
         };
 
         let json = serde_json::to_string_pretty(&circuit).unwrap();
@@ -1,9 +1,11 @@
 use super::{
-    brillig::Brillig,
+    brillig::{Brillig, BrilligInputs, BrilligOutputs},
     directives::{Directive, LogInfo, QuotientDirective},
 };
-use crate::native_types::Expression;
+use crate::native_types::{Expression, Witness};
+use brillig_vm::ForeignCallOutput;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 mod black_box_function_call;
 mod block;
@@ -63,94 +65,295 @@ impl Opcode {
             _ => None,
         }
     }
+
+    /// Calls `f` on every witness which is read or written by this opcode.
+    pub fn for_each_witness(&self, f: &mut dyn FnMut(&Witness)) {
+        match self {
+            Opcode::Arithmetic(expr) => expr.for_each_witness(f),
+            Opcode::BlackBoxFuncCall(call) => call.for_each_witness(f),
+            Opcode::Directive(directive) => match directive {
+                Directive::Invert { x, result } => {
+                    f(x);
+                    f(result);
+                }
+                Directive::Quotient(QuotientDirective { a, b, q, r, predicate }) => {
+                    a.for_each_witness(f);
+                    b.for_each_witness(f);
+                    f(q);
+                    f(r);
+                    if let Some(predicate) = predicate {
+                        predicate.for_each_witness(f);
+                    }
+                }
+                Directive::ToLeRadix { a, b, .. } => {
+                    a.for_each_witness(f);
+                    b.iter().for_each(&mut *f);
+                }
+                Directive::PermutationSort { inputs, bits, .. } => {
+                    for expr in inputs.iter().flatten() {
+                        expr.for_each_witness(f);
+                    }
+                    bits.iter().for_each(&mut *f);
+                }
+                Directive::Log(LogInfo::WitnessOutput(witnesses)) => {
+                    witnesses.iter().for_each(&mut *f);
+                }
+                Directive::Log(LogInfo::FinalizedOutput(_)) => (),
+            },
+            Opcode::Block(block) | Opcode::ROM(block) | Opcode::RAM(block) => {
+                for op in &block.trace {
+                    for expr in [&op.operation, &op.index, &op.value] {
+                        expr.for_each_witness(f);
+                    }
+                }
+            }
+            Opcode::Brillig(brillig) => {
+                for input in &brillig.inputs {
+                    match input {
+                        BrilligInputs::Single(expr) => expr.for_each_witness(f),
+                        BrilligInputs::Array(exprs) => {
+                            exprs.iter().for_each(|expr| expr.for_each_witness(f));
+                        }
+                    }
+                }
+                for output in &brillig.outputs {
+                    match output {
+                        BrilligOutputs::Simple(witness) => f(witness),
+                        BrilligOutputs::Array(witnesses) => witnesses.iter().for_each(&mut *f),
+                    }
+                }
+                if let Some(predicate) = &brillig.predicate {
+                    predicate.for_each_witness(f);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` on every witness which is read or written by this opcode.
+    pub fn for_each_witness_mut(&mut self, f: &mut dyn FnMut(&mut Witness)) {
+        match self {
+            Opcode::Arithmetic(expr) => expr.for_each_witness_mut(f),
+            Opcode::BlackBoxFuncCall(call) => call.for_each_witness_mut(f),
+            Opcode::Directive(directive) => match directive {
+                Directive::Invert { x, result } => {
+                    f(x);
+                    f(result);
+                }
+                Directive::Quotient(QuotientDirective { a, b, q, r, predicate }) => {
+                    a.for_each_witness_mut(f);
+                    b.for_each_witness_mut(f);
+                    f(q);
+                    f(r);
+                    if let Some(predicate) = predicate {
+                        predicate.for_each_witness_mut(f);
+                    }
+                }
+                Directive::ToLeRadix { a, b, .. } => {
+                    a.for_each_witness_mut(f);
+                    b.iter_mut().for_each(&mut *f);
+                }
+                Directive::PermutationSort { inputs, bits, .. } => {
+                    for expr in inputs.iter_mut().flatten() {
+                        expr.for_each_witness_mut(f);
+                    }
+                    bits.iter_mut().for_each(&mut *f);
+                }
+                Directive::Log(LogInfo::WitnessOutput(witnesses)) => {
+                    witnesses.iter_mut().for_each(&mut *f);
+                }
+                Directive::Log(LogInfo::FinalizedOutput(_)) => (),
+            },
+            Opcode::Block(block) | Opcode::ROM(block) | Opcode::RAM(block) => {
+                for op in &mut block.trace {
+                    for expr in [&mut op.operation, &mut op.index, &mut op.value] {
+                        expr.for_each_witness_mut(f);
+                    }
+                }
+            }
+            Opcode::Brillig(brillig) => {
+                for input in &mut brillig.inputs {
+                    match input {
+                        BrilligInputs::Single(expr) => expr.for_each_witness_mut(f),
+                        BrilligInputs::Array(exprs) => {
+                            exprs.iter_mut().for_each(|expr| expr.for_each_witness_mut(f));
+                        }
+                    }
+                }
+                for output in &mut brillig.outputs {
+                    match output {
+                        BrilligOutputs::Simple(witness) => f(witness),
+                        BrilligOutputs::Array(witnesses) => witnesses.iter_mut().for_each(&mut *f),
+                    }
+                }
+                if let Some(predicate) = &mut brillig.predicate {
+                    predicate.for_each_witness_mut(f);
+                }
+            }
+        }
+    }
+
+    /// Returns every witness which is read or written by this opcode.
+    pub fn witnesses(&self) -> BTreeSet<Witness> {
+        let mut witnesses = BTreeSet::new();
+        self.for_each_witness(&mut |witness| {
+            witnesses.insert(*witness);
+        });
+        witnesses
+    }
 }
 
+/// Writes `expr` as `[ (q_M, _wL, _wR)... (q_L, _w)... q_c ]`.
+fn write_expression(f: &mut std::fmt::Formatter<'_>, expr: &Expression) -> std::fmt::Result {
+    write!(f, "[ ")?;
+    for i in &expr.mul_terms {
+        write!(f, "({}, _{}, _{}) ", i.0, i.1.witness_index(), i.2.witness_index())?;
+    }
+    for i in &expr.linear_combinations {
+        write!(f, "({}, _{}) ", i.0, i.1.witness_index())?;
+    }
+    write!(f, "{} ]", expr.q_c)
+}
+
+/// Writes `items` as a comma separated list surrounded by square brackets.
+fn write_list<T>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: &[T],
+    mut write_item: impl FnMut(&mut std::fmt::Formatter<'_>, &T) -> std::fmt::Result,
+) -> std::fmt::Result {
+    write!(f, "[")?;
+    for (index, item) in items.iter().enumerate() {
+        if index != 0 {
+            write!(f, ", ")?;
+        }
+        write_item(f, item)?;
+    }
+    write!(f, "]")
+}
+
+fn write_witnesses(f: &mut std::fmt::Formatter<'_>, witnesses: &[Witness]) -> std::fmt::Result {
+    write_list(f, witnesses, |f, witness| write!(f, "_{}", witness.witness_index()))
+}
+
+fn write_memory_block(
+    f: &mut std::fmt::Formatter<'_>,
+    name: &str,
+    block: &MemoryBlock,
+) -> std::fmt::Result {
+    write!(f, "{name} id: {} len: {}", block.id.0, block.len)?;
+    for op in &block.trace {
+        write!(f, "\n  operation: ")?;
+        write_expression(f, &op.operation)?;
+        write!(f, " index: ")?;
+        write_expression(f, &op.index)?;
+        write!(f, " value: ")?;
+        write_expression(f, &op.value)?;
+    }
+    Ok(())
+}
+
+/// Writes the opcode in the textual format which can be parsed back using its `FromStr` implementation.
+///
+/// Brillig opcodes and memory blocks span multiple lines, with each Brillig instruction or
+/// memory operation on its own indented line.
 impl std::fmt::Display for Opcode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Opcode::Arithmetic(expr) => {
-                write!(f, "EXPR [ ")?;
-                for i in &expr.mul_terms {
-                    write!(f, "({}, _{}, _{}) ", i.0, i.1.witness_index(), i.2.witness_index())?;
-                }
-                for i in &expr.linear_combinations {
-                    write!(f, "({}, _{}) ", i.0, i.1.witness_index())?;
-                }
-                write!(f, "{}", expr.q_c)?;
-
-                write!(f, " ]")
+                write!(f, "EXPR ")?;
+                write_expression(f, expr)
             }
             Opcode::Directive(Directive::Invert { x, result: r }) => {
                 write!(f, "DIR::INVERT ")?;
-                write!(f, "(_{}, out: _{}) ", x.witness_index(), r.witness_index())
+                write!(f, "(_{}, out: _{})", x.witness_index(), r.witness_index())
             }
             Opcode::Directive(Directive::Quotient(QuotientDirective { a, b, q, r, predicate })) => {
-                write!(f, "DIR::QUOTIENT ")?;
+                write!(f, "DIR::QUOTIENT a: ")?;
+                write_expression(f, a)?;
+                write!(f, " b: ")?;
+                write_expression(f, b)?;
+                write!(f, " q: _{} r: _{}", q.witness_index(), r.witness_index())?;
                 if let Some(pred) = predicate {
-                    writeln!(f, "PREDICATE = {pred}")?;
+                    write!(f, " predicate: ")?;
+                    write_expression(f, pred)?;
                 }
-
-                write!(
-                    f,
-                    "(out : _{},  (_{}, {}), _{})",
-                    a,
-                    q.witness_index(),
-                    b,
-                    r.witness_index()
-                )
+                Ok(())
             }
             Opcode::BlackBoxFuncCall(g) => write!(f, "{g}"),
-            Opcode::Directive(Directive::ToLeRadix { a, b, radix: _ }) => {
-                write!(f, "DIR::TORADIX ")?;
-                write!(
-                    f,
-                    // TODO (Note): this assumes that the decomposed bits have contiguous witness indices
-                    // This should be the case, however, we can also have a function which checks this
-                    "(_{}, [_{}..._{}] )",
-                    a,
-                    b.first().unwrap().witness_index(),
-                    b.last().unwrap().witness_index(),
-                )
+            Opcode::Directive(Directive::ToLeRadix { a, b, radix }) => {
+                write!(f, "DIR::TORADIX a: ")?;
+                write_expression(f, a)?;
+                write!(f, " b: ")?;
+                write_witnesses(f, b)?;
+                write!(f, " radix: {radix}")
             }
-            Opcode::Directive(Directive::PermutationSort { inputs: a, tuple, bits, sort_by }) => {
-                write!(f, "DIR::PERMUTATIONSORT ")?;
-                write!(
-                    f,
-                    "(permutation size: {} {}-tuples, sort_by: {:#?}, bits: [_{}..._{}]))",
-                    a.len(),
-                    tuple,
-                    sort_by,
-                    // (Note): the bits do not have contiguous index but there are too many for display
-                    bits.first().unwrap().witness_index(),
-                    bits.last().unwrap().witness_index(),
-                )
+            Opcode::Directive(Directive::PermutationSort { inputs, tuple, bits, sort_by }) => {
+                write!(f, "DIR::PERMUTATIONSORT inputs: ")?;
+                write_list(f, inputs, |f, tuple| write_list(f, tuple, write_expression))?;
+                write!(f, " tuple: {tuple} bits: ")?;
+                write_witnesses(f, bits)?;
+                write!(f, " sort_by: ")?;
+                write_list(f, sort_by, |f, index| write!(f, "{index}"))
             }
             Opcode::Directive(Directive::Log(info)) => match info {
-                LogInfo::FinalizedOutput(output_string) => write!(f, "Log: {output_string}"),
-                LogInfo::WitnessOutput(witnesses) => write!(
-                    f,
-                    "Log: _{}..._{}",
-                    witnesses.first().unwrap().witness_index(),
-                    witnesses.last().unwrap().witness_index()
-                ),
+                LogInfo::FinalizedOutput(output_string) => write!(f, "DIR::LOG {output_string:?}"),
+                LogInfo::WitnessOutput(witnesses) => {
+                    write!(f, "DIR::LOG ")?;
+                    write_witnesses(f, witnesses)
+                }
             },
-            Opcode::Block(block) => {
-                write!(f, "BLOCK ")?;
-                write!(f, "(id: {}, len: {}) ", block.id.0, block.trace.len())
-            }
-            Opcode::ROM(block) => {
-                write!(f, "ROM ")?;
-                write!(f, "(id: {}, len: {}) ", block.id.0, block.trace.len())
-            }
-            Opcode::RAM(block) => {
-                write!(f, "RAM ")?;
-                write!(f, "(id: {}, len: {}) ", block.id.0, block.trace.len())
-            }
+            Opcode::Block(block) => write_memory_block(f, "BLOCK", block),
+            Opcode::ROM(block) => write_memory_block(f, "ROM", block),
+            Opcode::RAM(block) => write_memory_block(f, "RAM", block),
             Opcode::Brillig(brillig) => {
-                write!(f, "BRILLIG: ")?;
-                writeln!(f, "inputs: {:?}", brillig.inputs)?;
-                writeln!(f, "outputs: {:?}", brillig.outputs)?;
-                writeln!(f, "{:?}", brillig.bytecode)
+                write!(f, "BRILLIG inputs: ")?;
+                write_list(f, &brillig.inputs, |f, input| match input {
+                    BrilligInputs::Single(expr) => {
+                        write!(f, "Single(")?;
+                        write_expression(f, expr)?;
+                        write!(f, ")")
+                    }
+                    BrilligInputs::Array(exprs) => {
+                        write!(f, "Array(")?;
+                        write_list(f, exprs, write_expression)?;
+                        write!(f, ")")
+                    }
+                })?;
+                write!(f, " outputs: ")?;
+                write_list(f, &brillig.outputs, |f, output| match output {
+                    BrilligOutputs::Simple(witness) => {
+                        write!(f, "Simple(_{})", witness.witness_index())
+                    }
+                    BrilligOutputs::Array(witnesses) => {
+                        write!(f, "Array(")?;
+                        write_witnesses(f, witnesses)?;
+                        write!(f, ")")
+                    }
+                })?;
+                write!(f, " foreign_call_results: ")?;
+                write_list(f, &brillig.foreign_call_results, |f, result| {
+                    write_list(f, &result.values, |f, output| match output {
+                        ForeignCallOutput::Single(value) => {
+                            write!(f, "Single({})", value.to_field())
+                        }
+                        ForeignCallOutput::Array(values) => {
+                            write!(f, "Array(")?;
+                            write_list(f, values, |f, value| write!(f, "{}", value.to_field()))?;
+                            write!(f, ")")
+                        }
+                    })?;
+                    match &result.error {
+                        Some(message) => write!(f, " Error({message:?})"),
+                        None => Ok(()),
+                    }
+                })?;
+                if let Some(predicate) = &brillig.predicate {
+                    write!(f, " predicate: ")?;
+                    write_expression(f, predicate)?;
+                }
+                for opcode in &brillig.bytecode {
+                    write!(f, "\n  {opcode}")?;
+                }
+                Ok(())
             }
         }
     }
@@ -288,6 +288,190 @@ impl BlackBoxFuncCall {
         }
     }
 
+    /// Calls `f` on every witness which is read or written by this call.
+    pub fn for_each_witness(&self, f: &mut dyn FnMut(&Witness)) {
+        let visit_inputs = |inputs: &[FunctionInput], f: &mut dyn FnMut(&Witness)| {
+            inputs.iter().for_each(|input| f(&input.witness));
+        };
+
+        match self {
+            BlackBoxFuncCall::AND { lhs, rhs, output }
+            | BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+                f(&lhs.witness);
+                f(&rhs.witness);
+                f(output);
+            }
+            BlackBoxFuncCall::RANGE { input } => f(&input.witness),
+            BlackBoxFuncCall::SHA256 { inputs, outputs }
+            | BlackBoxFuncCall::Blake2s { inputs, outputs }
+            | BlackBoxFuncCall::Keccak256 { inputs, outputs } => {
+                visit_inputs(inputs, f);
+                outputs.iter().for_each(&mut *f);
+            }
+            BlackBoxFuncCall::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                signature,
+                message,
+                output,
+            } => {
+                f(&public_key_x.witness);
+                f(&public_key_y.witness);
+                visit_inputs(signature, f);
+                visit_inputs(message, f);
+                f(output);
+            }
+            BlackBoxFuncCall::Pedersen { inputs, outputs, .. } => {
+                visit_inputs(inputs, f);
+                f(&outputs.0);
+                f(&outputs.1);
+            }
+            BlackBoxFuncCall::HashToField128Security { inputs, output } => {
+                visit_inputs(inputs, f);
+                f(output);
+            }
+            BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            }
+            | BlackBoxFuncCall::EcdsaSecp256r1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            } => {
+                visit_inputs(public_key_x, f);
+                visit_inputs(public_key_y, f);
+                visit_inputs(signature, f);
+                visit_inputs(hashed_message, f);
+                f(output);
+            }
+            BlackBoxFuncCall::FixedBaseScalarMul { input, outputs } => {
+                f(&input.witness);
+                f(&outputs.0);
+                f(&outputs.1);
+            }
+            BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs } => {
+                visit_inputs(inputs, f);
+                f(&var_message_size.witness);
+                outputs.iter().for_each(&mut *f);
+            }
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            } => {
+                visit_inputs(verification_key, f);
+                visit_inputs(proof, f);
+                visit_inputs(public_inputs, f);
+                f(&key_hash.witness);
+                if let Some(input_aggregation_object) = input_aggregation_object {
+                    visit_inputs(input_aggregation_object, f);
+                }
+                output_aggregation_object.iter().for_each(&mut *f);
+            }
+        }
+    }
+
+    /// Calls `f` on every witness which is read or written by this call.
+    pub fn for_each_witness_mut(&mut self, f: &mut dyn FnMut(&mut Witness)) {
+        let visit_inputs = |inputs: &mut [FunctionInput], f: &mut dyn FnMut(&mut Witness)| {
+            inputs.iter_mut().for_each(|input| f(&mut input.witness));
+        };
+
+        match self {
+            BlackBoxFuncCall::AND { lhs, rhs, output }
+            | BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+                f(&mut lhs.witness);
+                f(&mut rhs.witness);
+                f(output);
+            }
+            BlackBoxFuncCall::RANGE { input } => f(&mut input.witness),
+            BlackBoxFuncCall::SHA256 { inputs, outputs }
+            | BlackBoxFuncCall::Blake2s { inputs, outputs }
+            | BlackBoxFuncCall::Keccak256 { inputs, outputs } => {
+                visit_inputs(inputs, f);
+                outputs.iter_mut().for_each(&mut *f);
+            }
+            BlackBoxFuncCall::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                signature,
+                message,
+                output,
+            } => {
+                f(&mut public_key_x.witness);
+                f(&mut public_key_y.witness);
+                visit_inputs(signature, f);
+                visit_inputs(message, f);
+                f(output);
+            }
+            BlackBoxFuncCall::Pedersen { inputs, outputs, .. } => {
+                visit_inputs(inputs, f);
+                f(&mut outputs.0);
+                f(&mut outputs.1);
+            }
+            BlackBoxFuncCall::HashToField128Security { inputs, output } => {
+                visit_inputs(inputs, f);
+                f(output);
+            }
+            BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            }
+            | BlackBoxFuncCall::EcdsaSecp256r1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            } => {
+                visit_inputs(public_key_x, f);
+                visit_inputs(public_key_y, f);
+                visit_inputs(signature, f);
+                visit_inputs(hashed_message, f);
+                f(output);
+            }
+            BlackBoxFuncCall::FixedBaseScalarMul { input, outputs } => {
+                f(&mut input.witness);
+                f(&mut outputs.0);
+                f(&mut outputs.1);
+            }
+            BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs } => {
+                visit_inputs(inputs, f);
+                f(&mut var_message_size.witness);
+                outputs.iter_mut().for_each(&mut *f);
+            }
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            } => {
+                visit_inputs(verification_key, f);
+                visit_inputs(proof, f);
+                visit_inputs(public_inputs, f);
+                f(&mut key_hash.witness);
+                if let Some(input_aggregation_object) = input_aggregation_object {
+                    visit_inputs(input_aggregation_object, f);
+                }
+                output_aggregation_object.iter_mut().for_each(&mut *f);
+            }
+        }
+    }
+
     pub fn get_outputs_vec(&self) -> Vec<Witness> {
         match self {
             BlackBoxFuncCall::SHA256 { outputs, .. }
@@ -310,92 +494,158 @@ impl BlackBoxFuncCall {
     }
 }
 
-const ABBREVIATION_LIMIT: usize = 5;
-
-fn get_inputs_string(inputs: &[FunctionInput]) -> String {
-    // Once a vectors length gets above this limit,
-    // instead of listing all of their elements, we use ellipses
-    // to abbreviate them
-    let should_abbreviate_inputs = inputs.len() <= ABBREVIATION_LIMIT;
+impl std::fmt::Display for FunctionInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(_{}, num_bits: {})", self.witness.witness_index(), self.num_bits)
+    }
+}
 
-    if should_abbreviate_inputs {
-        let mut result = String::new();
-        for (index, inp) in inputs.iter().enumerate() {
-            result += &format!("(_{}, num_bits: {})", inp.witness.witness_index(), inp.num_bits);
-            // Add a comma, unless it is the last entry
-            if index != inputs.len() - 1 {
-                result += ", "
-            }
+fn write_inputs(f: &mut std::fmt::Formatter<'_>, inputs: &[FunctionInput]) -> std::fmt::Result {
+    write!(f, "[")?;
+    for (index, input) in inputs.iter().enumerate() {
+        if index != 0 {
+            write!(f, ", ")?;
         }
-        result
-    } else {
-        let first = inputs.first().unwrap();
-        let last = inputs.last().unwrap();
-
-        let mut result = String::new();
-
-        result += &format!(
-            "(_{}, num_bits: {})...(_{}, num_bits: {})",
-            first.witness.witness_index(),
-            first.num_bits,
-            last.witness.witness_index(),
-            last.num_bits,
-        );
-
-        result
+        write!(f, "{input}")?;
     }
+    write!(f, "]")
 }
 
-fn get_outputs_string(outputs: &[Witness]) -> String {
-    let should_abbreviate_outputs = outputs.len() <= ABBREVIATION_LIMIT;
+fn write_outputs(f: &mut std::fmt::Formatter<'_>, outputs: &[Witness]) -> std::fmt::Result {
+    write!(f, "[")?;
+    for (index, output) in outputs.iter().enumerate() {
+        if index != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "_{}", output.witness_index())?;
+    }
+    write!(f, "]")
+}
 
-    if should_abbreviate_outputs {
-        let mut result = String::new();
-        for (index, output) in outputs.iter().enumerate() {
-            result += &format!("_{}", output.witness_index());
-            // Add a comma, unless it is the last entry
-            if index != outputs.len() - 1 {
-                result += ", "
+impl BlackBoxFuncCall {
+    /// Name of the call in the textual format.
+    ///
+    /// This differs from [`BlackBoxFuncCall::name`] in that it distinguishes between
+    /// `Keccak256` and `Keccak256VariableLength`.
+    pub(crate) fn text_name(&self) -> String {
+        match self {
+            BlackBoxFuncCall::Keccak256VariableLength { .. } => {
+                "KECCAK256_VARIABLE_LENGTH".to_owned()
             }
+            _ => self.name().to_uppercase(),
         }
-        result
-    } else {
-        let first = outputs.first().unwrap();
-        let last = outputs.last().unwrap();
-
-        let mut result = String::new();
-        result += &format!("(_{},...,_{})", first.witness_index(), last.witness_index());
-        result
     }
 }
 
+/// Writes the call with each of its fields named, e.g.
+/// `BLACKBOX::AND lhs: (_1, num_bits: 8) rhs: (_2, num_bits: 8) output: _3`.
 impl std::fmt::Display for BlackBoxFuncCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let uppercase_name = self.name().to_uppercase();
-        write!(f, "BLACKBOX::{uppercase_name} ")?;
-        // INPUTS
-        write!(f, "[")?;
-
-        let inputs_str = get_inputs_string(&self.get_inputs_vec());
-
-        write!(f, "{inputs_str}")?;
-        write!(f, "] ")?;
-
-        // OUTPUTS
-        write!(f, "[ ")?;
-
-        let outputs_str = get_outputs_string(&self.get_outputs_vec());
-
-        write!(f, "{outputs_str}")?;
-
-        write!(f, "]")?;
-
-        // SPECIFIC PARAMETERS
+        write!(f, "BLACKBOX::{}", self.text_name())?;
         match self {
-            BlackBoxFuncCall::Pedersen { domain_separator, .. } => {
-                write!(f, " domain_separator: {domain_separator}")
+            BlackBoxFuncCall::AND { lhs, rhs, output }
+            | BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+                write!(f, " lhs: {lhs} rhs: {rhs} output: _{}", output.witness_index())
+            }
+            BlackBoxFuncCall::RANGE { input } => write!(f, " input: {input}"),
+            BlackBoxFuncCall::SHA256 { inputs, outputs }
+            | BlackBoxFuncCall::Blake2s { inputs, outputs }
+            | BlackBoxFuncCall::Keccak256 { inputs, outputs } => {
+                write!(f, " inputs: ")?;
+                write_inputs(f, inputs)?;
+                write!(f, " outputs: ")?;
+                write_outputs(f, outputs)
+            }
+            BlackBoxFuncCall::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                signature,
+                message,
+                output,
+            } => {
+                write!(
+                    f,
+                    " public_key_x: {public_key_x} public_key_y: {public_key_y} signature: "
+                )?;
+                write_inputs(f, signature)?;
+                write!(f, " message: ")?;
+                write_inputs(f, message)?;
+                write!(f, " output: _{}", output.witness_index())
+            }
+            BlackBoxFuncCall::Pedersen { inputs, domain_separator, outputs } => {
+                write!(f, " inputs: ")?;
+                write_inputs(f, inputs)?;
+                write!(
+                    f,
+                    " domain_separator: {domain_separator} outputs: (_{}, _{})",
+                    outputs.0.witness_index(),
+                    outputs.1.witness_index()
+                )
+            }
+            BlackBoxFuncCall::HashToField128Security { inputs, output } => {
+                write!(f, " inputs: ")?;
+                write_inputs(f, inputs)?;
+                write!(f, " output: _{}", output.witness_index())
+            }
+            BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            }
+            | BlackBoxFuncCall::EcdsaSecp256r1 {
+                public_key_x,
+                public_key_y,
+                signature,
+                hashed_message,
+                output,
+            } => {
+                write!(f, " public_key_x: ")?;
+                write_inputs(f, public_key_x)?;
+                write!(f, " public_key_y: ")?;
+                write_inputs(f, public_key_y)?;
+                write!(f, " signature: ")?;
+                write_inputs(f, signature)?;
+                write!(f, " hashed_message: ")?;
+                write_inputs(f, hashed_message)?;
+                write!(f, " output: _{}", output.witness_index())
+            }
+            BlackBoxFuncCall::FixedBaseScalarMul { input, outputs } => write!(
+                f,
+                " input: {input} outputs: (_{}, _{})",
+                outputs.0.witness_index(),
+                outputs.1.witness_index()
+            ),
+            BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs } => {
+                write!(f, " inputs: ")?;
+                write_inputs(f, inputs)?;
+                write!(f, " var_message_size: {var_message_size} outputs: ")?;
+                write_outputs(f, outputs)
+            }
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            } => {
+                write!(f, " verification_key: ")?;
+                write_inputs(f, verification_key)?;
+                write!(f, " proof: ")?;
+                write_inputs(f, proof)?;
+                write!(f, " public_inputs: ")?;
+                write_inputs(f, public_inputs)?;
+                write!(f, " key_hash: {key_hash}")?;
+                // The input aggregation object is omitted entirely when there is none.
+                if let Some(input_aggregation_object) = input_aggregation_object {
+                    write!(f, " input_aggregation_object: ")?;
+                    write_inputs(f, input_aggregation_object)?;
+                }
+                write!(f, " output_aggregation_object: ")?;
+                write_outputs(f, output_aggregation_object)
             }
-            _ => write!(f, ""),
         }
     }
 }
@@ -0,0 +1,938 @@
+//! Parser for the textual format of ACIR produced by the `Display` implementations of
+//! [`Circuit`] and [`Opcode`].
+//!
+//! Every opcode starts on a new line. Opcodes which contain a list of items, i.e. the bytecode of
+//! a Brillig opcode or the trace of a memory block, place each item on a following indented line.
+//! Blank lines and lines starting with `//` are ignored, which allows circuits to be written by hand.
+//!
+//! ```text
+//! current witness index : 3
+//! public parameters indices : [1]
+//! return value indices : [3]
+//! assert messages : [(1, "out of range"), (2.0, "input too large")]
+//! // w3 = w1 * w2
+//! EXPR [ (1, _1, _2) (-1, _3) 0 ]
+//! BLACKBOX::RANGE input: (_3, num_bits: 32)
+//! BRILLIG inputs: [Single([ (1, _1) 0 ])] outputs: [Simple(_2)] foreign_call_results: []
+//!   fadd r0, r0, r0
+//!   stop
+//! ```
+
+use std::{collections::BTreeSet, str::FromStr};
+
+use acir_field::FieldElement;
+use brillig_vm::{tokenize, ForeignCallOutput, ForeignCallResult, Token};
+use thiserror::Error;
+
+use super::{
+    brillig::{Brillig, BrilligInputs, BrilligOutputs},
+    directives::{Directive, LogInfo, QuotientDirective},
+    opcodes::{BlackBoxFuncCall, BlockId, FunctionInput, MemOp, MemoryBlock},
+    Circuit, Opcode, OpcodeLocation, PublicInputs,
+};
+use crate::native_types::{Expression, Witness};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}: {message}")]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl ParseError {
+    /// Line of the input on which the error occurred, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// The punctuation of ACIR opcodes, whose words and strings are tokenized in the same way as the
+/// Brillig instructions which they contain.
+const PUNCTUATION: &[char] = &[',', ':', '(', ')', '[', ']'];
+
+/// Parses the tokens of a single line.
+struct LineParser {
+    tokens: std::vec::IntoIter<Token>,
+}
+
+impl LineParser {
+    fn new(line: &str) -> Result<Self, String> {
+        Ok(LineParser { tokens: tokenize(line, PUNCTUATION)?.into_iter() })
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        self.tokens.next().ok_or_else(|| "unexpected end of line".to_owned())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.as_slice().first()
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.peek() == Some(token);
+        if matches {
+            self.tokens.next();
+        }
+        matches
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected {expected} but found {token}")),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match self.tokens.next() {
+            Some(token) => Err(format!("unexpected trailing {token}")),
+            None => Ok(()),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            token => Err(format!("expected a word but found {token}")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), String> {
+        self.expect(Token::Word(keyword.to_owned()))
+    }
+
+    /// Consumes `name:`, which precedes the value of each named field.
+    fn field_name(&mut self, name: &str) -> Result<(), String> {
+        self.keyword(name)?;
+        self.expect(Token::Colon)
+    }
+
+    /// Consumes `name:` if it is the next token, used for optional fields.
+    fn optional_field_name(&mut self, name: &str) -> Result<bool, String> {
+        if self.peek() != Some(&Token::Word(name.to_owned())) {
+            return Ok(false);
+        }
+        self.field_name(name)?;
+        Ok(true)
+    }
+
+    fn integer<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        word.parse().map_err(|_| format!("expected an integer but found `{word}`"))
+    }
+
+    fn field_element(&mut self) -> Result<FieldElement, String> {
+        let word = self.word()?;
+        FieldElement::try_from_display_str(&word)
+            .ok_or_else(|| format!("expected a field element but found `{word}`"))
+    }
+
+    fn witness(&mut self) -> Result<Witness, String> {
+        let word = self.word()?;
+        word.strip_prefix('_')
+            .and_then(|index| index.parse().ok())
+            .map(Witness)
+            .ok_or_else(|| format!("expected a witness but found `{word}`"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::String(string) => Ok(string),
+            token => Err(format!("expected a string but found {token}")),
+        }
+    }
+
+    /// Parses a comma separated list surrounded by square brackets.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        self.expect(Token::OpenBracket)?;
+        let mut items = Vec::new();
+        if self.eat(&Token::CloseBracket) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(&Token::CloseBracket) {
+                return Ok(items);
+            }
+            self.expect(Token::Comma)?;
+        }
+    }
+
+    /// Parses a value wrapped in a named constructor, e.g. `Single(...)`.
+    fn wrapped<T>(
+        &mut self,
+        value: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.expect(Token::OpenParen)?;
+        let value = value(self)?;
+        self.expect(Token::CloseParen)?;
+        Ok(value)
+    }
+
+    /// Parses `[ (q_M, _wL, _wR)... (q_L, _w)... q_c ]`.
+    fn expression(&mut self) -> Result<Expression, String> {
+        self.expect(Token::OpenBracket)?;
+        let mut expr = Expression::default();
+        while self.eat(&Token::OpenParen) {
+            let coefficient = self.field_element()?;
+            self.expect(Token::Comma)?;
+            let witness = self.witness()?;
+            if self.eat(&Token::Comma) {
+                let rhs = self.witness()?;
+                expr.push_multiplication_term(coefficient, witness, rhs);
+            } else {
+                expr.push_addition_term(coefficient, witness);
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        expr.q_c = self.field_element()?;
+        self.expect(Token::CloseBracket)?;
+        Ok(expr)
+    }
+
+    fn witnesses(&mut self) -> Result<Vec<Witness>, String> {
+        self.list(Self::witness)
+    }
+
+    fn witness_pair(&mut self) -> Result<(Witness, Witness), String> {
+        self.expect(Token::OpenParen)?;
+        let first = self.witness()?;
+        self.expect(Token::Comma)?;
+        let second = self.witness()?;
+        self.expect(Token::CloseParen)?;
+        Ok((first, second))
+    }
+
+    /// Parses `(_w, num_bits: n)`.
+    fn function_input(&mut self) -> Result<FunctionInput, String> {
+        self.expect(Token::OpenParen)?;
+        let witness = self.witness()?;
+        self.expect(Token::Comma)?;
+        self.field_name("num_bits")?;
+        let num_bits = self.integer()?;
+        self.expect(Token::CloseParen)?;
+        Ok(FunctionInput { witness, num_bits })
+    }
+
+    fn function_inputs(&mut self) -> Result<Vec<FunctionInput>, String> {
+        self.list(Self::function_input)
+    }
+
+    fn named_witness(&mut self, name: &str) -> Result<Witness, String> {
+        self.field_name(name)?;
+        self.witness()
+    }
+
+    fn named_witnesses(&mut self, name: &str) -> Result<Vec<Witness>, String> {
+        self.field_name(name)?;
+        self.witnesses()
+    }
+
+    fn named_input(&mut self, name: &str) -> Result<FunctionInput, String> {
+        self.field_name(name)?;
+        self.function_input()
+    }
+
+    fn named_inputs(&mut self, name: &str) -> Result<Vec<FunctionInput>, String> {
+        self.field_name(name)?;
+        self.function_inputs()
+    }
+
+    fn named_expression(&mut self, name: &str) -> Result<Expression, String> {
+        self.field_name(name)?;
+        self.expression()
+    }
+
+    fn black_box_func_call(&mut self, name: &str) -> Result<BlackBoxFuncCall, String> {
+        let call = match name {
+            "AND" | "XOR" => {
+                let lhs = self.named_input("lhs")?;
+                let rhs = self.named_input("rhs")?;
+                let output = self.named_witness("output")?;
+                if name == "AND" {
+                    BlackBoxFuncCall::AND { lhs, rhs, output }
+                } else {
+                    BlackBoxFuncCall::XOR { lhs, rhs, output }
+                }
+            }
+            "RANGE" => BlackBoxFuncCall::RANGE { input: self.named_input("input")? },
+            "SHA256" | "BLAKE2S" | "KECCAK256" => {
+                let inputs = self.named_inputs("inputs")?;
+                let outputs = self.named_witnesses("outputs")?;
+                match name {
+                    "SHA256" => BlackBoxFuncCall::SHA256 { inputs, outputs },
+                    "BLAKE2S" => BlackBoxFuncCall::Blake2s { inputs, outputs },
+                    _ => BlackBoxFuncCall::Keccak256 { inputs, outputs },
+                }
+            }
+            "SCHNORR_VERIFY" => BlackBoxFuncCall::SchnorrVerify {
+                public_key_x: self.named_input("public_key_x")?,
+                public_key_y: self.named_input("public_key_y")?,
+                signature: self.named_inputs("signature")?,
+                message: self.named_inputs("message")?,
+                output: self.named_witness("output")?,
+            },
+            "PEDERSEN" => {
+                let inputs = self.named_inputs("inputs")?;
+                self.field_name("domain_separator")?;
+                let domain_separator = self.integer()?;
+                self.field_name("outputs")?;
+                BlackBoxFuncCall::Pedersen {
+                    inputs,
+                    domain_separator,
+                    outputs: self.witness_pair()?,
+                }
+            }
+            "HASH_TO_FIELD_128_SECURITY" => BlackBoxFuncCall::HashToField128Security {
+                inputs: self.named_inputs("inputs")?,
+                output: self.named_witness("output")?,
+            },
+            "ECDSA_SECP256K1" | "ECDSA_SECP256R1" => {
+                let public_key_x = self.named_inputs("public_key_x")?;
+                let public_key_y = self.named_inputs("public_key_y")?;
+                let signature = self.named_inputs("signature")?;
+                let hashed_message = self.named_inputs("hashed_message")?;
+                let output = self.named_witness("output")?;
+                if name == "ECDSA_SECP256K1" {
+                    BlackBoxFuncCall::EcdsaSecp256k1 {
+                        public_key_x,
+                        public_key_y,
+                        signature,
+                        hashed_message,
+                        output,
+                    }
+                } else {
+                    BlackBoxFuncCall::EcdsaSecp256r1 {
+                        public_key_x,
+                        public_key_y,
+                        signature,
+                        hashed_message,
+                        output,
+                    }
+                }
+            }
+            "FIXED_BASE_SCALAR_MUL" => {
+                let input = self.named_input("input")?;
+                self.field_name("outputs")?;
+                BlackBoxFuncCall::FixedBaseScalarMul { input, outputs: self.witness_pair()? }
+            }
+            "KECCAK256_VARIABLE_LENGTH" => BlackBoxFuncCall::Keccak256VariableLength {
+                inputs: self.named_inputs("inputs")?,
+                var_message_size: self.named_input("var_message_size")?,
+                outputs: self.named_witnesses("outputs")?,
+            },
+            "RECURSIVE_AGGREGATION" => BlackBoxFuncCall::RecursiveAggregation {
+                verification_key: self.named_inputs("verification_key")?,
+                proof: self.named_inputs("proof")?,
+                public_inputs: self.named_inputs("public_inputs")?,
+                key_hash: self.named_input("key_hash")?,
+                input_aggregation_object: if self.optional_field_name("input_aggregation_object")? {
+                    Some(self.function_inputs()?)
+                } else {
+                    None
+                },
+                output_aggregation_object: self.named_witnesses("output_aggregation_object")?,
+            },
+            _ => return Err(format!("unknown black box function `{name}`")),
+        };
+        Ok(call)
+    }
+
+    fn directive(&mut self, name: &str) -> Result<Directive, String> {
+        let directive = match name {
+            "INVERT" => {
+                self.expect(Token::OpenParen)?;
+                let x = self.witness()?;
+                self.expect(Token::Comma)?;
+                let result = self.named_witness("out")?;
+                self.expect(Token::CloseParen)?;
+                Directive::Invert { x, result }
+            }
+            "QUOTIENT" => Directive::Quotient(QuotientDirective {
+                a: self.named_expression("a")?,
+                b: self.named_expression("b")?,
+                q: self.named_witness("q")?,
+                r: self.named_witness("r")?,
+                predicate: if self.optional_field_name("predicate")? {
+                    Some(self.expression()?)
+                } else {
+                    None
+                },
+            }),
+            "TORADIX" => {
+                let a = self.named_expression("a")?;
+                let b = self.named_witnesses("b")?;
+                self.field_name("radix")?;
+                Directive::ToLeRadix { a, b, radix: self.integer()? }
+            }
+            "PERMUTATIONSORT" => {
+                self.field_name("inputs")?;
+                let inputs = self.list(|parser| parser.list(Self::expression))?;
+                self.field_name("tuple")?;
+                let tuple = self.integer()?;
+                let bits = self.named_witnesses("bits")?;
+                self.field_name("sort_by")?;
+                let sort_by = self.list(Self::integer)?;
+                Directive::PermutationSort { inputs, tuple, bits, sort_by }
+            }
+            "LOG" => match self.peek() {
+                Some(Token::String(_)) => Directive::Log(LogInfo::FinalizedOutput(self.string()?)),
+                _ => Directive::Log(LogInfo::WitnessOutput(self.witnesses()?)),
+            },
+            _ => return Err(format!("unknown directive `{name}`")),
+        };
+        Ok(directive)
+    }
+
+    fn brillig_input(&mut self) -> Result<BrilligInputs, String> {
+        match self.word()?.as_str() {
+            "Single" => self.wrapped(Self::expression).map(BrilligInputs::Single),
+            "Array" => {
+                self.wrapped(|parser| parser.list(Self::expression)).map(BrilligInputs::Array)
+            }
+            word => Err(format!("expected a brillig input but found `{word}`")),
+        }
+    }
+
+    fn brillig_output(&mut self) -> Result<BrilligOutputs, String> {
+        match self.word()?.as_str() {
+            "Simple" => self.wrapped(Self::witness).map(BrilligOutputs::Simple),
+            "Array" => self.wrapped(Self::witnesses).map(BrilligOutputs::Array),
+            word => Err(format!("expected a brillig output but found `{word}`")),
+        }
+    }
+
+    fn foreign_call_output(&mut self) -> Result<ForeignCallOutput, String> {
+        match self.word()?.as_str() {
+            "Single" => self
+                .wrapped(Self::field_element)
+                .map(|value| ForeignCallOutput::Single(value.into())),
+            "Array" => self.wrapped(|parser| parser.list(Self::field_element)).map(|values| {
+                ForeignCallOutput::Array(values.into_iter().map(Into::into).collect())
+            }),
+            word => Err(format!("expected a foreign call output but found `{word}`")),
+        }
+    }
+
+    /// Parses the first line of a Brillig opcode, returning it without any bytecode.
+    fn brillig_header(&mut self) -> Result<Brillig, String> {
+        self.field_name("inputs")?;
+        let inputs = self.list(Self::brillig_input)?;
+        self.field_name("outputs")?;
+        let outputs = self.list(Self::brillig_output)?;
+        self.field_name("foreign_call_results")?;
+        let foreign_call_results = self.list(|parser| {
+            let values = parser.list(Self::foreign_call_output)?;
+            let error = if parser.peek() == Some(&Token::Word("Error".to_owned())) {
+                parser.keyword("Error")?;
+                Some(parser.wrapped(Self::string)?)
+            } else {
+                None
+            };
+            Ok(ForeignCallResult { values, error })
+        })?;
+        let predicate =
+            if self.optional_field_name("predicate")? { Some(self.expression()?) } else { None };
+        Ok(Brillig { inputs, outputs, foreign_call_results, bytecode: Vec::new(), predicate })
+    }
+
+    /// Parses the first line of a memory block, returning it without any trace.
+    fn memory_block_header(&mut self) -> Result<MemoryBlock, String> {
+        self.field_name("id")?;
+        let id = BlockId(self.integer()?);
+        self.field_name("len")?;
+        let len = self.integer()?;
+        Ok(MemoryBlock { id, len, trace: Vec::new() })
+    }
+
+    /// Parses `acir_index` or `acir_index.brillig_index`.
+    fn opcode_location(&mut self) -> Result<OpcodeLocation, String> {
+        let word = self.word()?;
+        let parse_index = |index: &str| index.parse::<usize>().ok();
+        match word.split_once('.') {
+            None => parse_index(&word).map(OpcodeLocation::Acir),
+            Some((acir_index, brillig_index)) => parse_index(acir_index)
+                .zip(parse_index(brillig_index))
+                .map(|(acir_index, brillig_index)| OpcodeLocation::Brillig {
+                    acir_index,
+                    brillig_index,
+                }),
+        }
+        .ok_or_else(|| format!("expected an opcode location but found `{word}`"))
+    }
+
+    /// Parses `(location, "message")`.
+    fn assert_message(&mut self) -> Result<(OpcodeLocation, String), String> {
+        self.wrapped(|parser| {
+            let location = parser.opcode_location()?;
+            parser.expect(Token::Comma)?;
+            Ok((location, parser.string()?))
+        })
+    }
+
+    fn mem_op(&mut self) -> Result<MemOp, String> {
+        Ok(MemOp {
+            operation: self.named_expression("operation")?,
+            index: self.named_expression("index")?,
+            value: self.named_expression("value")?,
+        })
+    }
+}
+
+/// Returns the non-empty lines of `input` along with their line numbers, skipping comments.
+fn content_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input.lines().enumerate().map(|(index, line)| (index + 1, line)).filter(|(_, line)| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with("//")
+    })
+}
+
+/// Runs `parse` over the tokens of `line`, attributing any error to `line_number`.
+fn parse_line<T>(
+    line_number: usize,
+    line: &str,
+    parse: impl FnOnce(&mut LineParser) -> Result<T, String>,
+) -> Result<T, ParseError> {
+    LineParser::new(line)
+        .and_then(|mut parser| {
+            let value = parse(&mut parser)?;
+            parser.finish()?;
+            Ok(value)
+        })
+        .map_err(|message| ParseError { line: line_number, message })
+}
+
+/// Parses an opcode from its first line and any indented lines which follow it.
+fn parse_opcode(
+    (line_number, line): (usize, &str),
+    continuation: &[(usize, &str)],
+) -> Result<Opcode, ParseError> {
+    let line = line.trim();
+    let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+    let opcode = if keyword == "BRILLIG" {
+        let mut brillig = parse_line(line_number, rest, LineParser::brillig_header)?;
+        for &(line_number, line) in continuation {
+            let opcode = line.parse().map_err(|err: brillig_vm::ParseOpcodeError| ParseError {
+                line: line_number,
+                message: err.to_string(),
+            })?;
+            brillig.bytecode.push(opcode);
+        }
+        return Ok(Opcode::Brillig(brillig));
+    } else if matches!(keyword, "BLOCK" | "ROM" | "RAM") {
+        let mut block = parse_line(line_number, rest, LineParser::memory_block_header)?;
+        for &(line_number, line) in continuation {
+            block.trace.push(parse_line(line_number, line, LineParser::mem_op)?);
+        }
+        return Ok(match keyword {
+            "BLOCK" => Opcode::Block(block),
+            "ROM" => Opcode::ROM(block),
+            _ => Opcode::RAM(block),
+        });
+    } else if keyword == "EXPR" {
+        Opcode::Arithmetic(parse_line(line_number, rest, LineParser::expression)?)
+    } else if let Some(name) = keyword.strip_prefix("BLACKBOX::") {
+        let call = parse_line(line_number, rest, |parser| parser.black_box_func_call(name))?;
+        Opcode::BlackBoxFuncCall(call)
+    } else if let Some(name) = keyword.strip_prefix("DIR::") {
+        Opcode::Directive(parse_line(line_number, rest, |parser| parser.directive(name))?)
+    } else {
+        return Err(ParseError {
+            line: line_number,
+            message: format!("unknown opcode `{keyword}`"),
+        });
+    };
+
+    match continuation.first() {
+        Some(&(line_number, _)) => Err(ParseError {
+            line: line_number,
+            message: format!("unexpected indented line following `{keyword}` opcode"),
+        }),
+        None => Ok(opcode),
+    }
+}
+
+/// Splits `lines` into opcodes, each of which is an unindented line followed by any indented lines.
+fn parse_opcodes(lines: &[(usize, &str)]) -> Result<Vec<Opcode>, ParseError> {
+    let mut opcodes = Vec::new();
+    let mut remaining = lines;
+    while let Some((&first, rest)) = remaining.split_first() {
+        if first.1.starts_with(char::is_whitespace) {
+            return Err(ParseError {
+                line: first.0,
+                message: "indented line does not follow an opcode".to_owned(),
+            });
+        }
+        let continuation_len =
+            rest.iter().take_while(|(_, line)| line.starts_with(char::is_whitespace)).count();
+        opcodes.push(parse_opcode(first, &rest[..continuation_len])?);
+        remaining = &rest[continuation_len..];
+    }
+    Ok(opcodes)
+}
+
+impl FromStr for Opcode {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<_> = content_lines(input).collect();
+        let mut opcodes = parse_opcodes(&lines)?;
+        match opcodes.len() {
+            1 => Ok(opcodes.remove(0)),
+            0 => Err(ParseError { line: 1, message: "expected an opcode".to_owned() }),
+            _ => {
+                Err(ParseError { line: lines[1].0, message: "expected a single opcode".to_owned() })
+            }
+        }
+    }
+}
+
+/// Parses a header line of the form `<name> : <value>`.
+fn parse_header<T>(
+    line: Option<(usize, &str)>,
+    name: &str,
+    parse: impl FnOnce(&mut LineParser) -> Result<T, String>,
+) -> Result<T, ParseError> {
+    let (line_number, line) =
+        line.ok_or_else(|| ParseError { line: 1, message: format!("missing `{name}`") })?;
+    let value = line
+        .trim()
+        .strip_prefix(name)
+        .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        .ok_or_else(|| ParseError { line: line_number, message: format!("expected `{name}`") })?;
+    parse_line(line_number, value, parse)
+}
+
+fn public_inputs(parser: &mut LineParser) -> Result<PublicInputs, String> {
+    let indices = parser.list(LineParser::integer)?;
+    Ok(PublicInputs(indices.into_iter().map(Witness).collect::<BTreeSet<_>>()))
+}
+
+impl FromStr for Circuit {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut lines = content_lines(input);
+        let current_witness_index =
+            parse_header(lines.next(), "current witness index", LineParser::integer)?;
+        let public_parameters =
+            parse_header(lines.next(), "public parameters indices", public_inputs)?;
+        let return_values = parse_header(lines.next(), "return value indices", public_inputs)?;
+        let mut lines = lines.peekable();
+        // Assert messages are only written for circuits which have any.
+        let assert_messages = match lines.peek() {
+            Some((_, line)) if line.starts_with("assert messages") => {
+                parse_header(lines.next(), "assert messages", |parser| {
+                    parser.list(LineParser::assert_message)
+                })?
+            }
+            _ => Vec::new(),
+        };
+        let opcodes = parse_opcodes(&lines.collect::<Vec<_>>())?;
+        Ok(Circuit {
+            current_witness_index,
+            opcodes,
+            public_parameters,
+            return_values,
+            assert_messages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use acir_field::FieldElement;
+    use brillig_vm::{ForeignCallOutput, ForeignCallResult, RegisterIndex, RegisterOrMemory};
+
+    use super::ParseError;
+    use crate::{
+        circuit::{
+            brillig::{Brillig, BrilligInputs, BrilligOutputs},
+            directives::{Directive, LogInfo, QuotientDirective},
+            opcodes::{BlackBoxFuncCall, BlockId, FunctionInput, MemOp, MemoryBlock},
+            Circuit, Opcode, OpcodeLocation, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+    };
+
+    fn input(witness: u32) -> FunctionInput {
+        FunctionInput { witness: Witness(witness), num_bits: 8 }
+    }
+
+    fn inputs(witnesses: std::ops::Range<u32>) -> Vec<FunctionInput> {
+        witnesses.map(input).collect()
+    }
+
+    fn outputs(witnesses: std::ops::Range<u32>) -> Vec<Witness> {
+        witnesses.map(Witness).collect()
+    }
+
+    fn expression() -> Expression {
+        Expression {
+            mul_terms: vec![(FieldElement::from(2_i128), Witness(1), Witness(2))],
+            linear_combinations: vec![
+                (-FieldElement::one(), Witness(3)),
+                (FieldElement::from(3_i128 << 64), Witness(4)),
+            ],
+            q_c: -FieldElement::from(1_i128 << 100),
+        }
+    }
+
+    fn black_box_calls() -> Vec<BlackBoxFuncCall> {
+        vec![
+            BlackBoxFuncCall::AND { lhs: input(1), rhs: input(2), output: Witness(3) },
+            BlackBoxFuncCall::XOR { lhs: input(1), rhs: input(2), output: Witness(3) },
+            BlackBoxFuncCall::RANGE { input: input(1) },
+            BlackBoxFuncCall::SHA256 { inputs: inputs(1..10), outputs: outputs(10..42) },
+            BlackBoxFuncCall::Blake2s { inputs: inputs(1..10), outputs: outputs(10..42) },
+            BlackBoxFuncCall::SchnorrVerify {
+                public_key_x: input(1),
+                public_key_y: input(2),
+                signature: inputs(3..67),
+                message: inputs(67..70),
+                output: Witness(70),
+            },
+            BlackBoxFuncCall::Pedersen {
+                inputs: inputs(1..3),
+                domain_separator: 7,
+                outputs: (Witness(3), Witness(4)),
+            },
+            BlackBoxFuncCall::HashToField128Security { inputs: inputs(1..3), output: Witness(3) },
+            BlackBoxFuncCall::EcdsaSecp256k1 {
+                public_key_x: inputs(1..33),
+                public_key_y: inputs(33..65),
+                signature: inputs(65..129),
+                hashed_message: inputs(129..161),
+                output: Witness(161),
+            },
+            BlackBoxFuncCall::EcdsaSecp256r1 {
+                public_key_x: inputs(1..33),
+                public_key_y: inputs(33..65),
+                signature: inputs(65..129),
+                hashed_message: vec![],
+                output: Witness(161),
+            },
+            BlackBoxFuncCall::FixedBaseScalarMul {
+                input: input(1),
+                outputs: (Witness(2), Witness(3)),
+            },
+            BlackBoxFuncCall::Keccak256 { inputs: inputs(1..3), outputs: outputs(3..35) },
+            BlackBoxFuncCall::Keccak256VariableLength {
+                inputs: inputs(1..3),
+                var_message_size: input(3),
+                outputs: outputs(4..36),
+            },
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key: inputs(1..3),
+                proof: inputs(3..5),
+                public_inputs: inputs(5..7),
+                key_hash: input(7),
+                input_aggregation_object: None,
+                output_aggregation_object: outputs(8..10),
+            },
+            BlackBoxFuncCall::RecursiveAggregation {
+                verification_key: inputs(1..3),
+                proof: inputs(3..5),
+                public_inputs: vec![],
+                key_hash: input(7),
+                input_aggregation_object: Some(inputs(10..12)),
+                output_aggregation_object: outputs(12..14),
+            },
+        ]
+    }
+
+    fn directives() -> Vec<Directive> {
+        vec![
+            Directive::Invert { x: Witness(1), result: Witness(2) },
+            Directive::Quotient(QuotientDirective {
+                a: expression(),
+                b: Expression::from(Witness(5)),
+                q: Witness(6),
+                r: Witness(7),
+                predicate: None,
+            }),
+            Directive::Quotient(QuotientDirective {
+                a: expression(),
+                b: Expression::one(),
+                q: Witness(6),
+                r: Witness(7),
+                predicate: Some(Expression::from(Witness(8))),
+            }),
+            Directive::ToLeRadix { a: expression(), b: outputs(5..13), radix: 2 },
+            Directive::PermutationSort {
+                inputs: vec![
+                    vec![Expression::from(Witness(1)), Expression::zero()],
+                    vec![Expression::from(Witness(2)), expression()],
+                ],
+                tuple: 2,
+                bits: outputs(5..8),
+                sort_by: vec![1, 0],
+            },
+            Directive::Log(LogInfo::FinalizedOutput("a \"quoted\"\nmessage ✓".to_owned())),
+            Directive::Log(LogInfo::WitnessOutput(outputs(1..4))),
+        ]
+    }
+
+    fn memory_block() -> MemoryBlock {
+        MemoryBlock {
+            id: BlockId(3),
+            len: 1,
+            trace: vec![
+                MemOp {
+                    operation: Expression::one(),
+                    index: Expression::zero(),
+                    value: Expression::from(Witness(1)),
+                },
+                MemOp {
+                    operation: Expression::from(Witness(2)),
+                    index: expression(),
+                    value: Expression::from(Witness(3)),
+                },
+            ],
+        }
+    }
+
+    fn brillig() -> Brillig {
+        let r = |index| RegisterOrMemory::RegisterIndex(RegisterIndex::from(index));
+        Brillig {
+            inputs: vec![
+                BrilligInputs::Single(expression()),
+                BrilligInputs::Array(vec![Expression::from(Witness(1)), Expression::one()]),
+            ],
+            outputs: vec![BrilligOutputs::Simple(Witness(5)), BrilligOutputs::Array(outputs(6..8))],
+            foreign_call_results: vec![
+                ForeignCallResult {
+                    values: vec![
+                        ForeignCallOutput::Single(FieldElement::from(5_i128).into()),
+                        ForeignCallOutput::Array(vec![
+                            FieldElement::one().into(),
+                            (-FieldElement::one()).into(),
+                        ]),
+                    ],
+                    error: None,
+                },
+                ForeignCallResult::error("no value for \"key\""),
+            ],
+            bytecode: vec![
+                brillig_vm::Opcode::ForeignCall {
+                    function: "get".to_owned(),
+                    destinations: vec![r(0)],
+                    inputs: vec![r(0), r(1)],
+                },
+                brillig_vm::Opcode::Stop,
+            ],
+            predicate: Some(Expression::from(Witness(2))),
+        }
+    }
+
+    fn circuit() -> Circuit {
+        let mut opcodes =
+            vec![Opcode::Arithmetic(expression()), Opcode::Arithmetic(Expression::zero())];
+        opcodes.extend(black_box_calls().into_iter().map(Opcode::BlackBoxFuncCall));
+        opcodes.extend(directives().into_iter().map(Opcode::Directive));
+        opcodes.push(Opcode::Block(memory_block()));
+        opcodes.push(Opcode::ROM(MemoryBlock { trace: vec![], ..memory_block() }));
+        opcodes.push(Opcode::RAM(memory_block()));
+        opcodes.push(Opcode::Brillig(brillig()));
+        opcodes.push(Opcode::Brillig(Brillig {
+            inputs: vec![],
+            outputs: vec![],
+            foreign_call_results: vec![],
+            bytecode: vec![],
+            predicate: None,
+        }));
+        Circuit {
+            current_witness_index: 161,
+            opcodes,
+            public_parameters: PublicInputs(BTreeSet::from_iter([Witness(1), Witness(2)])),
+            return_values: PublicInputs(BTreeSet::new()),
+            assert_messages: vec![
+                (OpcodeLocation::Acir(0), "expression \"failed\"\n".to_owned()),
+                (OpcodeLocation::Brillig { acir_index: 30, brillig_index: 2 }, "trap".to_owned()),
+            ],
+        }
+    }
+
+    #[test]
+    fn opcode_display_roundtrip() {
+        for opcode in circuit().opcodes {
+            let text = opcode.to_string();
+            assert_eq!(text.parse::<Opcode>(), Ok(opcode), "{text}");
+        }
+    }
+
+    #[test]
+    fn circuit_display_roundtrip() {
+        let circuit = circuit();
+        let text = circuit.to_string();
+        assert_eq!(text.parse::<Circuit>(), Ok(circuit.clone()), "{text}");
+
+        let circuit = Circuit { assert_messages: Vec::new(), ..circuit };
+        let text = circuit.to_string();
+        assert!(!text.contains("assert messages"), "{text}");
+        assert_eq!(text.parse::<Circuit>(), Ok(circuit), "{text}");
+    }
+
+    #[test]
+    fn parses_hand_written_circuit() {
+        let src = r#"current witness index : 3
+public parameters indices : [1, 2]
+return value indices : [3]
+assert messages : [(1, "out of range"), (2.0, "input too large")]
+
+// w3 = w1 * w2
+EXPR [ (1, _1, _2) (-1, _3) 0 ]
+BLACKBOX::RANGE input: (_3, num_bits: 32)
+BRILLIG inputs: [Single([ (1, _1) 0 ])] outputs: [Simple(_2)] foreign_call_results: []
+  // double the input
+  fadd r0, r0, r0
+  stop
+"#;
+
+        let circuit: Circuit = src.parse().unwrap();
+        assert_eq!(circuit.current_witness_index, 3);
+        assert_eq!(circuit.public_parameters.indices(), vec![1, 2]);
+        assert_eq!(circuit.return_values.indices(), vec![3]);
+        assert_eq!(circuit.opcodes.len(), 3);
+        assert_eq!(
+            circuit.assert_messages,
+            vec![
+                (OpcodeLocation::Acir(1), "out of range".to_owned()),
+                (
+                    OpcodeLocation::Brillig { acir_index: 2, brillig_index: 0 },
+                    "input too large".to_owned()
+                ),
+            ]
+        );
+
+        let mut expr = Expression::default();
+        expr.push_multiplication_term(FieldElement::one(), Witness(1), Witness(2));
+        expr.push_addition_term(-FieldElement::one(), Witness(3));
+        assert_eq!(circuit.opcodes[0], Opcode::Arithmetic(expr));
+
+        let Opcode::Brillig(brillig) = &circuit.opcodes[2] else {
+            panic!("expected a brillig opcode")
+        };
+        assert_eq!(brillig.bytecode.len(), 2);
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let src = "current witness index : 3\npublic parameters indices : []\nreturn value indices : []\nEXPR [ 0 ]\n\nBRILLIG inputs: [] outputs: [] foreign_call_results: []\n  stop\n  jmp r0\n";
+        let err = src.parse::<Circuit>().unwrap_err();
+        assert_eq!(err.line(), 8);
+
+        let err = "EXPR [ (1, _1 0 ]".parse::<Opcode>().unwrap_err();
+        assert_eq!(err, ParseError { line: 1, message: "expected `)` but found `0`".to_owned() });
+
+        assert!("EXPR [ 0 ]\n  stop".parse::<Opcode>().is_err());
+        assert!("DIR::UNKNOWN".parse::<Opcode>().is_err());
+        assert!("EXPR [ 0 ]\nEXPR [ 0 ]".parse::<Opcode>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Container format used by [`Circuit::write`][super::Circuit::write] and [`Circuit::read`][super::Circuit::read].
+//!
+//! A serialized circuit is laid out as follows (all integers are little endian):
+//!
+//! | Bytes  | Contents                                                            |
+//! |--------|---------------------------------------------------------------------|
+//! | 0..4   | Magic bytes `ACIR`                                                  |
+//! | 4..8   | Format version (`u32`)                                              |
+//! | 8      | Field the circuit was compiled against (see [`FieldOptions`])       |
+//! | 9..13  | CRC32 checksum of the payload (`u32`)                               |
+//! | 13..   | Payload: the msgpack encoded [`Circuit`][super::Circuit], deflated  |
+//!
+//! The header allows us to reject circuits which were compiled against a different field
+//! or an incompatible version of ACIR rather than silently misinterpreting them.
+
+use std::io::{Read, Write};
+
+use acir_field::FieldOptions;
+use thiserror::Error;
+
+use super::migration::MigrationError;
+
+/// Magic bytes which prefix every serialized circuit.
+pub const MAGIC: [u8; 4] = *b"ACIR";
+
+/// Version of the serialized circuit format written by this version of ACIR.
+///
+/// This must be incremented whenever the serialized shape of [`Circuit`][super::Circuit] changes.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Size of the header in bytes.
+const HEADER_LEN: usize = MAGIC.len() + 4 + 1 + 4;
+
+#[derive(Debug, Error)]
+pub enum CircuitSerializationError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    MsgpackEncode(#[from] rmp_serde::encode::Error),
+
+    #[error(transparent)]
+    MsgpackDecode(#[from] rmp_serde::decode::Error),
+
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+
+    #[error("data is not a serialized ACIR circuit: missing magic bytes")]
+    InvalidMagic,
+
+    #[error("circuit was serialized using format version {found} but the latest supported version is {expected}")]
+    UnsupportedVersion { found: u32, expected: u32 },
+
+    #[error("circuit was serialized with an unknown field identifier {0}")]
+    UnknownField(u8),
+
+    #[error(
+        "circuit was compiled for the {found} field but ACIR is compiled for the {expected} field"
+    )]
+    FieldMismatch { found: FieldOptions, expected: FieldOptions },
+
+    #[error("checksum mismatch: header specifies {expected:#010x} but payload has checksum {found:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+}
+
+/// Header which is written in front of every serialized circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Header {
+    pub(super) version: u32,
+    pub(super) field: FieldOptions,
+    pub(super) checksum: u32,
+}
+
+impl Header {
+    /// Creates the header for a payload written by this version of ACIR.
+    pub(super) fn new(payload: &[u8]) -> Self {
+        Header {
+            version: FORMAT_VERSION,
+            field: acir_field::CHOSEN_FIELD,
+            checksum: checksum(payload),
+        }
+    }
+
+    pub(super) fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        bytes[8] = field_to_byte(self.field);
+        bytes[9..13].copy_from_slice(&self.checksum.to_le_bytes());
+        writer.write_all(&bytes)
+    }
+
+    pub(super) fn read<R: Read>(reader: &mut R) -> Result<Self, CircuitSerializationError> {
+        let mut bytes = [0u8; HEADER_LEN];
+        reader.read_exact(&mut bytes)?;
+        if bytes[0..4] != MAGIC {
+            return Err(CircuitSerializationError::InvalidMagic);
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        let field =
+            field_from_byte(bytes[8]).ok_or(CircuitSerializationError::UnknownField(bytes[8]))?;
+        let checksum = u32::from_le_bytes(bytes[9..13].try_into().unwrap());
+
+        Ok(Header { version, field, checksum })
+    }
+
+    /// Checks that a circuit with this header can be deserialized by this version of ACIR.
+    ///
+    /// Older format versions are accepted as they can be [migrated][super::migration].
+    pub(super) fn validate(&self, payload: &[u8]) -> Result<(), CircuitSerializationError> {
+        if self.version == 0 || self.version > FORMAT_VERSION {
+            return Err(CircuitSerializationError::UnsupportedVersion {
+                found: self.version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        if self.field != acir_field::CHOSEN_FIELD {
+            return Err(CircuitSerializationError::FieldMismatch {
+                found: self.field,
+                expected: acir_field::CHOSEN_FIELD,
+            });
+        }
+        let payload_checksum = checksum(payload);
+        if self.checksum != payload_checksum {
+            return Err(CircuitSerializationError::ChecksumMismatch {
+                expected: self.checksum,
+                found: payload_checksum,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn checksum(payload: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(payload);
+    crc.sum()
+}
+
+fn field_to_byte(field: FieldOptions) -> u8 {
+    match field {
+        FieldOptions::BN254 => 0,
+        FieldOptions::BLS12_381 => 1,
+    }
+}
+
+fn field_from_byte(byte: u8) -> Option<FieldOptions> {
+    match byte {
+        0 => Some(FieldOptions::BN254),
+        1 => Some(FieldOptions::BLS12_381),
+        _ => None,
+    }
+}
@@ -99,6 +99,7 @@ impl Expression {
     ///
     /// - `mul_term` in an expression contains degree-2 terms
     /// - `linear_combinations` contains degree-1 terms
+    ///
     /// Hence, it is sufficient to check that there are no `mul_terms`
     ///
     /// Examples:
@@ -112,11 +113,11 @@ impl Expression {
     /// Returns `true` if the expression can be seen as a degree-1 univariate polynomial
     ///
     /// - `mul_terms` in an expression can be univariate, however unless the coefficient
-    /// is zero, it is always degree-2.
+    ///   is zero, it is always degree-2.
     /// - `linear_combinations` contains the sum of degree-1 terms, these terms do not
-    /// need to contain the same variable and so it can be multivariate. However, we
-    /// have thus far only checked if `linear_combinations` contains one term, so this
-    /// method will return false, if the `Expression` has not been simplified.
+    ///   need to contain the same variable and so it can be multivariate. However, we
+    ///   have thus far only checked if `linear_combinations` contains one term, so this
+    ///   method will return false, if the `Expression` has not been simplified.
     ///
     /// Hence, we check in the simplest case if an expression is a degree-1 univariate,
     /// by checking if it contains no `mul_terms` and it contains one `linear_combination` term.
@@ -171,11 +172,33 @@ impl Expression {
         None
     }
 
+    /// Calls `f` on every witness in the expression.
+    pub fn for_each_witness(&self, f: &mut dyn FnMut(&Witness)) {
+        for (_, w_l, w_r) in &self.mul_terms {
+            f(w_l);
+            f(w_r);
+        }
+        for (_, witness) in &self.linear_combinations {
+            f(witness);
+        }
+    }
+
+    /// Calls `f` on every witness in the expression.
+    pub fn for_each_witness_mut(&mut self, f: &mut dyn FnMut(&mut Witness)) {
+        for (_, w_l, w_r) in &mut self.mul_terms {
+            f(w_l);
+            f(w_r);
+        }
+        for (_, witness) in &mut self.linear_combinations {
+            f(witness);
+        }
+    }
+
     /// Sorts gate in a deterministic order
     /// XXX: We can probably make this more efficient by sorting on each phase. We only care if it is deterministic
     pub fn sort(&mut self) {
         self.mul_terms.sort_by(|a, b| a.1.cmp(&b.1).then(a.2.cmp(&b.2)));
-        self.linear_combinations.sort_by(|a, b| a.1.cmp(&b.1));
+        self.linear_combinations.sort_by_key(|a| a.1);
     }
 
     /// Checks if this polynomial can fit into one arithmetic identity
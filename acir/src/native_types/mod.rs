@@ -1,8 +1,10 @@
 mod expression;
 mod witness;
 mod witness_map;
+mod witness_remapping;
 
 pub use expression::Expression;
 pub use witness::Witness;
 pub use witness_map::WitnessMap;
 pub use witness_map::WitnessMapError;
+pub use witness_remapping::WitnessRemapping;
@@ -0,0 +1,146 @@
+use std::collections::{btree_map, BTreeMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Witness, WitnessMap};
+
+/// A one-to-one mapping between the witnesses of a circuit before and after they were renumbered.
+///
+/// This allows values which refer to the original numbering, such as the [`WitnessMap`] of inputs
+/// to a program or the witness indices in its ABI, to be translated to the new numbering and back.
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct WitnessRemapping {
+    old_to_new: BTreeMap<Witness, Witness>,
+    new_to_old: BTreeMap<Witness, Witness>,
+}
+
+impl WitnessRemapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `old` is renumbered to `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either witness has already been mapped as this would make the mapping ambiguous.
+    pub fn insert(&mut self, old: Witness, new: Witness) {
+        assert!(
+            self.old_to_new.insert(old, new).is_none(),
+            "witness {} is already remapped",
+            old.witness_index()
+        );
+        assert!(
+            self.new_to_old.insert(new, old).is_none(),
+            "witness {} is already the target of a remapping",
+            new.witness_index()
+        );
+    }
+
+    /// Returns the new witness which replaces `old`.
+    pub fn to_new(&self, old: Witness) -> Option<Witness> {
+        self.old_to_new.get(&old).copied()
+    }
+
+    /// Returns the original witness which was replaced by `new`.
+    pub fn to_old(&self, new: Witness) -> Option<Witness> {
+        self.new_to_old.get(&new).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.old_to_new.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.old_to_new.is_empty()
+    }
+
+    /// Iterates over pairs of `(old, new)` witnesses in order of the old witness.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.old_to_new.iter())
+    }
+
+    /// Translates a [`WitnessMap`] using the original numbering into the new numbering.
+    ///
+    /// Witnesses which are not part of the mapping, i.e. they were removed from the circuit, are dropped.
+    pub fn map_to_new(&self, witness_map: &WitnessMap) -> WitnessMap {
+        Self::translate(&self.old_to_new, witness_map)
+    }
+
+    /// Translates a [`WitnessMap`] using the new numbering back into the original numbering.
+    pub fn map_to_old(&self, witness_map: &WitnessMap) -> WitnessMap {
+        Self::translate(&self.new_to_old, witness_map)
+    }
+
+    fn translate(mapping: &BTreeMap<Witness, Witness>, witness_map: &WitnessMap) -> WitnessMap {
+        let mut translated = WitnessMap::new();
+        for (witness, value) in witness_map.clone() {
+            if let Some(witness) = mapping.get(&witness) {
+                translated.insert(*witness, value);
+            }
+        }
+        translated
+    }
+}
+
+impl FromIterator<(Witness, Witness)> for WitnessRemapping {
+    /// Builds a mapping from pairs of `(old, new)` witnesses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pairs do not form a one-to-one mapping.
+    fn from_iter<T: IntoIterator<Item = (Witness, Witness)>>(iter: T) -> Self {
+        let mut remapping = Self::new();
+        for (old, new) in iter {
+            remapping.insert(old, new);
+        }
+        remapping
+    }
+}
+
+pub struct Iter<'a>(btree_map::Iter<'a, Witness, Witness>);
+
+impl Iterator for Iter<'_> {
+    type Item = (Witness, Witness);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(old, new)| (*old, *new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir_field::FieldElement;
+
+    use super::WitnessRemapping;
+    use crate::native_types::{Witness, WitnessMap};
+
+    #[test]
+    fn translates_witness_maps_in_both_directions() {
+        let remapping: WitnessRemapping =
+            [(Witness(3), Witness(1)), (Witness(7), Witness(2))].into_iter().collect();
+
+        let mut original = WitnessMap::new();
+        original.insert(Witness(3), FieldElement::from(30_i128));
+        original.insert(Witness(7), FieldElement::from(70_i128));
+        // Witness 5 was removed from the circuit.
+        original.insert(Witness(5), FieldElement::from(50_i128));
+
+        let renumbered = remapping.map_to_new(&original);
+        assert_eq!(renumbered.get(&Witness(1)), Some(&FieldElement::from(30_i128)));
+        assert_eq!(renumbered.get(&Witness(2)), Some(&FieldElement::from(70_i128)));
+        assert_eq!(renumbered.get(&Witness(3)), None);
+
+        let restored = remapping.map_to_old(&renumbered);
+        assert_eq!(restored.get(&Witness(3)), original.get(&Witness(3)));
+        assert_eq!(restored.get(&Witness(7)), original.get(&Witness(7)));
+        assert_eq!(restored.get(&Witness(5)), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_ambiguous_mappings() {
+        let _: WitnessRemapping =
+            [(Witness(3), Witness(1)), (Witness(7), Witness(1))].into_iter().collect();
+    }
+}
@@ -210,6 +210,35 @@ impl<F: PrimeField> FieldElement<F> {
         Some(FieldElement(fr))
     }
 
+    /// Parses the notation produced by the `Display` implementation, e.g. `-2⁶⁴×3`.
+    ///
+    /// Plain decimal and hex representations are also accepted.
+    pub fn try_from_display_str(input: &str) -> Option<FieldElement<F>> {
+        if let Some(magnitude) = input.strip_prefix('-') {
+            return FieldElement::try_from_display_str(magnitude).map(|value| -value);
+        }
+
+        // Powers of two are written as `2ⁿ`, optionally followed by a multiplier `×q`.
+        let exponent_str: String =
+            input.chars().skip(1).take_while(|c| from_superscript(*c).is_some()).collect();
+        if !input.starts_with('2') || exponent_str.is_empty() {
+            return FieldElement::try_from_str(input);
+        }
+        let exponent = exponent_str
+            .chars()
+            .try_fold(0u32, |acc, c| acc.checked_mul(10)?.checked_add(from_superscript(c)?))?;
+        let power_of_two = BigUint::from(2_u8).pow(exponent);
+
+        let remainder = &input[1 + exponent_str.len()..];
+        let value = if remainder.is_empty() {
+            power_of_two
+        } else {
+            let multiplier: BigUint = remainder.strip_prefix('×')?.parse().ok()?;
+            power_of_two * multiplier
+        };
+        Some(FieldElement::from_be_bytes_reduce(&value.to_bytes_be()))
+    }
+
     /// This is the number of bits required to represent this specific field element
     pub fn num_bits(&self) -> u32 {
         let bits = self.bits();
@@ -341,7 +370,7 @@ impl<F: PrimeField> FieldElement<F> {
 
         let and_byte_arr: Vec<_> = lhs_bytes
             .into_iter()
-            .zip(rhs_bytes.into_iter())
+            .zip(rhs_bytes)
             .map(|(lhs, rhs)| if is_xor { lhs ^ rhs } else { lhs & rhs })
             .collect();
 
@@ -405,6 +434,69 @@ impl<F: PrimeField> SubAssign for FieldElement<F> {
     }
 }
 
+// For pretty printing powers
+fn superscript(n: u64) -> String {
+    if n == 0 {
+        "⁰".to_owned()
+    } else if n == 1 {
+        "¹".to_owned()
+    } else if n == 2 {
+        "²".to_owned()
+    } else if n == 3 {
+        "³".to_owned()
+    } else if n == 4 {
+        "⁴".to_owned()
+    } else if n == 5 {
+        "⁵".to_owned()
+    } else if n == 6 {
+        "⁶".to_owned()
+    } else if n == 7 {
+        "⁷".to_owned()
+    } else if n == 8 {
+        "⁸".to_owned()
+    } else if n == 9 {
+        "⁹".to_owned()
+    } else if n >= 10 {
+        superscript(n / 10) + &superscript(n % 10)
+    } else {
+        panic!("{}", n.to_string() + " can't be converted to superscript.");
+    }
+}
+
+// Inverse of `superscript` for a single digit
+fn from_superscript(c: char) -> Option<u32> {
+    "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().position(|digit| digit == c).map(|digit| digit as u32)
+}
+
+fn mask_vector_le(bytes: &mut [u8], num_bits: usize) {
+    // reverse to big endian format
+    bytes.reverse();
+
+    let mask_power = num_bits % 8;
+    let array_mask_index = num_bits / 8;
+
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        match index.cmp(&array_mask_index) {
+            std::cmp::Ordering::Less => {
+                // do nothing if the current index is less than
+                // the array index.
+            }
+            std::cmp::Ordering::Equal => {
+                let mask = 2u8.pow(mask_power as u32) - 1;
+                // mask the byte
+                *byte &= mask;
+            }
+            std::cmp::Ordering::Greater => {
+                // Anything greater than the array index
+                // will be set to zero
+                *byte = 0;
+            }
+        }
+    }
+    // reverse back to little endian
+    bytes.reverse();
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -441,62 +533,26 @@ mod tests {
         let max_num_bits_bn254 = crate::generic_ark::FieldElement::<ark_bn254::Fr>::max_num_bits();
         assert_eq!(max_num_bits_bn254, 254)
     }
-}
-
-fn mask_vector_le(bytes: &mut [u8], num_bits: usize) {
-    // reverse to big endian format
-    bytes.reverse();
-
-    let mask_power = num_bits % 8;
-    let array_mask_index = num_bits / 8;
 
-    for (index, byte) in bytes.iter_mut().enumerate() {
-        match index.cmp(&array_mask_index) {
-            std::cmp::Ordering::Less => {
-                // do nothing if the current index is less than
-                // the array index.
-            }
-            std::cmp::Ordering::Equal => {
-                let mask = 2u8.pow(mask_power as u32) - 1;
-                // mask the byte
-                *byte &= mask;
-            }
-            std::cmp::Ordering::Greater => {
-                // Anything greater than the array index
-                // will be set to zero
-                *byte = 0;
-            }
+    #[test]
+    fn display_str_roundtrip() {
+        type FieldElement = crate::generic_ark::FieldElement<ark_bn254::Fr>;
+
+        let values = [
+            FieldElement::zero(),
+            FieldElement::one(),
+            FieldElement::from(8_i128),
+            FieldElement::from(12345_i128),
+            FieldElement::from(1_i128 << 100),
+            FieldElement::from(3_i128 << 64),
+            FieldElement::from(5_i128 << 16),
+            -FieldElement::one(),
+            -FieldElement::from(7_i128 << 32),
+            FieldElement::from_hex("0x1234567890abcdef1234567890abcdef1234567890abcdef").unwrap(),
+        ];
+        for value in values {
+            let display = value.to_string();
+            assert_eq!(FieldElement::try_from_display_str(&display), Some(value), "{display}");
         }
     }
-    // reverse back to little endian
-    bytes.reverse();
-}
-
-// For pretty printing powers
-fn superscript(n: u64) -> String {
-    if n == 0 {
-        "⁰".to_owned()
-    } else if n == 1 {
-        "¹".to_owned()
-    } else if n == 2 {
-        "²".to_owned()
-    } else if n == 3 {
-        "³".to_owned()
-    } else if n == 4 {
-        "⁴".to_owned()
-    } else if n == 5 {
-        "⁵".to_owned()
-    } else if n == 6 {
-        "⁶".to_owned()
-    } else if n == 7 {
-        "⁷".to_owned()
-    } else if n == 8 {
-        "⁸".to_owned()
-    } else if n == 9 {
-        "⁹".to_owned()
-    } else if n >= 10 {
-        superscript(n / 10) + &superscript(n % 10)
-    } else {
-        panic!("{}", n.to_string() + " can't be converted to superscript.");
-    }
 }
@@ -16,12 +16,21 @@ cfg_if::cfg_if! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FieldOptions {
     BN254,
     BLS12_381,
 }
 
+impl std::fmt::Display for FieldOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldOptions::BN254 => write!(f, "bn254"),
+            FieldOptions::BLS12_381 => write!(f, "bls12_381"),
+        }
+    }
+}
+
 // This is needed because features are additive through the dependency graph; if a dependency turns on the bn254, then it
 // will be turned on in all crates that depend on it
 #[macro_export]
@@ -0,0 +1,518 @@
+use std::collections::{HashMap, HashSet};
+
+use acir::{
+    circuit::{Circuit, Opcode, OpcodeLabel, OpcodeLocation},
+    native_types::{Expression, Witness},
+    FieldElement,
+};
+use indexmap::IndexMap;
+
+use crate::compiler::transformers::CSatTransformer;
+
+/// `CommonSubexpressionOptimizer` removes redundancy between the arithmetic opcodes of a [`Circuit`].
+///
+/// It performs two optimizations:
+/// - Arithmetic opcodes which are equal up to a constant factor are duplicates of each other
+///   so all but the first are removed. Opcodes with different [assertion messages][Circuit::assert_messages]
+///   are kept apart so that no message is lost.
+/// - If a `width` is given, any sub-expression which is left after removing a single linear term from
+///   several opcodes is replaced with an intermediate witness which is constrained once. This is only done
+///   where it reduces the number of gates which [`CSatTransformer`] splits the circuit into for that width,
+///   without increasing the number of witnesses.
+///
+/// # Example
+///
+/// The opcodes
+/// ```text
+/// x + y + z - a = 0
+/// 2x + 2y + 2z - b = 0
+/// -x - y - z + c = 0
+/// ```
+/// share the sub-expression `x + y + z` so are rewritten as
+/// ```text
+/// x + y + z - t = 0
+/// t - a = 0
+/// 2t - b = 0
+/// -t + c = 0
+/// ```
+/// which fit into 5 gates of width 3 rather than 6.
+pub(crate) struct CommonSubexpressionOptimizer {
+    width: Option<usize>,
+}
+
+impl CommonSubexpressionOptimizer {
+    pub(crate) fn new(width: Option<usize>) -> Self {
+        Self { width }
+    }
+
+    /// Returns the optimized circuit along with the labels of its opcodes.
+    ///
+    /// Intermediate witnesses and the opcodes which define them take the label of the first
+    /// opcode which uses them. Public inputs are left untouched.
+    pub(crate) fn optimize(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> (Circuit, Vec<OpcodeLabel>) {
+        let (circuit, opcode_labels) = remove_duplicate_opcodes(circuit, opcode_labels);
+        match self.width {
+            Some(width) => share_subexpressions(circuit, opcode_labels, width),
+            None => (circuit, opcode_labels),
+        }
+    }
+}
+
+/// Scales `expr` such that its first coefficient is one, returning the scaled expression and the
+/// factor which was removed.
+///
+/// Returns `None` for constant expressions.
+fn normalize(expr: &Expression) -> Option<(FieldElement, Expression)> {
+    let mut expr = expr.clone();
+    expr.sort();
+    let norm = expr
+        .linear_combinations
+        .first()
+        .map(|term| term.0)
+        .or_else(|| expr.mul_terms.first().map(|term| term.0))
+        .filter(|norm| !norm.is_zero())?;
+    Some((norm, &expr * norm.inverse()))
+}
+
+fn remove_duplicate_opcodes(
+    circuit: Circuit,
+    opcode_labels: Vec<OpcodeLabel>,
+) -> (Circuit, Vec<OpcodeLabel>) {
+    // The messages reported when the opcode at each index fails.
+    let mut messages: HashMap<usize, Vec<String>> = HashMap::new();
+    for (location, message) in &circuit.assert_messages {
+        if let OpcodeLocation::Acir(index) = location {
+            messages.entry(*index).or_default().push(message.clone());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut new_opcode_labels = Vec::with_capacity(opcode_labels.len());
+    let mut opcodes = Vec::with_capacity(circuit.opcodes.len());
+    for (index, (opcode, label)) in circuit.opcodes.into_iter().zip(opcode_labels).enumerate() {
+        if let Opcode::Arithmetic(expr) = &opcode {
+            if let Some((_, normalized)) = normalize(expr) {
+                if !seen.insert((normalized, messages.remove(&index))) {
+                    continue;
+                }
+            }
+        }
+        opcodes.push(opcode);
+        new_opcode_labels.push(label);
+    }
+    (Circuit { opcodes, ..circuit }, new_opcode_labels)
+}
+
+/// A way of writing an expression as `norm * remainder + coefficient * witness`.
+struct Decomposition {
+    remainder: Expression,
+    norm: FieldElement,
+    coefficient: FieldElement,
+    witness: Witness,
+}
+
+/// Returns each way of splitting a single linear term off of `expr` which leaves a remainder
+/// with at least two terms, as only these are worth sharing.
+fn decompositions(expr: &Expression) -> Vec<Decomposition> {
+    let num_terms = expr.mul_terms.len() + expr.linear_combinations.len();
+    if num_terms < 3 {
+        return Vec::new();
+    }
+    (0..expr.linear_combinations.len())
+        .filter_map(|index| {
+            let mut remainder = expr.clone();
+            let (coefficient, witness) = remainder.linear_combinations.remove(index);
+            let (norm, remainder) = normalize(&remainder)?;
+            Some(Decomposition { remainder, norm, coefficient, witness })
+        })
+        .collect()
+}
+
+/// The size of a set of arithmetic opcodes once they are split to fit into gates of a fixed width.
+#[derive(Debug, Clone, Copy)]
+struct GateCost {
+    gates: usize,
+    witnesses: usize,
+}
+
+impl GateCost {
+    /// Returns the cost of `exprs` once transformed by `transformer`, along with `witnesses` extra
+    /// witnesses which have already been added to the circuit.
+    ///
+    /// `next_witness_index` must not be used by any of `exprs`.
+    fn of<'e>(
+        transformer: &CSatTransformer,
+        exprs: impl IntoIterator<Item = &'e Expression>,
+        next_witness_index: u32,
+        witnesses: usize,
+    ) -> GateCost {
+        let mut intermediate_variables = IndexMap::new();
+        let mut next_witness_index = next_witness_index;
+        let mut gates = 0;
+        for expr in exprs {
+            transformer.transform(
+                expr.clone(),
+                &mut intermediate_variables,
+                &mut next_witness_index,
+            );
+            gates += 1;
+        }
+        GateCost {
+            gates: gates + intermediate_variables.len(),
+            witnesses: witnesses + intermediate_variables.len(),
+        }
+    }
+
+    fn improves_on(&self, other: &GateCost) -> bool {
+        self.gates < other.gates && self.witnesses <= other.witnesses
+    }
+}
+
+/// Constrains `witness` to equal `remainder`.
+fn definition(remainder: &Expression, witness: Witness) -> Expression {
+    let mut definition = remainder.clone();
+    definition.push_addition_term(-FieldElement::one(), witness);
+    definition.sort();
+    definition
+}
+
+/// Rewrites the opcode decomposed by `decomposition` to use `shared_witness` in place of its remainder.
+fn replacement(decomposition: &Decomposition, shared_witness: Witness) -> Expression {
+    let mut expr = Expression::default();
+    expr.push_addition_term(decomposition.norm, shared_witness);
+    expr.push_addition_term(decomposition.coefficient, decomposition.witness);
+    expr.sort();
+    expr
+}
+
+fn share_subexpressions(
+    circuit: Circuit,
+    opcode_labels: Vec<OpcodeLabel>,
+    width: usize,
+) -> (Circuit, Vec<OpcodeLabel>) {
+    let transformer = CSatTransformer::new(width);
+    let decompositions: Vec<Vec<Decomposition>> = circuit
+        .opcodes
+        .iter()
+        .map(|opcode| match opcode {
+            Opcode::Arithmetic(expr) => decompositions(expr),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    // Count the number of opcodes in which each remainder appears.
+    let mut occurrences: HashMap<&Expression, usize> = HashMap::new();
+    for opcode_decompositions in &decompositions {
+        let unique: HashSet<_> = opcode_decompositions.iter().map(|d| &d.remainder).collect();
+        for remainder in unique {
+            *occurrences.entry(remainder).or_default() += 1;
+        }
+    }
+
+    // Each opcode may share the remainder which appears in the most opcodes.
+    let chosen: Vec<Option<&Decomposition>> = decompositions
+        .iter()
+        .map(|opcode_decompositions| {
+            opcode_decompositions
+                .iter()
+                .filter(|d| occurrences[&d.remainder] > 1)
+                .max_by_key(|d| occurrences[&d.remainder])
+        })
+        .collect();
+    let mut sharing_opcodes: IndexMap<&Expression, Vec<usize>> = IndexMap::new();
+    for (index, decomposition) in chosen.iter().enumerate() {
+        if let Some(decomposition) = decomposition {
+            sharing_opcodes.entry(&decomposition.remainder).or_default().push(index);
+        }
+    }
+
+    // Only share remainders where doing so reduces the size of the opcodes which share them.
+    let placeholder = Witness(circuit.current_witness_index + 1);
+    let next_witness_index = circuit.current_witness_index + 2;
+    let shared: HashSet<&Expression> = sharing_opcodes
+        .into_iter()
+        .filter(|(remainder, indices)| {
+            let original = indices.iter().map(|index| match &circuit.opcodes[*index] {
+                Opcode::Arithmetic(expr) => expr,
+                _ => unreachable!("only arithmetic opcodes are decomposed"),
+            });
+            let rewritten: Vec<Expression> = std::iter::once(definition(remainder, placeholder))
+                .chain(indices.iter().map(|index| {
+                    replacement(chosen[*index].expect("opcode shares a remainder"), placeholder)
+                }))
+                .collect();
+            indices.len() > 1
+                && GateCost::of(&transformer, &rewritten, next_witness_index, 1)
+                    .improves_on(&GateCost::of(&transformer, original, next_witness_index, 0))
+        })
+        .map(|(remainder, _)| remainder)
+        .collect();
+    if shared.is_empty() {
+        return (circuit, opcode_labels);
+    }
+
+    let mut next_witness_index = circuit.current_witness_index + 1;
+    let mut shared_witnesses: HashMap<&Expression, Witness> = HashMap::new();
+    let mut new_opcode_labels = Vec::with_capacity(opcode_labels.len());
+    let mut opcodes = Vec::with_capacity(circuit.opcodes.len());
+    for ((opcode, label), decomposition) in circuit.opcodes.iter().zip(&opcode_labels).zip(&chosen)
+    {
+        let decomposition = match decomposition {
+            Some(decomposition) if shared.contains(&decomposition.remainder) => decomposition,
+            _ => {
+                opcodes.push(opcode.clone());
+                new_opcode_labels.push(*label);
+                continue;
+            }
+        };
+
+        let shared_witness =
+            *shared_witnesses.entry(&decomposition.remainder).or_insert_with(|| {
+                let witness = Witness(next_witness_index);
+                next_witness_index += 1;
+                opcodes.push(Opcode::Arithmetic(definition(&decomposition.remainder, witness)));
+                new_opcode_labels.push(*label);
+                witness
+            });
+        opcodes.push(Opcode::Arithmetic(replacement(decomposition, shared_witness)));
+        new_opcode_labels.push(*label);
+    }
+
+    // Remainders which are shared may overlap with the intermediate variables which CSAT would create
+    // for other opcodes, so the circuit is only changed if it shrinks as a whole.
+    let arithmetic = |opcodes: &[Opcode]| -> Vec<Expression> {
+        opcodes
+            .iter()
+            .filter_map(|opcode| match opcode {
+                Opcode::Arithmetic(expr) => Some(expr.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    let original_cost =
+        GateCost::of(&transformer, &arithmetic(&circuit.opcodes), next_witness_index, 0);
+    let shared_cost = GateCost::of(
+        &transformer,
+        &arithmetic(&opcodes),
+        next_witness_index,
+        shared_witnesses.len(),
+    );
+    if !shared_cost.improves_on(&original_cost) {
+        return (circuit, opcode_labels);
+    }
+
+    (
+        Circuit {
+            current_witness_index: next_witness_index - 1,
+            opcodes,
+            // The optimizer does not add new public inputs
+            public_parameters: circuit.public_parameters,
+            return_values: circuit.return_values,
+            assert_messages: circuit.assert_messages,
+        },
+        new_opcode_labels,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use acir::{
+        circuit::{Circuit, Opcode, OpcodeLabel, OpcodeLocation, PublicInputs},
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use super::CommonSubexpressionOptimizer;
+    use crate::compiler::transformers::CSatTransformer;
+
+    const WIDTH: usize = 3;
+
+    fn linear(terms: &[(i128, u32)]) -> Expression {
+        let mut expr = Expression::default();
+        for &(coefficient, witness) in terms {
+            expr.push_addition_term(FieldElement::from(coefficient), Witness(witness));
+        }
+        expr
+    }
+
+    fn circuit(current_witness_index: u32, opcodes: Vec<Expression>) -> Circuit {
+        Circuit {
+            current_witness_index,
+            opcodes: opcodes.into_iter().map(Opcode::Arithmetic).collect(),
+            public_parameters: PublicInputs([Witness(1)].into()),
+            return_values: PublicInputs([Witness(5)].into()),
+            assert_messages: Vec::new(),
+        }
+    }
+
+    fn evaluate(expr: &Expression, assignment: &BTreeMap<Witness, FieldElement>) -> FieldElement {
+        let mul_terms =
+            expr.mul_terms.iter().map(|(q, w_l, w_r)| *q * assignment[w_l] * assignment[w_r]);
+        let linear_terms = expr.linear_combinations.iter().map(|(q, w)| *q * assignment[w]);
+        mul_terms.chain(linear_terms).fold(expr.q_c, |acc, term| acc + term)
+    }
+
+    fn csat(acir: Circuit) -> Circuit {
+        let opcode_labels = acir.initial_opcode_labels();
+        CSatTransformer::new(WIDTH).transform_circuit(acir, opcode_labels).0
+    }
+
+    /// Checks that sharing sub-expressions in `acir` before splitting it into gates never results in more
+    /// gates or witnesses than splitting it directly, returning the optimized circuit.
+    fn assert_no_growth(acir: &Circuit) -> Circuit {
+        let optimizer = CommonSubexpressionOptimizer::new(Some(WIDTH));
+        let (optimized, opcode_labels) =
+            optimizer.optimize(acir.clone(), acir.initial_opcode_labels());
+        assert_eq!(optimized.opcodes.len(), opcode_labels.len());
+
+        let (expected, actual) = (csat(acir.clone()), csat(optimized.clone()));
+        assert!(
+            actual.opcodes.len() <= expected.opcodes.len(),
+            "{} gates rather than {}",
+            actual.opcodes.len(),
+            expected.opcodes.len()
+        );
+        assert!(
+            actual.current_witness_index <= expected.current_witness_index,
+            "{} witnesses rather than {}",
+            actual.current_witness_index,
+            expected.current_witness_index
+        );
+        optimized
+    }
+
+    #[test]
+    fn removes_duplicate_opcodes() {
+        let expr = linear(&[(1, 1), (-1, 2)]);
+        let acir =
+            circuit(2, vec![expr.clone(), &expr * FieldElement::from(3_i128), linear(&[(1, 1)])]);
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let optimizer = CommonSubexpressionOptimizer::new(None);
+        let (optimized, opcode_labels) = optimizer.optimize(acir.clone(), opcode_labels);
+        assert_eq!(
+            optimized.opcodes,
+            [Opcode::Arithmetic(expr), Opcode::Arithmetic(linear(&[(1, 1)]))]
+        );
+        assert_eq!(opcode_labels, [OpcodeLabel::Resolved(0), OpcodeLabel::Resolved(2)]);
+        assert_eq!(optimized.current_witness_index, acir.current_witness_index);
+    }
+
+    #[test]
+    fn keeps_duplicates_with_different_assert_messages() {
+        let expr = linear(&[(1, 1), (-1, 2)]);
+        let message = |index, message: &str| (OpcodeLocation::Acir(index), message.to_owned());
+        let acir = Circuit {
+            assert_messages: vec![message(0, "first"), message(1, "second"), message(2, "first")],
+            ..circuit(2, vec![expr.clone(), expr.clone(), expr.clone(), expr])
+        };
+        let opcode_labels = acir.initial_opcode_labels();
+
+        // Only the third opcode repeats both the expression and the message of an earlier one, while
+        // the fourth has no message at all.
+        let optimizer = CommonSubexpressionOptimizer::new(None);
+        let (_, opcode_labels) = optimizer.optimize(acir, opcode_labels);
+        assert_eq!(opcode_labels, [0, 1, 3].map(OpcodeLabel::Resolved));
+    }
+
+    #[test]
+    fn shares_common_subexpressions() {
+        // x1 + x2 + x3 appears in each of the first three opcodes.
+        let opcodes = vec![
+            linear(&[(1, 1), (1, 2), (1, 3), (-1, 4)]),
+            linear(&[(2, 1), (2, 2), (2, 3), (-1, 5)]),
+            linear(&[(-1, 1), (-1, 2), (-1, 3), (1, 6)]),
+            linear(&[(1, 4), (-1, 6)]),
+        ];
+        let acir = circuit(6, opcodes.clone());
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let optimizer = CommonSubexpressionOptimizer::new(Some(WIDTH));
+        let (optimized, new_opcode_labels) = optimizer.optimize(acir.clone(), opcode_labels);
+
+        // A single intermediate witness is shared between the three opcodes, which saves a gate.
+        assert_eq!(optimized.current_witness_index, 7);
+        assert_eq!(
+            new_opcode_labels,
+            [0, 0, 1, 2, 3].map(OpcodeLabel::Resolved),
+            "intermediate opcodes should inherit the label of their first use"
+        );
+        assert_eq!(optimized.public_parameters, acir.public_parameters);
+        assert_eq!(optimized.return_values, acir.return_values);
+        assert!(csat(optimized.clone()).opcodes.len() < csat(acir.clone()).opcodes.len());
+        assert_no_growth(&acir);
+
+        // Any assignment which satisfies the original circuit satisfies the optimized circuit
+        // once the intermediate witness is assigned.
+        let mut assignment: BTreeMap<Witness, FieldElement> =
+            [(1, 1_i128), (2, 2), (3, 3), (4, 6), (5, 12), (6, 6), (7, 6)]
+                .into_iter()
+                .map(|(witness, value)| (Witness(witness), FieldElement::from(value)))
+                .collect();
+        for expr in &opcodes {
+            assert!(evaluate(expr, &assignment).is_zero());
+        }
+        for opcode in &optimized.opcodes {
+            let Opcode::Arithmetic(expr) = opcode else { panic!("expected arithmetic opcode") };
+            assert!(evaluate(expr, &assignment).is_zero());
+        }
+
+        // The intermediate witness is fully constrained.
+        assignment.insert(Witness(7), FieldElement::from(7_i128));
+        assert!(optimized.opcodes.iter().any(|opcode| {
+            let Opcode::Arithmetic(expr) = opcode else { unreachable!() };
+            !evaluate(expr, &assignment).is_zero()
+        }));
+    }
+
+    #[test]
+    fn only_shares_subexpressions_which_reduce_gates() {
+        // Each opcode already fits into a single gate, so sharing x1 + x2 would add a gate and a witness.
+        let acir = circuit(
+            4,
+            vec![linear(&[(1, 1), (1, 2), (-1, 3)]), linear(&[(2, 1), (2, 2), (-1, 4)])],
+        );
+        assert_eq!(assert_no_growth(&acir), acir);
+
+        // CSAT already shares the intermediate variable for x1 + x2 between these opcodes.
+        let acir = circuit(
+            6,
+            vec![
+                linear(&[(1, 1), (1, 2), (1, 3), (-1, 5)]),
+                linear(&[(1, 1), (1, 2), (1, 4), (-1, 6)]),
+            ],
+        );
+        assert_no_growth(&acir);
+
+        let acir = circuit(
+            7,
+            vec![
+                linear(&[(1, 1), (1, 2), (1, 3), (1, 4), (-1, 6)]),
+                linear(&[(1, 1), (1, 2), (1, 3), (1, 4), (-1, 7)]),
+                linear(&[(3, 1), (1, 5), (-1, 6)]),
+            ],
+        );
+        assert_no_growth(&acir);
+    }
+
+    #[test]
+    fn leaves_unique_expressions_untouched() {
+        let acir = circuit(
+            5,
+            vec![linear(&[(1, 1), (1, 2), (-1, 3)]), linear(&[(1, 3), (1, 4), (-1, 5)])],
+        );
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let optimizer = CommonSubexpressionOptimizer::new(Some(WIDTH));
+        let (optimized, new_opcode_labels) =
+            optimizer.optimize(acir.clone(), opcode_labels.clone());
+        assert_eq!(optimized, acir);
+        assert_eq!(new_opcode_labels, opcode_labels);
+    }
+}
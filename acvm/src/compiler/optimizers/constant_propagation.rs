@@ -0,0 +1,355 @@
+use acir::{
+    circuit::{directives::Directive, opcodes::BlackBoxFuncCall, Circuit, Opcode, OpcodeLabel},
+    native_types::{Expression, Witness, WitnessMap},
+    BlackBoxFunc, FieldElement,
+};
+
+use crate::{
+    compiler::CompileError,
+    pwg::{arithmetic::ArithmeticSolver, blackbox, directives::solve_directives, OpcodeResolution},
+    BlackBoxFunctionSolver, OpcodeResolutionError,
+};
+
+/// `ConstantPropagator` evaluates the parts of a [`Circuit`] which do not depend on its inputs.
+///
+/// Arithmetic opcodes which only leave a single witness unknown fix the value of that witness. These
+/// values are then substituted into the rest of the circuit until no more witnesses can be solved:
+/// - Arithmetic opcodes are partially evaluated, and removed if they are trivially satisfied.
+/// - Directives and black box functions whose inputs are all known are evaluated and replaced with
+///   arithmetic opcodes which assign their outputs.
+///
+/// Every witness which is solved keeps a single arithmetic opcode setting it to its value so that
+/// it remains constrained in the optimized circuit.
+pub(crate) struct ConstantPropagator {
+    known: WitnessMap,
+}
+
+impl ConstantPropagator {
+    pub(crate) fn new() -> Self {
+        Self { known: WitnessMap::new() }
+    }
+
+    /// Returns the optimized circuit along with the labels of its opcodes.
+    ///
+    /// Returns [`CompileError::UnsatisfiableConstraint`] if an opcode can never be satisfied.
+    pub(crate) fn propagate(
+        mut self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let mut opcodes = circuit.opcodes;
+        // The witnesses solved by each opcode which has been fully evaluated.
+        let mut solved: Vec<Option<Vec<Witness>>> = vec![None; opcodes.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, opcode) in opcodes.iter_mut().enumerate() {
+                if solved[index].is_some() {
+                    continue;
+                }
+                let unsatisfiable =
+                    || CompileError::UnsatisfiableConstraint { opcode_label: opcode_labels[index] };
+
+                match self.fold_opcode(opcode).map_err(|_| unsatisfiable())? {
+                    None => (),
+                    Some(Fold::Simplified(expr)) => {
+                        let simplified = Opcode::Arithmetic(expr);
+                        changed |= simplified != *opcode;
+                        *opcode = simplified;
+                    }
+                    Some(Fold::Solved(witnesses)) => {
+                        solved[index] = Some(witnesses);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut new_opcodes = Vec::with_capacity(opcodes.len());
+        let mut new_opcode_labels = Vec::with_capacity(opcodes.len());
+        for ((opcode, solved), label) in opcodes.into_iter().zip(solved).zip(opcode_labels) {
+            match solved {
+                None => {
+                    new_opcodes.push(opcode);
+                    new_opcode_labels.push(label);
+                }
+                // Replace the opcode with definitions of the witnesses it solved.
+                Some(witnesses) => {
+                    for witness in witnesses {
+                        new_opcodes.push(self.define(witness));
+                        new_opcode_labels.push(label);
+                    }
+                }
+            }
+        }
+        let opcodes = new_opcodes;
+
+        Ok((Circuit { opcodes, ..circuit }, new_opcode_labels))
+    }
+
+    /// Attempts to evaluate `opcode` using the witnesses solved so far.
+    ///
+    /// Returns `Ok(None)` if nothing could be done with the opcode.
+    fn fold_opcode(&mut self, opcode: &Opcode) -> Result<Option<Fold>, OpcodeResolutionError> {
+        match opcode {
+            Opcode::Arithmetic(expr) => self.fold_arithmetic(expr),
+            // Logs must be kept so that they are printed when solving the circuit.
+            Opcode::Directive(Directive::Log(_)) => Ok(None),
+            // Recursive aggregation is only simulated by the ACVM so must be left for the backend.
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RecursiveAggregation { .. }) => Ok(None),
+            Opcode::Directive(directive) => {
+                self.fold_with(|witness_map| solve_directives(witness_map, directive))
+            }
+            Opcode::BlackBoxFuncCall(bb_func) => {
+                self.check_input_sizes(bb_func)?;
+                self.fold_with(|witness_map| {
+                    blackbox::solve(&CompileTimeBlackBoxSolver, witness_map, bb_func)
+                })
+            }
+            // Brillig and memory opcodes are left for the ACVM to solve as the witnesses which they use
+            // remain assigned by their definitions.
+            Opcode::Brillig(_) | Opcode::Block(_) | Opcode::ROM(_) | Opcode::RAM(_) => Ok(None),
+        }
+    }
+
+    /// Checks that the known inputs of `bb_func` fit within their declared number of bits.
+    ///
+    /// The backend implicitly constrains the size of each input, which would be lost by replacing the call
+    /// with the values of its outputs.
+    fn check_input_sizes(&self, bb_func: &BlackBoxFuncCall) -> Result<(), OpcodeResolutionError> {
+        for input in bb_func.get_inputs_vec() {
+            match self.known.get(&input.witness) {
+                Some(value) if value.num_bits() > input.num_bits => {
+                    return Err(OpcodeResolutionError::UnsatisfiedConstrain {
+                        opcode_label: OpcodeLabel::Unresolved,
+                    });
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn fold_arithmetic(
+        &mut self,
+        expr: &Expression,
+    ) -> Result<Option<Fold>, OpcodeResolutionError> {
+        let evaluated = ArithmeticSolver::evaluate(expr, &self.known);
+        if let Some(value) = evaluated.to_const() {
+            return if value.is_zero() {
+                Ok(Some(Fold::Solved(Vec::new())))
+            } else {
+                Err(OpcodeResolutionError::UnsatisfiedConstrain {
+                    opcode_label: OpcodeLabel::Unresolved,
+                })
+            };
+        }
+
+        if evaluated.is_degree_one_univariate() {
+            let (coefficient, witness) = evaluated.linear_combinations[0];
+            self.known.insert(witness, -evaluated.q_c / coefficient);
+            return Ok(Some(Fold::Solved(vec![witness])));
+        }
+
+        Ok(Some(Fold::Simplified(evaluated)))
+    }
+
+    /// Runs the ACVM solver for an opcode against the witnesses solved so far, recording any new
+    /// witnesses which it solves.
+    fn fold_with(
+        &mut self,
+        solve: impl FnOnce(&mut WitnessMap) -> Result<OpcodeResolution, OpcodeResolutionError>,
+    ) -> Result<Option<Fold>, OpcodeResolutionError> {
+        let mut witness_map = self.known.clone();
+        match solve(&mut witness_map) {
+            Ok(OpcodeResolution::Solved) => {
+                let solved: Vec<Witness> = witness_map
+                    .clone()
+                    .into_iter()
+                    .filter(|(witness, _)| !self.known.contains_key(witness))
+                    .map(|(witness, _)| witness)
+                    .collect();
+                self.known = witness_map;
+                Ok(Some(Fold::Solved(solved)))
+            }
+            // The backend is required to evaluate this function so it must be left in the circuit.
+            Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(_)) | Ok(_) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns an arithmetic opcode which constrains `witness` to equal its solved value.
+    fn define(&self, witness: Witness) -> Opcode {
+        let mut expr = Expression::from(witness);
+        expr.q_c = -self.known[&witness];
+        Opcode::Arithmetic(expr)
+    }
+}
+
+/// The result of evaluating an opcode.
+enum Fold {
+    /// The arithmetic opcode was partially evaluated into a simpler expression.
+    Simplified(Expression),
+    /// The opcode was fully evaluated, solving these witnesses.
+    Solved(Vec<Witness>),
+}
+
+/// A [`BlackBoxFunctionSolver`] which refuses to evaluate any function which requires the backend,
+/// as there is none available at compile time.
+struct CompileTimeBlackBoxSolver;
+
+impl BlackBoxFunctionSolver for CompileTimeBlackBoxSolver {
+    fn schnorr_verify(
+        &self,
+        _public_key_x: &FieldElement,
+        _public_key_y: &FieldElement,
+        _signature: &[u8],
+        _message: &[u8],
+    ) -> Result<bool, OpcodeResolutionError> {
+        Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(BlackBoxFunc::SchnorrVerify))
+    }
+
+    fn pedersen(
+        &self,
+        _inputs: &[FieldElement],
+        _domain_separator: u32,
+    ) -> Result<(FieldElement, FieldElement), OpcodeResolutionError> {
+        Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(BlackBoxFunc::Pedersen))
+    }
+
+    fn fixed_base_scalar_mul(
+        &self,
+        _input: &FieldElement,
+    ) -> Result<(FieldElement, FieldElement), OpcodeResolutionError> {
+        Err(OpcodeResolutionError::UnsupportedBlackBoxFunc(BlackBoxFunc::FixedBaseScalarMul))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::{
+        circuit::{
+            directives::Directive,
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Circuit, Opcode, OpcodeLabel,
+        },
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use super::ConstantPropagator;
+    use crate::compiler::CompileError;
+
+    /// Returns an opcode constraining `witness` to equal `value`.
+    fn assign(witness: u32, value: i128) -> Opcode {
+        let mut expr = Expression::from(Witness(witness));
+        expr.q_c = -FieldElement::from(value);
+        Opcode::Arithmetic(expr)
+    }
+
+    fn range(witness: u32, num_bits: u32) -> Opcode {
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+            input: FunctionInput { witness: Witness(witness), num_bits },
+        })
+    }
+
+    fn propagate(opcodes: Vec<Opcode>) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let circuit = Circuit { current_witness_index: 10, opcodes, ..Circuit::default() };
+        let opcode_labels = circuit.initial_opcode_labels();
+        ConstantPropagator::new().propagate(circuit, opcode_labels)
+    }
+
+    #[test]
+    fn folds_constants_through_the_circuit() {
+        let square = {
+            let mut expr = Expression::from(Witness(2));
+            expr.push_multiplication_term(-FieldElement::one(), Witness(1), Witness(1));
+            Opcode::Arithmetic(expr)
+        };
+        let sum = {
+            let mut expr = Expression::default();
+            for witness in [2, 3, 4] {
+                expr.push_addition_term(FieldElement::one(), Witness(witness));
+            }
+            Opcode::Arithmetic(expr)
+        };
+        let invert = Opcode::Directive(Directive::Invert { x: Witness(1), result: Witness(5) });
+
+        // The square is defined after its first use to check that values propagate backwards.
+        let (circuit, opcode_labels) =
+            propagate(vec![sum, range(2, 8), square, assign(1, 2), invert]).unwrap();
+
+        let mut remaining_sum = Expression::default();
+        remaining_sum.push_addition_term(FieldElement::one(), Witness(3));
+        remaining_sum.push_addition_term(FieldElement::one(), Witness(4));
+        remaining_sum.q_c = FieldElement::from(4_i128);
+        let inverse = {
+            let mut expr = Expression::from(Witness(5));
+            expr.q_c = -FieldElement::from(2_i128).inverse();
+            Opcode::Arithmetic(expr)
+        };
+        assert_eq!(
+            circuit.opcodes,
+            [Opcode::Arithmetic(remaining_sum), assign(2, 4), assign(1, 2), inverse]
+        );
+        assert_eq!(opcode_labels, [0, 2, 3, 4].map(OpcodeLabel::Resolved));
+    }
+
+    #[test]
+    fn evaluates_hashes_of_constants() {
+        let sha256 = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::SHA256 {
+            inputs: vec![FunctionInput { witness: Witness(1), num_bits: 8 }],
+            outputs: (2..34).map(Witness).collect(),
+        });
+        let (circuit, opcode_labels) = propagate(vec![assign(1, 0x61), sha256]).unwrap();
+
+        // sha256("a")
+        let expected_digest = [
+            0xca, 0x97, 0x81, 0x12, 0xca, 0x1b, 0xbd, 0xca, 0xfa, 0xc2, 0x31, 0xb3, 0x9a, 0x23,
+            0xdc, 0x4d, 0xa7, 0x86, 0xef, 0xf8, 0x14, 0x7c, 0x4e, 0x72, 0xb9, 0x80, 0x77, 0x85,
+            0xaf, 0xee, 0x48, 0xbb,
+        ];
+        let mut expected_opcodes = vec![assign(1, 0x61)];
+        expected_opcodes
+            .extend((2..34).zip(expected_digest).map(|(witness, byte)| assign(witness, byte)));
+        assert_eq!(circuit.opcodes, expected_opcodes);
+        assert!(opcode_labels[1..].iter().all(|label| *label == OpcodeLabel::Resolved(1)));
+    }
+
+    #[test]
+    fn keeps_functions_which_require_the_backend() {
+        let pedersen = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Pedersen {
+            inputs: vec![FunctionInput { witness: Witness(1), num_bits: 254 }],
+            domain_separator: 0,
+            outputs: (Witness(2), Witness(3)),
+        });
+        let (circuit, _) = propagate(vec![assign(1, 1), pedersen.clone()]).unwrap();
+        assert_eq!(circuit.opcodes, [assign(1, 1), pedersen]);
+    }
+
+    #[test]
+    fn reports_unsatisfiable_constraints() {
+        assert_eq!(
+            propagate(vec![assign(1, 2), assign(1, 3)]).unwrap_err(),
+            CompileError::UnsatisfiableConstraint { opcode_label: OpcodeLabel::Resolved(1) }
+        );
+        assert_eq!(
+            propagate(vec![range(1, 8), assign(1, 256)]).unwrap_err(),
+            CompileError::UnsatisfiableConstraint { opcode_label: OpcodeLabel::Resolved(0) }
+        );
+    }
+
+    #[test]
+    fn reports_constant_black_box_inputs_which_are_out_of_range() {
+        let and = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::AND {
+            lhs: FunctionInput { witness: Witness(1), num_bits: 4 },
+            rhs: FunctionInput { witness: Witness(2), num_bits: 4 },
+            output: Witness(3),
+        });
+        assert_eq!(
+            propagate(vec![assign(1, 16), assign(2, 1), and]).unwrap_err(),
+            CompileError::UnsatisfiableConstraint { opcode_label: OpcodeLabel::Resolved(2) }
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeSet;
+
+use acir::{
+    brillig_vm,
+    circuit::{
+        brillig::BrilligOutputs,
+        directives::{Directive, QuotientDirective},
+        Circuit, Opcode, OpcodeLabel,
+    },
+    native_types::Witness,
+};
+
+/// `DeadCodeOptimizer` removes unconstrained computations whose results are never used.
+///
+/// A witness is live if it is a public input or it is used by a constraint. Any [`Directive`] or
+/// [`Brillig`][acir::circuit::brillig::Brillig] opcode which does not write to a live witness can be
+/// removed, after which the witnesses it reads are no longer live unless used elsewhere.
+///
+/// [`Directive::Log`] opcodes, Brillig opcodes without outputs and Brillig opcodes which make foreign calls
+/// are always kept as they may be run for their side effects.
+///
+/// The positions of [assertion messages][Circuit::assert_messages] are left as they are, to be updated
+/// from the returned opcode labels.
+pub(crate) struct DeadCodeOptimizer {
+    circuit: Circuit,
+}
+
+impl DeadCodeOptimizer {
+    pub(crate) fn new(circuit: Circuit) -> Self {
+        Self { circuit }
+    }
+
+    /// Returns the circuit with any dead opcodes removed, along with the labels of the remaining opcodes.
+    pub(crate) fn eliminate_dead_code(
+        self,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> (Circuit, Vec<OpcodeLabel>) {
+        let opcode_witnesses: Vec<BTreeSet<Witness>> =
+            self.circuit.opcodes.iter().map(Opcode::witnesses).collect();
+
+        let mut live_witnesses: BTreeSet<Witness> = self.circuit.public_inputs().0;
+        let mut live_opcodes: Vec<bool> = vec![false; self.circuit.opcodes.len()];
+        for (index, opcode) in self.circuit.opcodes.iter().enumerate() {
+            if unconstrained_outputs(opcode).map_or(true, |outputs| outputs.is_empty()) {
+                live_opcodes[index] = true;
+                live_witnesses.extend(&opcode_witnesses[index]);
+            }
+        }
+
+        // Keeping an unconstrained opcode makes its inputs live, which may in turn keep other
+        // unconstrained opcodes alive so iterate until nothing changes.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, opcode) in self.circuit.opcodes.iter().enumerate() {
+                if live_opcodes[index] {
+                    continue;
+                }
+                let outputs = unconstrained_outputs(opcode).unwrap_or_default();
+                if outputs.iter().any(|output| live_witnesses.contains(output)) {
+                    live_opcodes[index] = true;
+                    live_witnesses.extend(&opcode_witnesses[index]);
+                    changed = true;
+                }
+            }
+        }
+
+        let (opcodes, new_opcode_labels) = self
+            .circuit
+            .opcodes
+            .into_iter()
+            .zip(opcode_labels)
+            .zip(live_opcodes)
+            .filter_map(|(opcode_and_label, is_live)| is_live.then_some(opcode_and_label))
+            .unzip();
+
+        (Circuit { opcodes, ..self.circuit }, new_opcode_labels)
+    }
+}
+
+/// Returns the witnesses written by `opcode` if it does not constrain them.
+fn unconstrained_outputs(opcode: &Opcode) -> Option<Vec<Witness>> {
+    match opcode {
+        Opcode::Directive(Directive::Invert { result, .. }) => Some(vec![*result]),
+        Opcode::Directive(Directive::Quotient(QuotientDirective { q, r, .. })) => {
+            Some(vec![*q, *r])
+        }
+        Opcode::Directive(Directive::ToLeRadix { b, .. }) => Some(b.clone()),
+        Opcode::Directive(Directive::PermutationSort { bits, .. }) => Some(bits.clone()),
+        // Foreign calls may have side effects, such as printing, which the executor relies on seeing.
+        Opcode::Brillig(brillig)
+            if brillig
+                .bytecode
+                .iter()
+                .any(|opcode| matches!(opcode, brillig_vm::Opcode::ForeignCall { .. })) =>
+        {
+            None
+        }
+        Opcode::Brillig(brillig) => Some(
+            brillig
+                .outputs
+                .iter()
+                .flat_map(|output| match output {
+                    BrilligOutputs::Simple(witness) => vec![*witness],
+                    BrilligOutputs::Array(witnesses) => witnesses.clone(),
+                })
+                .collect(),
+        ),
+        // Logs are only run for their side effects so are never dead.
+        Opcode::Directive(Directive::Log(_))
+        | Opcode::Arithmetic(_)
+        | Opcode::BlackBoxFuncCall(_)
+        | Opcode::Block(_)
+        | Opcode::ROM(_)
+        | Opcode::RAM(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::{
+        brillig_vm,
+        circuit::{
+            brillig::{Brillig, BrilligInputs, BrilligOutputs},
+            directives::{Directive, LogInfo},
+            Circuit, Opcode, OpcodeLabel, OpcodeLocation, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+    };
+
+    use super::DeadCodeOptimizer;
+    use crate::compiler::eliminate_dead_code;
+
+    fn invert(x: u32, result: u32) -> Opcode {
+        Opcode::Directive(Directive::Invert { x: Witness(x), result: Witness(result) })
+    }
+
+    fn brillig(input: u32, output: u32) -> Opcode {
+        Opcode::Brillig(Brillig {
+            inputs: vec![BrilligInputs::Single(Witness(input).into())],
+            outputs: vec![BrilligOutputs::Simple(Witness(output))],
+            foreign_call_results: Vec::new(),
+            bytecode: Vec::new(),
+            predicate: None,
+        })
+    }
+
+    fn assert_equal(lhs: u32, rhs: u32) -> Opcode {
+        Opcode::Arithmetic(&Expression::from(Witness(lhs)) - &Expression::from(Witness(rhs)))
+    }
+
+    #[test]
+    fn removes_unused_unconstrained_opcodes() {
+        let acir = Circuit {
+            current_witness_index: 6,
+            opcodes: vec![
+                invert(1, 2),
+                // Duplicate of the previous inversion whose result is never used.
+                invert(1, 3),
+                // Writes to a witness which is never read.
+                brillig(4, 6),
+                brillig(2, 5),
+                assert_equal(2, 5),
+                Opcode::Directive(Directive::Log(LogInfo::WitnessOutput(vec![Witness(2)]))),
+            ],
+            public_parameters: PublicInputs([Witness(1)].into()),
+            return_values: PublicInputs::default(),
+            assert_messages: vec![
+                (OpcodeLocation::Acir(2), "unused".to_owned()),
+                (OpcodeLocation::Acir(4), "not equal".to_owned()),
+            ],
+        };
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let (optimized, opcode_labels) =
+            DeadCodeOptimizer::new(acir.clone()).eliminate_dead_code(opcode_labels);
+        assert_eq!(
+            optimized.opcodes,
+            [
+                acir.opcodes[0].clone(),
+                acir.opcodes[3].clone(),
+                acir.opcodes[4].clone(),
+                acir.opcodes[5].clone()
+            ]
+        );
+        assert_eq!(opcode_labels, [0, 3, 4, 5].map(OpcodeLabel::Resolved));
+        assert_eq!(optimized.assert_messages, acir.assert_messages);
+        assert_eq!(optimized.current_witness_index, acir.current_witness_index);
+
+        // Assertion messages follow their opcodes when dead code is eliminated through the compiler.
+        let opcode_labels = acir.initial_opcode_labels();
+        let (optimized, _, _) = eliminate_dead_code(acir, opcode_labels, false);
+        assert_eq!(optimized.assert_messages, [(OpcodeLocation::Acir(2), "not equal".to_owned())]);
+    }
+
+    #[test]
+    fn keeps_brillig_opcodes_which_make_foreign_calls() {
+        let mut print = brillig(1, 2);
+        if let Opcode::Brillig(brillig) = &mut print {
+            brillig.bytecode = vec![brillig_vm::Opcode::ForeignCall {
+                function: "print".to_owned(),
+                destinations: vec![],
+                inputs: vec![brillig_vm::RegisterOrMemory::RegisterIndex(0.into())],
+            }];
+        }
+        let acir = Circuit {
+            current_witness_index: 2,
+            opcodes: vec![print],
+            public_parameters: PublicInputs([Witness(1)].into()),
+            return_values: PublicInputs::default(),
+            assert_messages: Vec::new(),
+        };
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let (optimized, _) =
+            DeadCodeOptimizer::new(acir.clone()).eliminate_dead_code(opcode_labels);
+        assert_eq!(optimized, acir);
+    }
+
+    #[test]
+    fn keeps_unconstrained_opcodes_which_feed_live_witnesses() {
+        // Witness 3 is computed by a chain of unconstrained opcodes before being returned.
+        let acir = Circuit {
+            current_witness_index: 3,
+            opcodes: vec![invert(1, 2), brillig(2, 3)],
+            public_parameters: PublicInputs::default(),
+            return_values: PublicInputs([Witness(3)].into()),
+            assert_messages: Vec::new(),
+        };
+        let opcode_labels = acir.initial_opcode_labels();
+
+        let (optimized, new_opcode_labels) =
+            DeadCodeOptimizer::new(acir.clone()).eliminate_dead_code(opcode_labels.clone());
+        assert_eq!(optimized, acir);
+        assert_eq!(new_opcode_labels, opcode_labels);
+    }
+}
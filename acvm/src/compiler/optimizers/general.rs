@@ -32,7 +32,7 @@ fn simplify_mul_terms(mut gate: Expression) -> Expression {
 
     // Canonicalize the ordering of the multiplication, lets just order by variable name
     for (scale, w_l, w_r) in gate.mul_terms.clone().into_iter() {
-        let mut pair = vec![w_l, w_r];
+        let mut pair = [w_l, w_r];
         // Sort using rust sort algorithm
         pair.sort();
 
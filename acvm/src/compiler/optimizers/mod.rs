@@ -1,7 +1,13 @@
+mod common_subexpression;
+mod constant_propagation;
+mod dead_code;
 mod general;
 mod redundant_range;
 mod simplify;
 
+pub(crate) use common_subexpression::CommonSubexpressionOptimizer;
+pub(crate) use constant_propagation::ConstantPropagator;
+pub(crate) use dead_code::DeadCodeOptimizer;
 pub(crate) use general::GeneralOptimizer;
 pub(crate) use redundant_range::RangeOptimizer;
 // Public as these need to be passed to `acvm::compiler::compile()`
@@ -107,7 +107,7 @@ impl CircuitSimplifier {
     }
 
     // Simplify a gate and propagate the solved witness onto the previous gates, as long as it can solve some witness
-    pub fn simplify(&mut self, gates: &mut Vec<Opcode>) -> SimplifyResult {
+    pub fn simplify(&mut self, gates: &mut [Opcode]) -> SimplifyResult {
         let mut first = true;
         let mut solved = true;
         self.min_use = gates.len() - 1;
@@ -265,7 +265,6 @@ impl CircuitSimplifier {
         gate_idx: usize,
         first: bool,
     ) -> SimplifyResult {
-        if result.0 == 44 {}
         self.use_witness(result, gate_idx, first);
         if let Some(f) = self.solved.get(&x) {
             let result_value = if f.is_zero() { FieldElement::zero() } else { f.inverse() };
@@ -475,8 +474,7 @@ mod tests {
         assert_eq!(circuit.len(), 3);
         assert_eq!(simplifier.solved_gates.len(), 1);
         let support_all = |_opcode: &Opcode| true;
-        let mut acir = Circuit::default();
-        acir.opcodes = circuit;
+        let acir = Circuit { opcodes: circuit, ..Circuit::default() };
         let opcode_labels = acir.initial_opcode_labels();
         let (acir, _) =
             FallbackTransformer::transform(acir, support_all, &simplifier, opcode_labels).unwrap();
@@ -0,0 +1,460 @@
+use acir::circuit::{transform_opcode_locations, Circuit, Opcode, OpcodeLabel};
+
+use super::{
+    optimizers::CircuitSimplifier,
+    passes::{
+        CSatPass, ConstantPropagationPass, CsePass, DeadCodePass, FallbackPass,
+        GeneralOptimizerPass, R1CSPass, RangeOptimizerPass,
+    },
+    CompileError,
+};
+use crate::Language;
+
+/// A single transformation which is applied to a [`Circuit`] during compilation.
+pub trait Pass {
+    /// Name used to identify the pass within a [`PassManager`].
+    fn name(&self) -> &str;
+
+    /// Transforms `circuit`, returning the new circuit along with the label of each of its opcodes.
+    ///
+    /// `opcode_labels` holds a label for each opcode in `circuit`. Any opcodes which are derived
+    /// from an existing opcode should inherit its label so that they can be mapped back to the
+    /// original circuit.
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError>;
+}
+
+/// Summary of the changes made to a [`Circuit`] by a single [`Pass`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassStatistics {
+    pub name: String,
+    pub opcodes_before: usize,
+    pub opcodes_after: usize,
+    /// Number of witnesses which were added by the pass.
+    pub witnesses_added: u32,
+}
+
+struct RegisteredPass<'a> {
+    pass: Box<dyn Pass + 'a>,
+    enabled: bool,
+}
+
+/// An ordered list of [`Pass`]es which are applied to a [`Circuit`] in turn.
+#[derive(Default)]
+pub struct PassManager<'a> {
+    passes: Vec<RegisteredPass<'a>>,
+}
+
+impl<'a> PassManager<'a> {
+    /// Creates a [`PassManager`] with no passes registered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`PassManager`] with the passes run by [`compile`][super::compile].
+    ///
+    /// These are, in order:
+    /// - `fallback`: replaces unsupported black box functions with arithmetic fallbacks.
+    /// - `general_optimizer`: removes zero terms and merges quadratic terms in each expression.
+    /// - `redundant_range`: removes range constraints implied by stricter ones on the same witness.
+    /// - `r1cs` or `csat`: reduces opcodes to fit the backend's [`Language`].
+    pub fn with_default_passes(
+        np_language: Language,
+        is_opcode_supported: &'a dyn Fn(&Opcode) -> bool,
+        simplifier: &'a CircuitSimplifier,
+    ) -> Self {
+        let mut manager = Self::new();
+        manager.add_pass(FallbackPass { is_opcode_supported, simplifier });
+        manager.add_pass(GeneralOptimizerPass);
+        manager.add_pass(RangeOptimizerPass);
+        manager.add_reduction_pass(np_language);
+        manager
+    }
+
+    /// Creates a [`PassManager`] with the default passes along with optimizations which change the number
+    /// of opcodes and the witnesses used by the compiled circuit.
+    ///
+    /// These are, in order:
+    /// - `fallback`, `general_optimizer`: as in [`PassManager::with_default_passes`].
+    /// - `constant_propagation`: evaluates opcodes which do not depend on the inputs of the circuit.
+    /// - `redundant_range`: as in [`PassManager::with_default_passes`].
+    /// - `dead_code`: removes directives and Brillig opcodes whose outputs are never used. Brillig opcodes
+    ///   which make foreign calls are kept for their side effects.
+    /// - `cse`: removes duplicate arithmetic opcodes. For [`Language::PLONKCSat`] it also shares common
+    ///   sub-expressions between them where this reduces the number of gates produced by `csat`.
+    /// - `r1cs` or `csat`: reduces opcodes to fit the backend's [`Language`].
+    pub fn with_optimizing_passes(
+        np_language: Language,
+        is_opcode_supported: &'a dyn Fn(&Opcode) -> bool,
+        simplifier: &'a CircuitSimplifier,
+    ) -> Self {
+        let mut manager = Self::new();
+        manager.add_pass(FallbackPass { is_opcode_supported, simplifier });
+        manager.add_pass(GeneralOptimizerPass);
+        manager.add_pass(ConstantPropagationPass);
+        manager.add_pass(RangeOptimizerPass);
+        manager.add_pass(DeadCodePass);
+        manager.add_pass(CsePass {
+            width: match np_language {
+                Language::R1CS => None,
+                Language::PLONKCSat { width } => Some(width),
+            },
+        });
+        manager.add_reduction_pass(np_language);
+        manager
+    }
+
+    /// Appends the pass which reduces opcodes to fit the backend's [`Language`].
+    fn add_reduction_pass(&mut self, np_language: Language) {
+        match np_language {
+            Language::R1CS => self.add_pass(R1CSPass),
+            Language::PLONKCSat { width } => self.add_pass(CSatPass { width }),
+        }
+    }
+
+    /// Appends `pass` to the end of the pipeline.
+    pub fn add_pass(&mut self, pass: impl Pass + 'a) {
+        self.passes.push(RegisteredPass { pass: Box::new(pass), enabled: true });
+    }
+
+    /// Inserts `pass` at position `index` in the pipeline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of registered passes.
+    pub fn insert_pass(&mut self, index: usize, pass: impl Pass + 'a) {
+        self.passes.insert(index, RegisteredPass { pass: Box::new(pass), enabled: true });
+    }
+
+    /// Returns the position of the first pass named `name`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|registered| registered.pass.name() == name)
+    }
+
+    /// Removes the first pass named `name` from the pipeline, returning it so it may be reinserted elsewhere.
+    pub fn remove_pass(&mut self, name: &str) -> Option<Box<dyn Pass + 'a>> {
+        let index = self.position(name)?;
+        Some(self.passes.remove(index).pass)
+    }
+
+    /// Sets whether the passes named `name` are run. Returns `false` if no such pass is registered.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for registered in self.passes.iter_mut().filter(|registered| registered.pass.name() == name)
+        {
+            registered.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Returns the names of the registered passes in the order in which they are run.
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|registered| registered.pass.name()).collect()
+    }
+
+    /// Runs each enabled pass over `acir` in turn.
+    ///
+    /// Returns the transformed circuit, the label of each of its opcodes and statistics for each pass which was run.
+    pub fn run(
+        &self,
+        acir: Circuit,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>, Vec<PassStatistics>), CompileError> {
+        let mut opcode_labels = acir.initial_opcode_labels();
+        let assert_messages = acir.assert_messages.clone();
+        let mut acir = acir;
+        let mut statistics = Vec::new();
+
+        for registered in self.passes.iter().filter(|registered| registered.enabled) {
+            let pass = &registered.pass;
+            let opcodes_before = acir.opcodes.len();
+            let witness_index_before = acir.current_witness_index;
+
+            (acir, opcode_labels) = pass.run(acir, opcode_labels)?;
+            if acir.opcodes.len() != opcode_labels.len() {
+                return Err(CompileError::OpcodeLabelMismatch {
+                    pass: pass.name().to_owned(),
+                    opcodes: acir.opcodes.len(),
+                    labels: opcode_labels.len(),
+                });
+            }
+            acir.assert_messages = transform_opcode_locations(&assert_messages, &opcode_labels);
+
+            statistics.push(PassStatistics {
+                name: pass.name().to_owned(),
+                opcodes_before,
+                opcodes_after: acir.opcodes.len(),
+                witnesses_added: acir.current_witness_index.saturating_sub(witness_index_before),
+            });
+        }
+
+        Ok((acir, opcode_labels, statistics))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::{
+        circuit::{
+            opcodes::{BlackBoxFuncCall, FunctionInput},
+            Circuit, Opcode, OpcodeLabel, OpcodeLocation,
+        },
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use super::{Pass, PassManager, PassStatistics};
+    use crate::{
+        compiler::{optimizers::CircuitSimplifier, CompileError},
+        Language,
+    };
+
+    /// Appends an opcode constraining a fresh witness to zero.
+    struct AppendWitness;
+
+    impl Pass for AppendWitness {
+        fn name(&self) -> &str {
+            "append_witness"
+        }
+
+        fn run(
+            &self,
+            mut circuit: Circuit,
+            mut opcode_labels: Vec<OpcodeLabel>,
+        ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+            circuit.current_witness_index += 1;
+            let witness = Witness(circuit.current_witness_index);
+            circuit.opcodes.push(Opcode::Arithmetic(Expression::from(witness)));
+            opcode_labels.push(OpcodeLabel::Unresolved);
+            Ok((circuit, opcode_labels))
+        }
+    }
+
+    /// Drops every opcode without updating the labels.
+    struct ForgetLabels;
+
+    impl Pass for ForgetLabels {
+        fn name(&self) -> &str {
+            "forget_labels"
+        }
+
+        fn run(
+            &self,
+            circuit: Circuit,
+            opcode_labels: Vec<OpcodeLabel>,
+        ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+            Ok((Circuit { opcodes: Vec::new(), ..circuit }, opcode_labels))
+        }
+    }
+
+    fn test_circuit() -> Circuit {
+        let range = |num_bits| {
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                input: FunctionInput { witness: Witness(1), num_bits },
+            })
+        };
+        let mut expr = Expression::default();
+        for witness in 1..=4 {
+            expr.push_addition_term(FieldElement::one(), Witness(witness));
+        }
+        Circuit {
+            current_witness_index: 4,
+            opcodes: vec![range(32), Opcode::Arithmetic(expr), range(16)],
+            ..Circuit::default()
+        }
+    }
+
+    #[test]
+    fn default_passes() {
+        let simplifier = CircuitSimplifier::new(0);
+        let is_opcode_supported = |_: &Opcode| true;
+
+        let manager =
+            PassManager::with_default_passes(Language::R1CS, &is_opcode_supported, &simplifier);
+        assert_eq!(
+            manager.pass_names(),
+            ["fallback", "general_optimizer", "redundant_range", "r1cs"]
+        );
+
+        let manager = PassManager::with_default_passes(
+            Language::PLONKCSat { width: 3 },
+            &is_opcode_supported,
+            &simplifier,
+        );
+        assert_eq!(
+            manager.pass_names(),
+            ["fallback", "general_optimizer", "redundant_range", "csat"]
+        );
+
+        let (circuit, opcode_labels, statistics) = manager.run(test_circuit()).unwrap();
+        assert_eq!(circuit.opcodes.len(), opcode_labels.len());
+
+        let range_statistics = &statistics[2];
+        assert_eq!(range_statistics.opcodes_before, 3);
+        assert_eq!(range_statistics.opcodes_after, 2);
+
+        // Splitting the four term expression into width 3 requires an intermediate variable.
+        let csat_statistics = &statistics[3];
+        assert_eq!(csat_statistics.opcodes_after, 3);
+        assert!(csat_statistics.witnesses_added > 0);
+        assert_eq!(
+            opcode_labels,
+            [OpcodeLabel::Resolved(1), OpcodeLabel::Resolved(1), OpcodeLabel::Resolved(2)]
+        );
+    }
+
+    #[test]
+    fn optimizing_passes() {
+        let simplifier = CircuitSimplifier::new(0);
+        let is_opcode_supported = |_: &Opcode| true;
+
+        let manager =
+            PassManager::with_optimizing_passes(Language::R1CS, &is_opcode_supported, &simplifier);
+        assert_eq!(
+            manager.pass_names(),
+            [
+                "fallback",
+                "general_optimizer",
+                "constant_propagation",
+                "redundant_range",
+                "dead_code",
+                "cse",
+                "r1cs"
+            ]
+        );
+
+        let manager = PassManager::with_optimizing_passes(
+            Language::PLONKCSat { width: 3 },
+            &is_opcode_supported,
+            &simplifier,
+        );
+        assert_eq!(
+            manager.pass_names(),
+            [
+                "fallback",
+                "general_optimizer",
+                "constant_propagation",
+                "redundant_range",
+                "dead_code",
+                "cse",
+                "csat"
+            ]
+        );
+
+        // None of the additional passes apply to the test circuit, so it compiles as with the default passes.
+        let (circuit, opcode_labels, _) = manager.run(test_circuit()).unwrap();
+        let default_manager = PassManager::with_default_passes(
+            Language::PLONKCSat { width: 3 },
+            &is_opcode_supported,
+            &simplifier,
+        );
+        assert_eq!((circuit, opcode_labels), {
+            let (circuit, opcode_labels, _) = default_manager.run(test_circuit()).unwrap();
+            (circuit, opcode_labels)
+        });
+    }
+
+    #[test]
+    fn sharing_subexpressions_never_grows_the_circuit() {
+        let simplifier = CircuitSimplifier::new(0);
+        let is_opcode_supported = |_: &Opcode| true;
+        let linear = |terms: &[(i128, u32)]| {
+            let mut expr = Expression::default();
+            for &(coefficient, witness) in terms {
+                expr.push_addition_term(FieldElement::from(coefficient), Witness(witness));
+            }
+            Opcode::Arithmetic(expr)
+        };
+        // Both opcodes fit into a single gate of width 3 despite sharing x1 + x2.
+        let circuit = Circuit {
+            current_witness_index: 4,
+            opcodes: vec![linear(&[(1, 1), (1, 2), (-1, 3)]), linear(&[(2, 1), (2, 2), (-1, 4)])],
+            ..Circuit::default()
+        };
+
+        let mut manager = PassManager::with_optimizing_passes(
+            Language::PLONKCSat { width: 3 },
+            &is_opcode_supported,
+            &simplifier,
+        );
+        let (with_cse, _, _) = manager.run(circuit.clone()).unwrap();
+        manager.set_enabled("cse", false);
+        let (without_cse, _, _) = manager.run(circuit).unwrap();
+        assert_eq!(with_cse, without_cse);
+    }
+
+    #[test]
+    fn custom_passes_can_be_inserted_and_disabled() {
+        let mut manager = PassManager::new();
+        manager.add_pass(AppendWitness);
+        manager.insert_pass(0, AppendWitness);
+
+        let (circuit, opcode_labels, statistics) = manager.run(test_circuit()).unwrap();
+        assert_eq!(circuit.current_witness_index, 6);
+        assert_eq!(opcode_labels.len(), 5);
+        assert_eq!(
+            statistics[1],
+            PassStatistics {
+                name: "append_witness".to_owned(),
+                opcodes_before: 4,
+                opcodes_after: 5,
+                witnesses_added: 1,
+            }
+        );
+
+        assert!(manager.set_enabled("append_witness", false));
+        assert!(!manager.set_enabled("unknown", false));
+        let (circuit, _, statistics) = manager.run(test_circuit()).unwrap();
+        assert_eq!(circuit, test_circuit());
+        assert!(statistics.is_empty());
+
+        assert!(manager.remove_pass("append_witness").is_some());
+        assert_eq!(manager.pass_names(), ["append_witness"]);
+    }
+
+    #[test]
+    fn moves_assert_messages_with_their_opcodes() {
+        let simplifier = CircuitSimplifier::new(0);
+        let is_opcode_supported = |_: &Opcode| true;
+        let manager = PassManager::with_default_passes(
+            Language::PLONKCSat { width: 3 },
+            &is_opcode_supported,
+            &simplifier,
+        );
+
+        let circuit = Circuit {
+            assert_messages: vec![
+                (OpcodeLocation::Acir(0), "too large".to_owned()),
+                (OpcodeLocation::Acir(1), "bad sum".to_owned()),
+            ],
+            ..test_circuit()
+        };
+        let (circuit, opcode_labels, _) = manager.run(circuit).unwrap();
+
+        // The 32 bit range constraint is implied by the 16 bit one so is removed, while the sum
+        // is split across multiple opcodes which each report its message.
+        assert_eq!(opcode_labels, [1, 1, 2].map(OpcodeLabel::Resolved));
+        assert_eq!(
+            circuit.assert_messages,
+            [(OpcodeLocation::Acir(0), "bad sum"), (OpcodeLocation::Acir(1), "bad sum")]
+                .map(|(location, message)| (location, message.to_owned()))
+        );
+    }
+
+    #[test]
+    fn rejects_passes_which_lose_labels() {
+        let mut manager = PassManager::new();
+        manager.add_pass(ForgetLabels);
+        assert_eq!(
+            manager.run(test_circuit()).unwrap_err(),
+            CompileError::OpcodeLabelMismatch {
+                pass: "forget_labels".to_owned(),
+                opcodes: 0,
+                labels: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! [`Pass`] implementations for the transformers and optimizers which make up the pipelines of [`PassManager`][super::PassManager].
+
+use acir::circuit::{Circuit, Opcode, OpcodeLabel};
+
+use super::{
+    optimizers::{
+        CircuitSimplifier, CommonSubexpressionOptimizer, ConstantPropagator, DeadCodeOptimizer,
+        GeneralOptimizer, RangeOptimizer,
+    },
+    pass_manager::Pass,
+    transformers::{CSatTransformer, FallbackTransformer, R1CSTransformer},
+    CompileError,
+};
+
+pub(super) struct FallbackPass<'a> {
+    pub(super) is_opcode_supported: &'a dyn Fn(&Opcode) -> bool,
+    pub(super) simplifier: &'a CircuitSimplifier,
+}
+
+impl Pass for FallbackPass<'_> {
+    fn name(&self) -> &str {
+        "fallback"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        FallbackTransformer::transform(
+            circuit,
+            self.is_opcode_supported,
+            self.simplifier,
+            opcode_labels,
+        )
+    }
+}
+
+pub(super) struct GeneralOptimizerPass;
+
+impl Pass for GeneralOptimizerPass {
+    fn name(&self) -> &str {
+        "general_optimizer"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let opcodes = circuit
+            .opcodes
+            .into_iter()
+            .map(|opcode| match opcode {
+                Opcode::Arithmetic(arith_expr) => {
+                    Opcode::Arithmetic(GeneralOptimizer::optimize(arith_expr))
+                }
+                other_gate => other_gate,
+            })
+            .collect();
+        Ok((Circuit { opcodes, ..circuit }, opcode_labels))
+    }
+}
+
+pub(super) struct ConstantPropagationPass;
+
+impl Pass for ConstantPropagationPass {
+    fn name(&self) -> &str {
+        "constant_propagation"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        ConstantPropagator::new().propagate(circuit, opcode_labels)
+    }
+}
+
+pub(super) struct RangeOptimizerPass;
+
+impl Pass for RangeOptimizerPass {
+    fn name(&self) -> &str {
+        "redundant_range"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let range_optimizer = RangeOptimizer::new(circuit);
+        Ok(range_optimizer.replace_redundant_ranges(opcode_labels))
+    }
+}
+
+pub(super) struct DeadCodePass;
+
+impl Pass for DeadCodePass {
+    fn name(&self) -> &str {
+        "dead_code"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let optimizer = DeadCodeOptimizer::new(circuit);
+        Ok(optimizer.eliminate_dead_code(opcode_labels))
+    }
+}
+
+pub(super) struct CsePass {
+    /// The width of the gates which the circuit is later split into, or `None` if only duplicate opcodes
+    /// should be removed.
+    pub(super) width: Option<usize>,
+}
+
+impl Pass for CsePass {
+    fn name(&self) -> &str {
+        "cse"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let optimizer = CommonSubexpressionOptimizer::new(self.width);
+        Ok(optimizer.optimize(circuit, opcode_labels))
+    }
+}
+
+pub(super) struct R1CSPass;
+
+impl Pass for R1CSPass {
+    fn name(&self) -> &str {
+        "r1cs"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let transformer = R1CSTransformer::new(circuit);
+        Ok((transformer.transform(), opcode_labels))
+    }
+}
+
+pub(super) struct CSatPass {
+    pub(super) width: usize,
+}
+
+impl Pass for CSatPass {
+    fn name(&self) -> &str {
+        "csat"
+    }
+
+    fn run(
+        &self,
+        circuit: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> Result<(Circuit, Vec<OpcodeLabel>), CompileError> {
+        let transformer = CSatTransformer::new(self.width);
+        Ok(transformer.transform_circuit(circuit, opcode_labels))
+    }
+}
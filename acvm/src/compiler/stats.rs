@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+
+use acir::{
+    circuit::{debug_info::DebugInfo, opcodes::MemoryBlock, Circuit, Opcode, OpcodeLocation},
+    native_types::Expression,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::transformers::CSatTransformer;
+use crate::Language;
+
+/// A summary of the size of a [`Circuit`], used to estimate the cost of proving it.
+///
+/// Statistics can be serialized, e.g. with [`CircuitStats::to_json`], in order to track changes in
+/// circuit size over time.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CircuitStats {
+    /// Total number of opcodes in the circuit.
+    pub num_opcodes: usize,
+    /// Number of opcodes with each [name][Opcode::name].
+    pub opcodes: BTreeMap<String, usize>,
+    /// Number of calls to each black box function.
+    pub black_box_functions: BTreeMap<String, usize>,
+    /// Number of arithmetic opcodes with a given number of linear terms.
+    pub fan_in: BTreeMap<usize, usize>,
+    /// Number of arithmetic opcodes with a given degree.
+    pub degree: BTreeMap<usize, usize>,
+    pub memory_blocks: Vec<MemoryBlockStats>,
+    /// Number of instructions in the bytecode of each Brillig opcode, in the order in which they appear in the circuit.
+    pub brillig_bytecode_lengths: Vec<usize>,
+    pub witnesses: WitnessStats,
+    /// Estimated number of gates needed to represent the arithmetic opcodes of the circuit.
+    ///
+    /// The cost of black box functions and memory operations depends on the backend so is not included.
+    pub estimated_arithmetic_gates: usize,
+    /// Number of opcodes produced by each line of source code, keyed by `<path>:<line>`.
+    ///
+    /// This is only collected by [`CircuitStats::with_debug_info`].
+    #[serde(default)]
+    pub opcodes_by_source_line: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryBlockStats {
+    pub id: u32,
+    /// Either `block`, `rom` or `ram`.
+    pub kind: String,
+    pub len: u32,
+    /// Number of memory operations, including initialization.
+    pub num_operations: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct WitnessStats {
+    pub current_witness_index: u32,
+    /// Number of distinct witnesses which are used by the circuit.
+    pub used: usize,
+    pub public_parameters: usize,
+    pub return_values: usize,
+}
+
+impl CircuitStats {
+    /// Collects statistics for `circuit`, estimating the number of gates needed when targeting `np_language`.
+    pub fn new(circuit: &Circuit, np_language: Language) -> Self {
+        let mut stats = CircuitStats {
+            num_opcodes: circuit.opcodes.len(),
+            witnesses: WitnessStats {
+                current_witness_index: circuit.current_witness_index,
+                used: circuit.used_witnesses().len(),
+                public_parameters: circuit.public_parameters.0.len(),
+                return_values: circuit.return_values.0.len(),
+            },
+            ..CircuitStats::default()
+        };
+
+        for opcode in &circuit.opcodes {
+            *stats.opcodes.entry(opcode.name().to_owned()).or_default() += 1;
+            match opcode {
+                Opcode::Arithmetic(expr) => {
+                    *stats.fan_in.entry(expr.linear_combinations.len()).or_default() += 1;
+                    *stats.degree.entry(degree(expr)).or_default() += 1;
+                    stats.estimated_arithmetic_gates += estimate_gates(expr, np_language);
+                }
+                Opcode::BlackBoxFuncCall(bb_func) => {
+                    *stats.black_box_functions.entry(bb_func.name().to_owned()).or_default() += 1;
+                }
+                Opcode::Block(block) | Opcode::ROM(block) | Opcode::RAM(block) => {
+                    stats.memory_blocks.push(MemoryBlockStats::new(opcode.name(), block));
+                }
+                Opcode::Brillig(brillig) => {
+                    stats.brillig_bytecode_lengths.push(brillig.bytecode.len());
+                }
+                Opcode::Directive(_) => (),
+            }
+        }
+
+        stats
+    }
+
+    /// Collects statistics as with [`CircuitStats::new`], additionally attributing each opcode to the line of
+    /// source code which produced it using `debug_info`.
+    ///
+    /// Opcodes are attributed to the innermost span of their call stack. Opcodes without debug information
+    /// are not counted.
+    pub fn with_debug_info(
+        circuit: &Circuit,
+        np_language: Language,
+        debug_info: &DebugInfo,
+    ) -> Self {
+        let mut stats = CircuitStats::new(circuit, np_language);
+        for acir_index in 0..circuit.opcodes.len() {
+            let span = debug_info
+                .source_spans(&OpcodeLocation::Acir(acir_index))
+                .and_then(|spans| spans.last());
+            if let Some(span) = span {
+                let path = debug_info.file_path(span).unwrap_or("<unknown>");
+                *stats
+                    .opcodes_by_source_line
+                    .entry(format!("{path}:{}", span.start_line))
+                    .or_default() += 1;
+            }
+        }
+        stats
+    }
+
+    /// Serializes the statistics as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("statistics should always be serializable")
+    }
+}
+
+impl MemoryBlockStats {
+    fn new(kind: &str, block: &MemoryBlock) -> Self {
+        MemoryBlockStats {
+            id: block.id.0,
+            kind: kind.to_owned(),
+            len: block.len,
+            num_operations: block.trace.len(),
+        }
+    }
+}
+
+fn degree(expr: &Expression) -> usize {
+    if !expr.mul_terms.is_empty() {
+        2
+    } else if !expr.linear_combinations.is_empty() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Estimates the number of gates needed to represent `expr` on its own.
+///
+/// Intermediate variables are not shared between expressions so this is an upper bound on the
+/// number of gates produced by compiling the circuit.
+fn estimate_gates(expr: &Expression, np_language: Language) -> usize {
+    match np_language {
+        // Each R1CS constraint can contain a single multiplication.
+        Language::R1CS => expr.mul_terms.len().max(1),
+        Language::PLONKCSat { width } => {
+            if expr.fits_in_one_identity(width) {
+                return 1;
+            }
+            let mut intermediate_variables = IndexMap::new();
+            let mut next_witness_index = 0;
+            CSatTransformer::new(width).transform(
+                expr.clone(),
+                &mut intermediate_variables,
+                &mut next_witness_index,
+            );
+            intermediate_variables.len() + 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use acir::{
+        circuit::{
+            brillig::Brillig,
+            debug_info::{DebugInfo, FileId, SourceSpan},
+            opcodes::{BlackBoxFuncCall, BlockId, FunctionInput, MemOp, MemoryBlock},
+            Circuit, Opcode, OpcodeLocation, PublicInputs,
+        },
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use super::{CircuitStats, MemoryBlockStats};
+    use crate::Language;
+
+    fn test_circuit() -> Circuit {
+        let mut wide_expr = Expression::default();
+        for witness in 1..=6 {
+            wide_expr.push_addition_term(FieldElement::one(), Witness(witness));
+        }
+        let mut mul_expr = Expression::from(Witness(3));
+        mul_expr.push_multiplication_term(FieldElement::one(), Witness(1), Witness(2));
+
+        Circuit {
+            current_witness_index: 10,
+            opcodes: vec![
+                Opcode::Arithmetic(wide_expr),
+                Opcode::Arithmetic(mul_expr),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                    input: FunctionInput { witness: Witness(1), num_bits: 8 },
+                }),
+                Opcode::ROM(MemoryBlock {
+                    id: BlockId(2),
+                    len: 1,
+                    trace: vec![MemOp {
+                        operation: Expression::one(),
+                        index: Expression::zero(),
+                        value: Witness(4).into(),
+                    }],
+                }),
+                Opcode::Brillig(Brillig {
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
+                    foreign_call_results: Vec::new(),
+                    bytecode: vec![acir::brillig_vm::Opcode::Stop],
+                    predicate: None,
+                }),
+            ],
+            public_parameters: PublicInputs([Witness(1)].into()),
+            return_values: PublicInputs([Witness(6)].into()),
+            assert_messages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn collects_statistics() {
+        let stats = CircuitStats::new(&test_circuit(), Language::PLONKCSat { width: 3 });
+
+        assert_eq!(stats.num_opcodes, 5);
+        assert_eq!(
+            stats.opcodes,
+            BTreeMap::from(
+                [("arithmetic", 2), ("brillig", 1), ("range", 1), ("rom", 1)]
+                    .map(|(name, count)| (name.to_owned(), count))
+            )
+        );
+        assert_eq!(stats.black_box_functions, BTreeMap::from([("range".to_owned(), 1)]));
+        assert_eq!(stats.fan_in, BTreeMap::from([(1, 1), (6, 1)]));
+        assert_eq!(stats.degree, BTreeMap::from([(1, 1), (2, 1)]));
+        assert_eq!(
+            stats.memory_blocks,
+            [MemoryBlockStats { id: 2, kind: "rom".to_owned(), len: 1, num_operations: 1 }]
+        );
+        assert_eq!(stats.brillig_bytecode_lengths, [1]);
+        assert_eq!(stats.witnesses.used, 6);
+        assert_eq!(stats.witnesses.current_witness_index, 10);
+
+        // Six terms are split across four gates of width three using intermediate variables.
+        assert_eq!(stats.estimated_arithmetic_gates, 4 + 1);
+        let r1cs_stats = CircuitStats::new(&test_circuit(), Language::R1CS);
+        assert_eq!(r1cs_stats.estimated_arithmetic_gates, 2);
+    }
+
+    #[test]
+    fn attributes_opcodes_to_source_lines() {
+        let span = |line| SourceSpan {
+            file: FileId(0),
+            start_line: line,
+            start_column: 1,
+            end_line: line,
+            end_column: 10,
+        };
+        let debug_info = DebugInfo {
+            files: BTreeMap::from([(FileId(0), "main.nr".to_owned())]),
+            locations: BTreeMap::from([
+                (OpcodeLocation::Acir(0), vec![span(3)]),
+                // An opcode from a function called on line 3 is attributed to the line in the callee.
+                (OpcodeLocation::Acir(1), vec![span(3), span(8)]),
+                (OpcodeLocation::Acir(2), vec![span(8)]),
+            ]),
+        };
+
+        let stats = CircuitStats::with_debug_info(
+            &test_circuit(),
+            Language::PLONKCSat { width: 3 },
+            &debug_info,
+        );
+        assert_eq!(
+            stats.opcodes_by_source_line,
+            BTreeMap::from([("main.nr:3".to_owned(), 1), ("main.nr:8".to_owned(), 2)])
+        );
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let stats = CircuitStats::new(&test_circuit(), Language::PLONKCSat { width: 3 });
+        let deserialized: CircuitStats = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(deserialized, stats);
+    }
+}
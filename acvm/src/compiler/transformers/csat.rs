@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 
 use acir::{
+    circuit::{Circuit, Opcode, OpcodeLabel},
     native_types::{Expression, Witness},
     FieldElement,
 };
@@ -27,6 +28,74 @@ impl CSatTransformer {
         CSatTransformer { width }
     }
 
+    /// Reduces every arithmetic opcode in `acir` to fit within the configured width,
+    /// creating intermediate variables where necessary.
+    pub(crate) fn transform_circuit(
+        &self,
+        acir: Circuit,
+        opcode_labels: Vec<OpcodeLabel>,
+    ) -> (Circuit, Vec<OpcodeLabel>) {
+        let mut new_opcode_labels = Vec::with_capacity(opcode_labels.len());
+        // Optimize the arithmetic gates by reducing them into the correct width and
+        // creating intermediate variables when necessary
+        let mut transformed_gates = Vec::new();
+
+        let mut next_witness_index = acir.current_witness_index + 1;
+        // maps a normalized expression to the intermediate variable which represents the expression, along with its 'norm'
+        // the 'norm' is simply the value of the first non zero coefficient in the expression, taken from the linear terms, or quadratic terms if there is none.
+        let mut intermediate_variables: IndexMap<Expression, (FieldElement, Witness)> =
+            IndexMap::new();
+        for (index, opcode) in acir.opcodes.into_iter().enumerate() {
+            match opcode {
+                Opcode::Arithmetic(arith_expr) => {
+                    let len = intermediate_variables.len();
+
+                    let arith_expr = self.transform(
+                        arith_expr,
+                        &mut intermediate_variables,
+                        &mut next_witness_index,
+                    );
+
+                    // Update next_witness counter
+                    next_witness_index += (intermediate_variables.len() - len) as u32;
+                    let mut new_gates = Vec::new();
+                    for (g, (norm, w)) in intermediate_variables.iter().skip(len) {
+                        // de-normalize
+                        let mut intermediate_gate = g * *norm;
+                        // constrain the intermediate gate to the intermediate variable
+                        intermediate_gate.linear_combinations.push((-FieldElement::one(), *w));
+                        intermediate_gate.sort();
+                        new_gates.push(intermediate_gate);
+                    }
+                    new_gates.push(arith_expr);
+                    new_gates.sort();
+                    for gate in new_gates {
+                        new_opcode_labels.push(opcode_labels[index]);
+                        transformed_gates.push(Opcode::Arithmetic(gate));
+                    }
+                }
+                other_gate => {
+                    new_opcode_labels.push(opcode_labels[index]);
+                    transformed_gates.push(other_gate)
+                }
+            }
+        }
+
+        let current_witness_index = next_witness_index - 1;
+
+        (
+            Circuit {
+                current_witness_index,
+                opcodes: transformed_gates,
+                // The optimizer does not add new public inputs
+                public_parameters: acir.public_parameters,
+                return_values: acir.return_values,
+                assert_messages: acir.assert_messages,
+            },
+            new_opcode_labels,
+        )
+    }
+
     // Still missing dead witness optimization.
     // To do this, we will need the whole set of arithmetic gates
     // I think it can also be done before the local optimization seen here, as dead variables will come from the user
@@ -69,6 +69,7 @@ impl FallbackTransformer {
                 opcodes: acir_supported_opcodes,
                 public_parameters: acir.public_parameters,
                 return_values: acir.return_values,
+                assert_messages: acir.assert_messages,
             },
             new_opcode_labels,
         ))
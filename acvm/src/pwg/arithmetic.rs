@@ -4,11 +4,13 @@ use acir::{
     FieldElement,
 };
 
-use super::{insert_value, OpcodeNotSolvable, OpcodeResolution, OpcodeResolutionError};
+use super::{
+    insert_value, OpcodeNotSolvable, OpcodeResolution, OpcodeResolutionError, UnsatisfiedArithmetic,
+};
 
 /// An Arithmetic solver will take a Circuit's arithmetic gates with witness assignments
 /// and create the other witness variables
-pub(super) struct ArithmeticSolver;
+pub(crate) struct ArithmeticSolver;
 
 #[allow(clippy::enum_variant_names)]
 pub(super) enum GateStatus {
@@ -27,9 +29,9 @@ impl ArithmeticSolver {
     /// Derives the rest of the witness based on the initial low level variables
     pub(super) fn solve(
         initial_witness: &mut WitnessMap,
-        gate: &Expression,
+        opcode: &Expression,
     ) -> Result<OpcodeResolution, OpcodeResolutionError> {
-        let gate = &ArithmeticSolver::evaluate(gate, initial_witness);
+        let gate = &ArithmeticSolver::evaluate(opcode, initial_witness);
         // Evaluate multiplication term
         let mul_result = ArithmeticSolver::solve_mul_term(gate, initial_witness);
         // Evaluate the fan-in terms
@@ -47,9 +49,7 @@ impl ArithmeticSolver {
                     let total_sum = a + gate.q_c;
                     if (q + b).is_zero() {
                         if !total_sum.is_zero() {
-                            Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                                opcode_label: OpcodeLabel::Unresolved,
-                            })
+                            Err(ArithmeticSolver::unsatisfied(opcode, gate, initial_witness))
                         } else {
                             Ok(OpcodeResolution::Solved)
                         }
@@ -74,9 +74,7 @@ impl ArithmeticSolver {
                 let total_sum = sum + gate.q_c;
                 if partial_prod.is_zero() {
                     if !total_sum.is_zero() {
-                        Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                            opcode_label: OpcodeLabel::Unresolved,
-                        })
+                        Err(ArithmeticSolver::unsatisfied(opcode, gate, initial_witness))
                     } else {
                         Ok(OpcodeResolution::Solved)
                     }
@@ -91,9 +89,7 @@ impl ArithmeticSolver {
                 // All the variables in the MulTerm are solved and the Fan-in is also solved
                 // There is nothing to solve
                 if !(a + b + gate.q_c).is_zero() {
-                    Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                        opcode_label: OpcodeLabel::Unresolved,
-                    })
+                    Err(ArithmeticSolver::unsatisfied(opcode, gate, initial_witness))
                 } else {
                     Ok(OpcodeResolution::Solved)
                 }
@@ -108,9 +104,7 @@ impl ArithmeticSolver {
                 let total_sum = total_prod + partial_sum + gate.q_c;
                 if coeff.is_zero() {
                     if !total_sum.is_zero() {
-                        Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                            opcode_label: OpcodeLabel::Unresolved,
-                        })
+                        Err(ArithmeticSolver::unsatisfied(opcode, gate, initial_witness))
                     } else {
                         Ok(OpcodeResolution::Solved)
                     }
@@ -124,6 +118,29 @@ impl ArithmeticSolver {
         }
     }
 
+    /// Returns an error describing how `opcode` fails to be satisfied, where `residual` is the result of
+    /// evaluating `opcode` using the witnesses assigned in `initial_witness`.
+    fn unsatisfied(
+        opcode: &Expression,
+        residual: &Expression,
+        initial_witness: &WitnessMap,
+    ) -> OpcodeResolutionError {
+        let witnesses = opcode
+            .mul_terms
+            .iter()
+            .flat_map(|(_, lhs, rhs)| [*lhs, *rhs])
+            .chain(opcode.linear_combinations.iter().map(|(_, witness)| *witness));
+        let witness_values = witnesses
+            .filter_map(|witness| Some((witness, *initial_witness.get(&witness)?)))
+            .collect();
+        OpcodeResolutionError::UnsatisfiedArithmetic(Box::new(UnsatisfiedArithmetic {
+            opcode_label: OpcodeLabel::Unresolved,
+            opcode: opcode.clone(),
+            residual: residual.clone(),
+            witness_values,
+        }))
+    }
+
     /// Returns the evaluation of the multiplication term in the arithmetic gate
     /// If the witness values are not known, then the function returns a None
     /// XXX: Do we need to account for the case where 5xy + 6x = 0 ? We do not know y, but it can be solved given x . But I believe x can be solved with another gate
@@ -207,7 +224,7 @@ impl ArithmeticSolver {
     }
 
     // Partially evaluate the gate using the known witnesses
-    pub(super) fn evaluate(expr: &Expression, initial_witness: &WitnessMap) -> Expression {
+    pub(crate) fn evaluate(expr: &Expression, initial_witness: &WitnessMap) -> Expression {
         let mut result = Expression::default();
         for &(c, w1, w2) in &expr.mul_terms {
             let mul_result = ArithmeticSolver::solve_mul_term_helper(&(c, w1, w2), initial_witness);
@@ -114,7 +114,7 @@ fn write_digest_to_outputs(
     outputs: [Witness; 32],
     digest: [u8; 32],
 ) -> Result<(), OpcodeResolutionError> {
-    for (output_witness, value) in outputs.iter().zip(digest.into_iter()) {
+    for (output_witness, value) in outputs.iter().zip(digest) {
         insert_value(
             output_witness,
             FieldElement::from_be_bytes_reduce(&[value]),
@@ -1,21 +1,56 @@
 use acir::{
-    brillig_vm::{RegisterIndex, Registers, VMStatus, Value, VM},
-    circuit::brillig::{Brillig, BrilligInputs, BrilligOutputs},
+    brillig_vm::{FailureKind, RegisterIndex, Registers, VMStatus, Value, VM},
+    circuit::{
+        brillig::{Brillig, BrilligInputs, BrilligOutputs},
+        OpcodeLabel,
+    },
     native_types::WitnessMap,
     FieldElement,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{pwg::OpcodeNotSolvable, OpcodeResolution, OpcodeResolutionError};
 
 use super::{get_value, insert_value};
 
 pub(super) struct BrilligSolver;
 
+/// The state of a [`Brillig`] opcode before its bytecode is executed.
+pub(super) enum BrilligPreparation {
+    /// The opcode's predicate or inputs have not been assigned yet.
+    Stalled(OpcodeNotSolvable),
+    /// The opcode's predicate is zero so its bytecode should not be executed.
+    Skipped,
+    /// A VM loaded with the opcode's inputs, ready to execute its bytecode.
+    Ready(Box<VM>),
+}
+
 impl BrilligSolver {
     pub(super) fn solve(
         initial_witness: &mut WitnessMap,
         brillig: &Brillig,
+        instruction_budget: Option<usize>,
     ) -> Result<OpcodeResolution, OpcodeResolutionError> {
+        match Self::prepare(initial_witness, brillig, instruction_budget)? {
+            BrilligPreparation::Stalled(unsolved) => Ok(OpcodeResolution::Stalled(unsolved)),
+            BrilligPreparation::Skipped => Self::zero_out_brillig_outputs(initial_witness, brillig),
+            BrilligPreparation::Ready(mut vm) => {
+                // Run the Brillig VM on these inputs, bytecode, etc!
+                let vm_status = vm.process_opcodes();
+                Self::resolve(initial_witness, brillig, &vm, vm_status)
+            }
+        }
+    }
+
+    /// Evaluates the predicate and inputs of `brillig`, instantiating a VM to execute its bytecode if required.
+    ///
+    /// The VM fails once it has executed `instruction_budget` opcodes, if a budget is given.
+    pub(super) fn prepare(
+        initial_witness: &WitnessMap,
+        brillig: &Brillig,
+        instruction_budget: Option<usize>,
+    ) -> Result<BrilligPreparation, OpcodeResolutionError> {
         // If the predicate is `None`, then we simply return the value 1
         // If the predicate is `Some` but we cannot find a value, then we return stalled
         let pred_value = match &brillig.predicate {
@@ -25,14 +60,14 @@ impl BrilligSolver {
         let pred_value = match pred_value {
             Ok(pred_value) => pred_value,
             Err(OpcodeResolutionError::OpcodeNotSolvable(unsolved)) => {
-                return Ok(OpcodeResolution::Stalled(unsolved))
+                return Ok(BrilligPreparation::Stalled(unsolved))
             }
             Err(err) => return Err(err),
         };
 
         // A zero predicate indicates the oracle should be skipped, and its outputs zeroed.
         if pred_value.is_zero() {
-            return Self::zero_out_brillig_outputs(initial_witness, brillig);
+            return Ok(BrilligPreparation::Skipped);
         }
 
         // Set input values
@@ -47,7 +82,7 @@ impl BrilligSolver {
                 BrilligInputs::Single(expr) => match get_value(expr, initial_witness) {
                     Ok(value) => input_register_values.push(value.into()),
                     Err(_) => {
-                        return Ok(OpcodeResolution::Stalled(
+                        return Ok(BrilligPreparation::Stalled(
                             OpcodeNotSolvable::ExpressionHasTooManyUnknowns(expr.clone()),
                         ))
                     }
@@ -59,7 +94,7 @@ impl BrilligSolver {
                         match get_value(expr, initial_witness) {
                             Ok(value) => input_memory.push(value.into()),
                             Err(_) => {
-                                return Ok(OpcodeResolution::Stalled(
+                                return Ok(BrilligPreparation::Stalled(
                                     OpcodeNotSolvable::ExpressionHasTooManyUnknowns(expr.clone()),
                                 ))
                             }
@@ -81,26 +116,51 @@ impl BrilligSolver {
             brillig.bytecode.clone(),
             brillig.foreign_call_results.clone(),
         );
+        vm.set_instruction_budget(instruction_budget);
+        Ok(BrilligPreparation::Ready(Box::new(vm)))
+    }
 
-        // Run the Brillig VM on these inputs, bytecode, etc!
-        let vm_status = vm.process_opcodes();
-
+    /// Converts the status of a VM which has stopped executing the bytecode of `brillig` into the
+    /// resolution of the opcode, assigning its outputs if the VM has finished.
+    pub(super) fn resolve(
+        initial_witness: &mut WitnessMap,
+        brillig: &Brillig,
+        vm: &VM,
+        vm_status: VMStatus,
+    ) -> Result<OpcodeResolution, OpcodeResolutionError> {
         // Check the status of the Brillig VM.
         // It may be finished, in-progress, failed, or may be waiting for results of a foreign call.
         // Return the "resolution" to the caller who may choose to make subsequent calls
         // (when it gets foreign call results for example).
+        let failure =
+            |kind: FailureKind, program_counter| OpcodeResolutionError::BrilligFunctionFailed {
+                opcode_label: OpcodeLabel::Unresolved,
+                message: kind.to_string(),
+                program_counter,
+            };
         let result = match vm_status {
             VMStatus::Finished => {
+                // The bytecode may leave its outputs in registers or memory which don't exist.
+                let output_failure = |kind| failure(kind, vm.program_counter());
                 for (i, output) in brillig.outputs.iter().enumerate() {
-                    let register_value = vm.get_registers().get(RegisterIndex::from(i));
+                    let register_value = vm
+                        .get_registers()
+                        .try_get(RegisterIndex::from(i))
+                        .map_err(output_failure)?;
                     match output {
                         BrilligOutputs::Simple(witness) => {
                             insert_value(witness, register_value.to_field(), initial_witness)?;
                         }
                         BrilligOutputs::Array(witness_arr) => {
                             // Treat the register value as a pointer to memory
-                            for (i, witness) in witness_arr.iter().enumerate() {
-                                let value = &vm.get_memory()[register_value.to_usize() + i];
+                            let pointer = register_value.try_to_usize().map_err(output_failure)?;
+                            let size = witness_arr.len();
+                            let values = pointer
+                                .checked_add(size)
+                                .and_then(|end| vm.get_memory().get(pointer..end))
+                                .ok_or(FailureKind::MemoryOutOfBounds { pointer, size })
+                                .map_err(output_failure)?;
+                            for (witness, value) in witness_arr.iter().zip(values) {
                                 insert_value(witness, value.to_field(), initial_witness)?;
                             }
                         }
@@ -109,8 +169,19 @@ impl BrilligSolver {
                 OpcodeResolution::Solved
             }
             VMStatus::InProgress => unreachable!("Brillig VM has not completed execution"),
-            VMStatus::Failure { message } => {
-                return Err(OpcodeResolutionError::BrilligFunctionFailed(message))
+            VMStatus::Failure {
+                kind: FailureKind::ForeignCallFailed { function, message },
+                program_counter,
+            } => {
+                return Err(OpcodeResolutionError::ForeignCallFailed {
+                    opcode_label: OpcodeLabel::Unresolved,
+                    function,
+                    message,
+                    program_counter,
+                })
+            }
+            VMStatus::Failure { kind, program_counter } => {
+                return Err(failure(kind, program_counter))
             }
             VMStatus::ForeignCallWait { function, inputs } => {
                 OpcodeResolution::InProgressBrillig(ForeignCallWaitInfo { function, inputs })
@@ -145,7 +216,7 @@ impl BrilligSolver {
 /// where the result of the foreign call has not yet been provided.
 ///
 /// The caller must resolve this opcode externally based upon the information in the request.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct ForeignCallWaitInfo {
     /// An identifier interpreted by the caller process
     pub function: String,
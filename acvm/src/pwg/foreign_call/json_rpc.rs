@@ -0,0 +1,564 @@
+//! Resolution of foreign calls by a JSON-RPC 2.0 server, reached over HTTP.
+//!
+//! Each foreign call is sent as a [`resolve_foreign_call`][RESOLVE_FOREIGN_CALL_METHOD] request whose only
+//! parameter is the JSON encoded [`ForeignCallWaitInfo`], and the server replies with the JSON encoded
+//! [`ForeignCallResult`]:
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":0,"method":"resolve_foreign_call","params":[{"function":"get","inputs":[["0x01"]]}]}
+//! <-- {"jsonrpc":"2.0","id":0,"result":{"values":[{"Single":"0x02"}],"error":null}}
+//! ```
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+#[cfg(any(test, feature = "test-utils"))]
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use acir::brillig_vm::ForeignCallResult;
+use serde::{Deserialize, Serialize};
+
+use super::{ForeignCallError, ForeignCallExecutor};
+use crate::pwg::ForeignCallWaitInfo;
+
+/// Name of the JSON-RPC method used to resolve a foreign call.
+pub const RESOLVE_FOREIGN_CALL_METHOD: &str = "resolve_foreign_call";
+
+/// How long [`JsonRpcExecutor`] waits for each foreign call to be resolved unless
+/// [`JsonRpcExecutor::with_timeout`] is used.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest HTTP message body which will be read, so that a misbehaving peer can't exhaust memory.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+#[cfg(any(test, feature = "test-utils"))]
+const PARSE_ERROR: i64 = -32700;
+#[cfg(any(test, feature = "test-utils"))]
+const METHOD_NOT_FOUND: i64 = -32601;
+#[cfg(any(test, feature = "test-utils"))]
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    id: u64,
+    method: String,
+    params: (ForeignCallWaitInfo,),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    jsonrpc: String,
+    id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<ForeignCallResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Resolves foreign calls by sending them to a JSON-RPC server.
+///
+/// Only plain `http://` urls are supported and a new connection is made for each foreign call.
+#[derive(Debug, Clone)]
+pub struct JsonRpcExecutor {
+    /// The `host:port` of the server.
+    address: String,
+    path: String,
+    timeout: Duration,
+    next_id: u64,
+}
+
+impl JsonRpcExecutor {
+    /// Creates an executor which sends requests to `url`, of the form `http://host[:port][/path]`.
+    pub fn new(url: &str) -> Result<Self, ForeignCallError> {
+        let invalid_url = || ForeignCallError::InvalidUrl(url.to_owned());
+        let rest = url.strip_prefix("http://").ok_or_else(invalid_url)?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(invalid_url());
+        }
+        // IPv6 hosts are surrounded by brackets, so a port is only present if a colon follows them.
+        let has_port = authority.rsplit_once(']').map_or(authority, |(_, port)| port).contains(':');
+        let address = if has_port { authority.to_owned() } else { format!("{authority}:80") };
+
+        Ok(JsonRpcExecutor { address, path: path.to_owned(), timeout: DEFAULT_TIMEOUT, next_id: 0 })
+    }
+
+    /// Fails foreign calls for which connecting to the server, sending the request and receiving the response
+    /// take longer than `timeout` in total, rather than the default of 30 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Connects to the first of the server's addresses which accepts a connection before `deadline`.
+    fn connect(&self, deadline: Instant) -> std::io::Result<DeadlineStream> {
+        let mut last_error = None;
+        for address in self.address.to_socket_addrs()? {
+            let timeout = time_remaining(deadline)?;
+            match TcpStream::connect_timeout(&address, timeout) {
+                Ok(stream) => return Ok(DeadlineStream { stream, deadline }),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into()))
+    }
+
+    fn post(&self, body: &[u8]) -> Result<Vec<u8>, ForeignCallError> {
+        let deadline = Instant::now() + self.timeout;
+        let mut stream = self.connect(deadline)?;
+        // HTTP/1.0 rules out chunked responses, which aren't supported.
+        write!(
+            stream,
+            "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            self.path,
+            self.address,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let invalid_response = |err: std::io::Error| match err.kind() {
+            std::io::ErrorKind::InvalidData => ForeignCallError::InvalidResponse(err.to_string()),
+            _ => err.into(),
+        };
+        let mut reader = BufReader::new(stream);
+        let head = loop {
+            let head = read_http_head(&mut reader).map_err(invalid_response)?;
+            // Skip any interim responses, such as `100 Continue`, which have no body.
+            let status_line = head.first().map(String::as_str).unwrap_or_default();
+            if !status_line
+                .split_whitespace()
+                .nth(1)
+                .map_or(false, |status| status.starts_with('1'))
+            {
+                break head;
+            }
+        };
+        let body = read_http_body(&mut reader, &head).map_err(invalid_response)?;
+        let status_line = head.first().map(String::as_str).unwrap_or_default();
+        match status_line.split_whitespace().nth(1) {
+            Some("200") => Ok(body),
+            _ => Err(ForeignCallError::InvalidResponse(format!(
+                "unexpected HTTP status `{status_line}`"
+            ))),
+        }
+    }
+}
+
+/// Returns the time left until `deadline`, failing with [`std::io::ErrorKind::TimedOut`] once it has passed.
+fn time_remaining(deadline: Instant) -> std::io::Result<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(remaining) if !remaining.is_zero() => Ok(remaining),
+        _ => Err(std::io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// A connection to a server which fails to read or write once `deadline` has passed, so that a server
+/// which trickles out its response can't hold up the executor indefinitely.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.set_read_timeout(Some(time_remaining(self.deadline)?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.set_write_timeout(Some(time_remaining(self.deadline)?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl ForeignCallExecutor for JsonRpcExecutor {
+    fn execute(
+        &mut self,
+        foreign_call: &ForeignCallWaitInfo,
+    ) -> Result<ForeignCallResult, ForeignCallError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = Request {
+            jsonrpc: "2.0".to_owned(),
+            id,
+            method: RESOLVE_FOREIGN_CALL_METHOD.to_owned(),
+            params: (foreign_call.clone(),),
+        };
+        let request = serde_json::to_vec(&request).expect("requests should always be serializable");
+
+        let body = self.post(&request)?;
+        let body = String::from_utf8(body)
+            .map_err(|_| ForeignCallError::InvalidResponse("body is not valid UTF-8".to_owned()))?;
+        let response: Response = serde_json::from_str(&body)
+            .map_err(|err| ForeignCallError::InvalidResponse(err.to_string()))?;
+        if response.id != Some(id) {
+            return Err(ForeignCallError::InvalidResponse(format!(
+                "expected a response to request {id} but found {:?}",
+                response.id
+            )));
+        }
+        match response {
+            Response { error: Some(RpcError { code, message }), .. } => {
+                Err(ForeignCallError::Rpc { code, message })
+            }
+            Response { result: Some(result), .. } => Ok(result),
+            Response { .. } => Err(ForeignCallError::InvalidResponse(
+                "response has neither a result nor an error".to_owned(),
+            )),
+        }
+    }
+}
+
+/// A JSON-RPC server on localhost which resolves foreign calls using another executor,
+/// allowing [`JsonRpcExecutor`] to be used in tests without an external process.
+///
+/// The server runs on a background thread until it is dropped.
+///
+/// This is only available in tests, or with the `test-utils` feature.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug)]
+pub struct JsonRpcMockServer {
+    address: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl JsonRpcMockServer {
+    /// Starts serving requests on a free port, resolving them using `executor`.
+    pub fn start(mut executor: impl ForeignCallExecutor + Send + 'static) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let server_shutdown = shutdown.clone();
+        let handle = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                // A client which misbehaves only affects its own request.
+                if let Ok(stream) = stream {
+                    let _ = serve(&mut executor, stream);
+                }
+            }
+        });
+
+        Ok(JsonRpcMockServer { address, shutdown, handle: Some(handle) })
+    }
+
+    /// Returns the url at which the server accepts requests.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Drop for JsonRpcMockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the server thread, which is blocked waiting for a connection.
+        let _ = TcpStream::connect(self.address);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answers a single HTTP request containing a JSON-RPC request.
+#[cfg(any(test, feature = "test-utils"))]
+fn serve(executor: &mut impl ForeignCallExecutor, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let (_, body) = read_http_message(&mut reader)?;
+
+    let error = |id, code, message: String| Response {
+        jsonrpc: "2.0".to_owned(),
+        id,
+        result: None,
+        error: Some(RpcError { code, message }),
+    };
+    let response = match serde_json::from_slice::<Request>(&body) {
+        Err(err) => error(None, PARSE_ERROR, err.to_string()),
+        Ok(request) if request.method != RESOLVE_FOREIGN_CALL_METHOD => {
+            error(Some(request.id), METHOD_NOT_FOUND, format!("unknown method {}", request.method))
+        }
+        Ok(request) => match executor.execute(&request.params.0) {
+            Ok(result) => Response {
+                jsonrpc: "2.0".to_owned(),
+                id: Some(request.id),
+                result: Some(result),
+                error: None,
+            },
+            Err(err) => error(Some(request.id), SERVER_ERROR, err.to_string()),
+        },
+    };
+    let response = serde_json::to_vec(&response).expect("responses should always be serializable");
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        response.len()
+    )?;
+    stream.write_all(&response)?;
+    stream.flush()
+}
+
+/// Reads an HTTP request or response, returning the lines of its head and its body.
+#[cfg(any(test, feature = "test-utils"))]
+fn read_http_message(reader: &mut impl BufRead) -> std::io::Result<(Vec<String>, Vec<u8>)> {
+    let head = read_http_head(reader)?;
+    let body = read_http_body(reader, &head)?;
+    Ok((head, body))
+}
+
+/// Reads the start line and headers of an HTTP message.
+fn read_http_head(reader: &mut impl BufRead) -> std::io::Result<Vec<String>> {
+    let mut head = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(head);
+        }
+        head.push(line.to_owned());
+    }
+}
+
+/// Reads the body of an HTTP message with the given `head`.
+///
+/// Messages without a `Content-Length` header are assumed to end when the connection is closed.
+/// Bodies larger than [`MAX_BODY_SIZE`] or sent with a transfer encoding are rejected with
+/// [`std::io::ErrorKind::InvalidData`].
+fn read_http_body(reader: &mut impl BufRead, head: &[String]) -> std::io::Result<Vec<u8>> {
+    let header = |name: &str| {
+        head.iter().skip(1).find_map(|header| {
+            let (header_name, value) = header.split_once(':')?;
+            header_name.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    };
+    if header("transfer-encoding")
+        .map_or(false, |encoding| !encoding.eq_ignore_ascii_case("identity"))
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "HTTP transfer encodings are not supported",
+        ));
+    }
+
+    let content_length = header("content-length").and_then(|length| length.parse().ok());
+    let too_large = || {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("HTTP message body exceeds the limit of {MAX_BODY_SIZE} bytes"),
+        )
+    };
+    let mut body = Vec::new();
+    match content_length {
+        Some(length) if length > MAX_BODY_SIZE => return Err(too_large()),
+        Some(length) => {
+            body.resize(length, 0);
+            reader.read_exact(&mut body)?;
+        }
+        None => {
+            reader.take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body)?;
+            if body.len() > MAX_BODY_SIZE {
+                return Err(too_large());
+            }
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        time::{Duration, Instant},
+    };
+
+    use acir::{
+        brillig_vm::{ForeignCallResult, Value},
+        FieldElement,
+    };
+
+    use super::{JsonRpcExecutor, JsonRpcMockServer};
+    use crate::pwg::{
+        foreign_call::{ForeignCallError, ForeignCallExecutor, MapExecutor},
+        ForeignCallWaitInfo,
+    };
+
+    fn double_executor() -> MapExecutor {
+        MapExecutor::new().with_function("double", |inputs| {
+            Value::from(inputs[0][0].to_field() * FieldElement::from(2u128)).into()
+        })
+    }
+
+    fn call(function: &str, input: u128) -> ForeignCallWaitInfo {
+        ForeignCallWaitInfo { function: function.to_owned(), inputs: vec![vec![input.into()]] }
+    }
+
+    /// Starts a server which reads a single request and replies with each of the `chunks` after `delay`,
+    /// returning its url.
+    fn raw_server(chunks: Vec<&'static str>, delay: Duration) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            for chunk in chunks {
+                std::thread::sleep(delay);
+                if stream.write_all(chunk.as_bytes()).is_err() {
+                    return;
+                }
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn parses_urls() {
+        let executor = JsonRpcExecutor::new("http://localhost:5555/rpc").unwrap();
+        assert_eq!((executor.address.as_str(), executor.path.as_str()), ("localhost:5555", "/rpc"));
+        let executor = JsonRpcExecutor::new("http://[::1]").unwrap();
+        assert_eq!((executor.address.as_str(), executor.path.as_str()), ("[::1]:80", "/"));
+
+        for url in ["https://localhost", "localhost:5555", "http:///rpc"] {
+            assert!(matches!(JsonRpcExecutor::new(url), Err(ForeignCallError::InvalidUrl(_))));
+        }
+    }
+
+    #[test]
+    fn resolves_foreign_calls_through_mock_server() {
+        let server = JsonRpcMockServer::start(double_executor()).unwrap();
+        let mut executor = JsonRpcExecutor::new(&server.url()).unwrap();
+
+        assert_eq!(executor.execute(&call("double", 21)).unwrap(), Value::from(42u128).into());
+        assert_eq!(executor.execute(&call("double", 5)).unwrap(), Value::from(10u128).into());
+    }
+
+    #[test]
+    fn rejects_oversized_responses() {
+        let url = raw_server(
+            vec!["HTTP/1.0 200 OK\r\nContent-Length: 1000000000000\r\n\r\n"],
+            Duration::ZERO,
+        );
+        let mut executor = JsonRpcExecutor::new(&url).unwrap();
+
+        assert!(matches!(
+            executor.execute(&call("double", 1)),
+            Err(ForeignCallError::InvalidResponse(message)) if message.contains("exceeds the limit")
+        ));
+    }
+
+    #[test]
+    fn times_out_waiting_for_a_response() {
+        let url = raw_server(vec![""], Duration::from_secs(5));
+        let mut executor =
+            JsonRpcExecutor::new(&url).unwrap().with_timeout(Duration::from_millis(100));
+
+        assert!(matches!(
+            executor.execute(&call("double", 1)),
+            Err(ForeignCallError::Transport(err))
+                if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+        ));
+    }
+
+    #[test]
+    fn times_out_when_the_response_is_trickled() {
+        // Each byte arrives well within the timeout, but the response as a whole does not.
+        let mut chunks = vec!["HTTP/1.0 200 OK\r\n"];
+        chunks.extend(["X"; 100]);
+        let url = raw_server(chunks, Duration::from_millis(20));
+        let mut executor =
+            JsonRpcExecutor::new(&url).unwrap().with_timeout(Duration::from_millis(200));
+
+        let start = Instant::now();
+        assert!(matches!(
+            executor.execute(&call("double", 1)),
+            Err(ForeignCallError::Transport(err))
+                if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn skips_interim_responses() {
+        let url = raw_server(
+            vec![
+                "HTTP/1.1 100 Continue\r\n\r\n",
+                "HTTP/1.0 200 OK\r\nContent-Length: 66\r\n\r\n",
+                r#"{"jsonrpc":"2.0","id":0,"result":{"values":[],"error":"no value"}}"#,
+            ],
+            Duration::ZERO,
+        );
+        let mut executor = JsonRpcExecutor::new(&url).unwrap();
+
+        assert_eq!(
+            executor.execute(&call("double", 1)).unwrap(),
+            ForeignCallResult::error("no value")
+        );
+    }
+
+    #[test]
+    fn rejects_chunked_responses() {
+        let url = raw_server(
+            vec!["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n"],
+            Duration::ZERO,
+        );
+        let mut executor = JsonRpcExecutor::new(&url).unwrap();
+
+        assert!(matches!(
+            executor.execute(&call("double", 1)),
+            Err(ForeignCallError::InvalidResponse(message)) if message.contains("transfer encoding")
+        ));
+    }
+
+    #[test]
+    fn forwards_errors_from_the_server() {
+        let executor = double_executor()
+            .with_function("missing", |_| ForeignCallResult::error("value not found"));
+        let server = JsonRpcMockServer::start(executor).unwrap();
+        let mut executor = JsonRpcExecutor::new(&server.url()).unwrap();
+
+        assert_eq!(
+            executor.execute(&call("missing", 1)).unwrap(),
+            ForeignCallResult::error("value not found")
+        );
+        assert!(matches!(
+            executor.execute(&call("triple", 1)),
+            Err(ForeignCallError::Rpc { code: -32000, message })
+                if message == "no handler is registered for foreign call triple"
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+//! Resolution of the [foreign calls][acir::brillig_vm::Opcode::ForeignCall] requested by Brillig opcodes.
+//!
+//! Rather than driving [`ACVM::solve`][super::ACVM::solve] by hand, callers can pass a [`ForeignCallExecutor`]
+//! to [`ACVM::solve_with_foreign_calls`][super::ACVM::solve_with_foreign_calls], which resolves each pending
+//! foreign call as it is encountered.
+
+use std::collections::HashMap;
+
+use acir::brillig_vm::{ForeignCallResult, Value};
+use thiserror::Error;
+
+use super::ForeignCallWaitInfo;
+
+mod json_rpc;
+mod transcript;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub use json_rpc::JsonRpcMockServer;
+pub use json_rpc::{JsonRpcExecutor, RESOLVE_FOREIGN_CALL_METHOD};
+pub use transcript::{
+    ForeignCallTranscript, RecordedForeignCall, RecordingExecutor, ReplayExecutor,
+};
+
+#[derive(Debug, Error)]
+pub enum ForeignCallError {
+    #[error("no handler is registered for foreign call {0}")]
+    UnknownFunction(String),
+    #[error("invalid JSON-RPC url `{0}`, expected `http://host[:port][/path]`")]
+    InvalidUrl(String),
+    #[error("failed to reach JSON-RPC server: {0}")]
+    Transport(#[from] std::io::Error),
+    #[error("invalid JSON-RPC response: {0}")]
+    InvalidResponse(String),
+    #[error("JSON-RPC server returned error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("foreign call {index} diverged from the transcript: expected a call to {expected} but found a call to {found}")]
+    FunctionMismatch { index: usize, expected: String, found: String },
+    #[error("foreign call {index} to {function} diverged from the transcript: expected inputs {expected:?} but found {found:?}")]
+    InputsMismatch {
+        index: usize,
+        function: String,
+        expected: Vec<Vec<Value>>,
+        found: Vec<Vec<Value>>,
+    },
+    #[error("foreign call {index} to {function} is past the end of the transcript")]
+    TranscriptExhausted { index: usize, function: String },
+}
+
+/// Computes the results of foreign calls on behalf of the [ACVM][super::ACVM].
+pub trait ForeignCallExecutor {
+    /// Computes the result of `foreign_call`.
+    ///
+    /// Failures of the foreign call itself can be reported either by returning an error or by returning
+    /// a [result carrying an error][ForeignCallResult::error]. In both cases solving fails with
+    /// [`OpcodeResolutionError::ForeignCallFailed`][super::OpcodeResolutionError::ForeignCallFailed].
+    fn execute(
+        &mut self,
+        foreign_call: &ForeignCallWaitInfo,
+    ) -> Result<ForeignCallResult, ForeignCallError>;
+}
+
+type ForeignCallHandler = Box<dyn FnMut(&[Vec<Value>]) -> ForeignCallResult + Send>;
+
+/// Resolves foreign calls in-process by calling the closure registered under the name of the foreign function.
+#[derive(Default)]
+pub struct MapExecutor {
+    handlers: HashMap<String, ForeignCallHandler>,
+}
+
+impl MapExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` to compute the results of calls to `function`, replacing any existing handler.
+    ///
+    /// The handler is passed the resolved inputs of the foreign call.
+    pub fn with_function(
+        mut self,
+        function: impl Into<String>,
+        handler: impl FnMut(&[Vec<Value>]) -> ForeignCallResult + Send + 'static,
+    ) -> Self {
+        self.handlers.insert(function.into(), Box::new(handler));
+        self
+    }
+}
+
+impl std::fmt::Debug for MapExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut functions: Vec<_> = self.handlers.keys().collect();
+        functions.sort();
+        f.debug_struct("MapExecutor").field("functions", &functions).finish()
+    }
+}
+
+impl ForeignCallExecutor for MapExecutor {
+    fn execute(
+        &mut self,
+        foreign_call: &ForeignCallWaitInfo,
+    ) -> Result<ForeignCallResult, ForeignCallError> {
+        let handler = self
+            .handlers
+            .get_mut(&foreign_call.function)
+            .ok_or_else(|| ForeignCallError::UnknownFunction(foreign_call.function.clone()))?;
+        Ok(handler(&foreign_call.inputs))
+    }
+}
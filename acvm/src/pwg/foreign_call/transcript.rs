@@ -0,0 +1,182 @@
+//! Recording of the foreign calls made while solving a circuit, so that it can later be solved again
+//! without access to the original executor.
+
+use acir::brillig_vm::ForeignCallResult;
+use serde::{Deserialize, Serialize};
+
+use super::{ForeignCallError, ForeignCallExecutor};
+use crate::pwg::ForeignCallWaitInfo;
+
+/// The foreign calls made while solving a circuit, along with their results, in the order they were made.
+///
+/// As the ACVM makes foreign calls in a deterministic order, solving the same circuit with the same inputs
+/// using a [`ReplayExecutor`] reproduces the original witness map.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignCallTranscript {
+    pub calls: Vec<RecordedForeignCall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedForeignCall {
+    pub foreign_call: ForeignCallWaitInfo,
+    /// The result passed back to the ACVM, which carries an error if the executor failed to resolve the call.
+    pub result: ForeignCallResult,
+}
+
+impl ForeignCallTranscript {
+    /// Serializes the transcript as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("transcripts should always be serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Records the foreign calls resolved by another executor into a [`ForeignCallTranscript`].
+#[derive(Debug)]
+pub struct RecordingExecutor<E> {
+    executor: E,
+    transcript: ForeignCallTranscript,
+}
+
+impl<E: ForeignCallExecutor> RecordingExecutor<E> {
+    pub fn new(executor: E) -> Self {
+        RecordingExecutor { executor, transcript: ForeignCallTranscript::default() }
+    }
+
+    pub fn transcript(&self) -> &ForeignCallTranscript {
+        &self.transcript
+    }
+
+    pub fn into_transcript(self) -> ForeignCallTranscript {
+        self.transcript
+    }
+}
+
+impl<E: ForeignCallExecutor> ForeignCallExecutor for RecordingExecutor<E> {
+    fn execute(
+        &mut self,
+        foreign_call: &ForeignCallWaitInfo,
+    ) -> Result<ForeignCallResult, ForeignCallError> {
+        let result = self.executor.execute(foreign_call);
+        // Failures are recorded as the error result which the ACVM is resolved with in their place.
+        let recorded_result = match &result {
+            Ok(result) => result.clone(),
+            Err(err) => ForeignCallResult::error(err.to_string()),
+        };
+        self.transcript.calls.push(RecordedForeignCall {
+            foreign_call: foreign_call.clone(),
+            result: recorded_result,
+        });
+        result
+    }
+}
+
+/// Resolves foreign calls using the results recorded in a [`ForeignCallTranscript`], failing if the
+/// calls made diverge from those which were recorded.
+#[derive(Debug, Clone)]
+pub struct ReplayExecutor {
+    transcript: ForeignCallTranscript,
+    /// The index of the next call in the transcript.
+    next_call: usize,
+}
+
+impl ReplayExecutor {
+    pub fn new(transcript: ForeignCallTranscript) -> Self {
+        ReplayExecutor { transcript, next_call: 0 }
+    }
+
+    /// Returns the recorded calls which have not been replayed yet.
+    pub fn remaining(&self) -> &[RecordedForeignCall] {
+        &self.transcript.calls[self.next_call..]
+    }
+}
+
+impl ForeignCallExecutor for ReplayExecutor {
+    fn execute(
+        &mut self,
+        foreign_call: &ForeignCallWaitInfo,
+    ) -> Result<ForeignCallResult, ForeignCallError> {
+        let index = self.next_call;
+        let recorded = self.transcript.calls.get(index).ok_or_else(|| {
+            ForeignCallError::TranscriptExhausted { index, function: foreign_call.function.clone() }
+        })?;
+        if recorded.foreign_call.function != foreign_call.function {
+            return Err(ForeignCallError::FunctionMismatch {
+                index,
+                expected: recorded.foreign_call.function.clone(),
+                found: foreign_call.function.clone(),
+            });
+        }
+        if recorded.foreign_call.inputs != foreign_call.inputs {
+            return Err(ForeignCallError::InputsMismatch {
+                index,
+                function: foreign_call.function.clone(),
+                expected: recorded.foreign_call.inputs.clone(),
+                found: foreign_call.inputs.clone(),
+            });
+        }
+
+        self.next_call += 1;
+        Ok(recorded.result.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::brillig_vm::{ForeignCallResult, Value};
+
+    use super::{ForeignCallTranscript, RecordingExecutor, ReplayExecutor};
+    use crate::pwg::{
+        foreign_call::{ForeignCallError, ForeignCallExecutor, MapExecutor},
+        ForeignCallWaitInfo,
+    };
+
+    fn call(function: &str, input: u128) -> ForeignCallWaitInfo {
+        ForeignCallWaitInfo { function: function.to_owned(), inputs: vec![vec![input.into()]] }
+    }
+
+    fn record() -> ForeignCallTranscript {
+        let executor = MapExecutor::new()
+            .with_function("increment", |inputs| Value::from(inputs[0][0].to_u128() + 1).into());
+        let mut recorder = RecordingExecutor::new(executor);
+        assert_eq!(recorder.execute(&call("increment", 1)).unwrap(), Value::from(2u128).into());
+        assert!(recorder.execute(&call("decrement", 1)).is_err());
+        recorder.into_transcript()
+    }
+
+    #[test]
+    fn replays_recorded_calls() {
+        let transcript = record();
+        assert_eq!(ForeignCallTranscript::from_json(&transcript.to_json()).unwrap(), transcript);
+
+        let mut replay = ReplayExecutor::new(transcript);
+        assert_eq!(replay.execute(&call("increment", 1)).unwrap(), Value::from(2u128).into());
+        assert_eq!(
+            replay.execute(&call("decrement", 1)).unwrap(),
+            ForeignCallResult::error("no handler is registered for foreign call decrement")
+        );
+        assert!(replay.remaining().is_empty());
+        assert!(matches!(
+            replay.execute(&call("increment", 1)),
+            Err(ForeignCallError::TranscriptExhausted { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn reports_divergence_from_the_transcript() {
+        let mut replay = ReplayExecutor::new(record());
+        assert!(matches!(
+            replay.execute(&call("decrement", 1)),
+            Err(ForeignCallError::FunctionMismatch { index: 0, expected, found })
+                if expected == "increment" && found == "decrement"
+        ));
+        assert!(matches!(
+            replay.execute(&call("increment", 2)),
+            Err(ForeignCallError::InputsMismatch { index: 0, .. })
+        ));
+        assert_eq!(replay.remaining().len(), 2);
+    }
+}
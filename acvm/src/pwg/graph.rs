@@ -0,0 +1,206 @@
+use std::collections::{BTreeSet, HashMap};
+
+use acir::{
+    circuit::{brillig::BrilligInputs, directives::Directive, directives::LogInfo, Opcode},
+    native_types::{Expression, Witness, WitnessMap},
+};
+
+/// Records which witnesses each opcode reads so that an opcode which is waiting on missing inputs
+/// only needs to be revisited once one of those inputs has been assigned.
+pub(super) struct DependencyGraph {
+    /// The witnesses read by the opcode at each index.
+    reads: Vec<BTreeSet<Witness>>,
+    /// The witnesses referenced by the opcode at each index, including those which it defines.
+    witnesses: Vec<BTreeSet<Witness>>,
+    /// The indices of the opcodes which read each witness.
+    readers: HashMap<Witness, Vec<usize>>,
+}
+
+impl DependencyGraph {
+    pub(super) fn new<'a>(opcodes: impl IntoIterator<Item = &'a Opcode>) -> Self {
+        let mut graph =
+            DependencyGraph { reads: Vec::new(), witnesses: Vec::new(), readers: HashMap::new() };
+        for (index, opcode) in opcodes.into_iter().enumerate() {
+            let reads = opcode_inputs(opcode);
+            for witness in &reads {
+                graph.readers.entry(*witness).or_default().push(index);
+            }
+            graph.reads.push(reads);
+            graph.witnesses.push(opcode.witnesses());
+        }
+        graph
+    }
+
+    /// Adds `opcode` at the start of the graph, moving every other opcode along by one index.
+    pub(super) fn insert_first(&mut self, opcode: &Opcode) {
+        for readers in self.readers.values_mut() {
+            readers.iter_mut().for_each(|index| *index += 1);
+        }
+        let reads = opcode_inputs(opcode);
+        for witness in &reads {
+            self.readers.entry(*witness).or_default().insert(0, 0);
+        }
+        self.reads.insert(0, reads);
+        self.witnesses.insert(0, opcode.witnesses());
+    }
+
+    /// Moves the opcode at each index to `new_indices[index]`, removing those which map to `None`.
+    ///
+    /// Remaining opcodes must keep their relative order.
+    pub(super) fn remap(&mut self, new_indices: &[Option<usize>]) {
+        let mut kept = new_indices.iter().map(Option::is_some);
+        self.reads.retain(|_| kept.next().expect("one entry per opcode"));
+        let mut kept = new_indices.iter().map(Option::is_some);
+        self.witnesses.retain(|_| kept.next().expect("one entry per opcode"));
+        self.readers.retain(|_, readers| {
+            readers.retain_mut(|index| match new_indices[*index] {
+                Some(new_index) => {
+                    *index = new_index;
+                    true
+                }
+                None => false,
+            });
+            !readers.is_empty()
+        });
+    }
+
+    /// Returns the witnesses referenced by the opcode at `index`.
+    pub(super) fn witnesses(&self, index: usize) -> &BTreeSet<Witness> {
+        &self.witnesses[index]
+    }
+
+    /// Returns the witnesses referenced by the opcode at `index` which have not been assigned yet.
+    pub(super) fn unassigned_witnesses(
+        &self,
+        index: usize,
+        witness_map: &WitnessMap,
+    ) -> Vec<Witness> {
+        self.witnesses(index)
+            .iter()
+            .filter(|witness| !witness_map.contains_key(witness))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the inputs of the opcode at `index` which have not been assigned yet.
+    pub(super) fn missing_inputs(&self, index: usize, witness_map: &WitnessMap) -> Vec<Witness> {
+        self.reads[index]
+            .iter()
+            .filter(|witness| !witness_map.contains_key(witness))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the indices of the opcodes which read `witness`.
+    pub(super) fn readers(&self, witness: &Witness) -> &[usize] {
+        self.readers.get(witness).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Returns the witnesses which must be assigned before `opcode` can be solved.
+///
+/// An arithmetic opcode can be solved for any one of its witnesses so all of them are treated as inputs.
+fn opcode_inputs(opcode: &Opcode) -> BTreeSet<Witness> {
+    let mut inputs = BTreeSet::new();
+    match opcode {
+        Opcode::Arithmetic(expr) => expression_witnesses(expr, &mut inputs),
+        Opcode::BlackBoxFuncCall(bb_func) => {
+            inputs.extend(bb_func.get_inputs_vec().iter().map(|input| input.witness));
+        }
+        Opcode::Directive(directive) => match directive {
+            Directive::Invert { x, .. } => {
+                inputs.insert(*x);
+            }
+            Directive::Quotient(quotient) => {
+                expression_witnesses(&quotient.a, &mut inputs);
+                expression_witnesses(&quotient.b, &mut inputs);
+                if let Some(predicate) = &quotient.predicate {
+                    expression_witnesses(predicate, &mut inputs);
+                }
+            }
+            Directive::ToLeRadix { a, .. } => expression_witnesses(a, &mut inputs),
+            Directive::PermutationSort { inputs: tuples, .. } => {
+                for expr in tuples.iter().flatten() {
+                    expression_witnesses(expr, &mut inputs);
+                }
+            }
+            Directive::Log(LogInfo::WitnessOutput(witnesses)) => inputs.extend(witnesses),
+            Directive::Log(LogInfo::FinalizedOutput(_)) => (),
+        },
+        Opcode::Block(block) | Opcode::ROM(block) | Opcode::RAM(block) => {
+            for op in &block.trace {
+                expression_witnesses(&op.operation, &mut inputs);
+                expression_witnesses(&op.index, &mut inputs);
+                expression_witnesses(&op.value, &mut inputs);
+            }
+        }
+        Opcode::Brillig(brillig) => {
+            for input in &brillig.inputs {
+                match input {
+                    BrilligInputs::Single(expr) => expression_witnesses(expr, &mut inputs),
+                    BrilligInputs::Array(exprs) => {
+                        for expr in exprs {
+                            expression_witnesses(expr, &mut inputs);
+                        }
+                    }
+                }
+            }
+            if let Some(predicate) = &brillig.predicate {
+                expression_witnesses(predicate, &mut inputs);
+            }
+        }
+    }
+    inputs
+}
+
+fn expression_witnesses(expr: &Expression, witnesses: &mut BTreeSet<Witness>) {
+    for (_, lhs, rhs) in &expr.mul_terms {
+        witnesses.insert(*lhs);
+        witnesses.insert(*rhs);
+    }
+    witnesses.extend(expr.linear_combinations.iter().map(|(_, witness)| *witness));
+}
+
+#[cfg(test)]
+mod tests {
+    use acir::{
+        circuit::Opcode,
+        native_types::{Expression, Witness},
+        FieldElement,
+    };
+
+    use super::DependencyGraph;
+
+    fn sum(lhs: u32, rhs: u32, output: u32) -> Opcode {
+        Opcode::Arithmetic(Expression {
+            mul_terms: Vec::new(),
+            linear_combinations: vec![
+                (FieldElement::one(), Witness(lhs)),
+                (FieldElement::one(), Witness(rhs)),
+                (-FieldElement::one(), Witness(output)),
+            ],
+            q_c: FieldElement::zero(),
+        })
+    }
+
+    fn assert_same_graph(graph: &DependencyGraph, opcodes: &[Opcode]) {
+        let rebuilt = DependencyGraph::new(opcodes);
+        assert_eq!(graph.reads, rebuilt.reads);
+        assert_eq!(graph.witnesses, rebuilt.witnesses);
+        assert_eq!(graph.readers, rebuilt.readers);
+    }
+
+    #[test]
+    fn updates_match_rebuilding_the_graph() {
+        let mut opcodes = vec![sum(1, 2, 3), sum(3, 4, 5), sum(3, 5, 6)];
+        let mut graph = DependencyGraph::new(&opcodes);
+
+        graph.remap(&[Some(0), None, Some(1)]);
+        opcodes.remove(1);
+        assert_same_graph(&graph, &opcodes);
+
+        opcodes.insert(0, sum(6, 7, 8));
+        graph.insert_first(&opcodes[0]);
+        assert_same_graph(&graph, &opcodes);
+    }
+}
@@ -1,20 +1,29 @@
 // Re-usable methods that backends can use to implement their PWG
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Instant,
+};
 
 use acir::{
-    brillig_vm::ForeignCallResult,
-    circuit::{brillig::Brillig, opcodes::BlockId, Opcode, OpcodeLabel},
+    brillig_vm::{ForeignCallResult, VMStatus, VM},
+    circuit::{brillig::Brillig, opcodes::BlockId, Opcode, OpcodeLabel, OpcodeLocation},
     native_types::{Expression, Witness, WitnessMap},
     BlackBoxFunc, FieldElement,
 };
 
 use self::{
-    arithmetic::ArithmeticSolver, block::BlockSolver, brillig::BrilligSolver,
+    arithmetic::ArithmeticSolver,
+    block::BlockSolver,
+    brillig::{BrilligPreparation, BrilligSolver},
     directives::solve_directives,
+    graph::DependencyGraph,
+    parallel::PresolvedOpcode,
+    state::{BrilligSession, SolveState},
 };
 use crate::{BlackBoxFunctionSolver, Language};
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 // arithmetic
@@ -22,12 +31,29 @@ pub(crate) mod arithmetic;
 // Brillig bytecode
 mod brillig;
 // Directives
-mod directives;
+pub(crate) mod directives;
+// resolution of foreign calls by the caller
+mod foreign_call;
 // black box functions
-mod blackbox;
+pub(crate) mod blackbox;
 mod block;
+// dependencies between opcodes
+mod graph;
+// solving of independent opcodes across threads
+mod parallel;
+// profiling of witness generation
+mod profiler;
+// progress of the solving loop between calls
+mod state;
 
 pub use brillig::ForeignCallWaitInfo;
+#[cfg(any(test, feature = "test-utils"))]
+pub use foreign_call::JsonRpcMockServer;
+pub use foreign_call::{
+    ForeignCallError, ForeignCallExecutor, ForeignCallTranscript, JsonRpcExecutor, MapExecutor,
+    RecordedForeignCall, RecordingExecutor, ReplayExecutor, RESOLVE_FOREIGN_CALL_METHOD,
+};
+pub use profiler::{OpcodeProfile, SolveProfile};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ACVMStatus {
@@ -43,7 +69,8 @@ pub enum ACVMStatus {
 
     /// The ACVM has encountered a request for a Brillig [foreign call][acir::brillig_vm::Opcode::ForeignCall]
     /// to retrieve information from outside of the ACVM. The result of the foreign call must be passed back
-    /// to the ACVM using [`ACVM::resolve_pending_foreign_call`].
+    /// to the ACVM using [`ACVM::resolve_pending_foreign_call`]. Several independent foreign calls may be pending
+    /// at once, in which case they can be listed using [`ACVM::pending_foreign_calls`].
     ///
     /// Once this is done, the ACVM can be restarted to solve the remaining opcodes.
     RequiresForeignCall,
@@ -87,8 +114,77 @@ pub enum OpcodeResolutionError {
     UnsatisfiedConstrain { opcode_label: OpcodeLabel },
     #[error("failed to solve blackbox function: {0}, reason: {1}")]
     BlackBoxFunctionFailed(BlackBoxFunc, String),
-    #[error("failed to solve brillig function, reason: {0}")]
-    BrilligFunctionFailed(String),
+    #[error("arithmetic opcode {:?} is not satisfied as it evaluates to {}", .0.opcode_label, .0.residual)]
+    UnsatisfiedArithmetic(Box<UnsatisfiedArithmetic>),
+    #[error(
+        "failed to solve brillig function at program counter {program_counter}, reason: {message}"
+    )]
+    BrilligFunctionFailed { opcode_label: OpcodeLabel, message: String, program_counter: usize },
+    #[error("foreign call {function} failed at program counter {program_counter}: {message}")]
+    ForeignCallFailed {
+        opcode_label: OpcodeLabel,
+        function: String,
+        message: String,
+        program_counter: usize,
+    },
+    #[error("cannot solve opcodes as some of their inputs are never assigned: {0:?}")]
+    UnsolvableOpcodes(Vec<UnsolvableOpcode>),
+    #[error("assertion failed: {message}")]
+    AssertionFailed { message: String, source: Box<OpcodeResolutionError> },
+}
+
+impl OpcodeResolutionError {
+    /// Returns the location of the opcode which caused the error, if it is known.
+    ///
+    /// For Brillig failures this is the location of the instruction which failed. This can be used to look up
+    /// the source code of the opcode in a [`DebugInfo`][acir::circuit::debug_info::DebugInfo].
+    pub fn opcode_location(&self) -> Option<OpcodeLocation> {
+        let resolved_index = |opcode_label: &OpcodeLabel| match opcode_label {
+            OpcodeLabel::Resolved(index) => Some(*index as usize),
+            OpcodeLabel::Unresolved => None,
+        };
+        match self {
+            OpcodeResolutionError::UnsatisfiedConstrain { opcode_label } => {
+                resolved_index(opcode_label).map(OpcodeLocation::Acir)
+            }
+            OpcodeResolutionError::UnsatisfiedArithmetic(unsatisfied) => {
+                resolved_index(&unsatisfied.opcode_label).map(OpcodeLocation::Acir)
+            }
+            OpcodeResolutionError::BrilligFunctionFailed {
+                opcode_label, program_counter, ..
+            }
+            | OpcodeResolutionError::ForeignCallFailed { opcode_label, program_counter, .. } => {
+                resolved_index(opcode_label).map(|acir_index| OpcodeLocation::Brillig {
+                    acir_index,
+                    brillig_index: *program_counter,
+                })
+            }
+            OpcodeResolutionError::AssertionFailed { source, .. } => source.opcode_location(),
+            OpcodeResolutionError::OpcodeNotSolvable(_)
+            | OpcodeResolutionError::UnsupportedBlackBoxFunc(_)
+            | OpcodeResolutionError::BlackBoxFunctionFailed(..)
+            | OpcodeResolutionError::UnsolvableOpcodes(_) => None,
+        }
+    }
+}
+
+/// The values involved in an arithmetic opcode which is not satisfied by the assigned witnesses.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnsatisfiedArithmetic {
+    pub opcode_label: OpcodeLabel,
+    pub opcode: Expression,
+    /// The result of evaluating `opcode` using the assigned witnesses, which should have been zero.
+    pub residual: Expression,
+    /// The values assigned to the witnesses used in `opcode`.
+    pub witness_values: BTreeMap<Witness, FieldElement>,
+}
+
+/// An opcode which can never be solved as some of the witnesses which it reads are never assigned.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnsolvableOpcode {
+    pub opcode_label: OpcodeLabel,
+    /// The witnesses read by the opcode which have not been assigned.
+    pub missing_witnesses: Vec<Witness>,
 }
 
 pub struct ACVM<B: BlackBoxFunctionSolver> {
@@ -106,11 +202,29 @@ pub struct ACVM<B: BlackBoxFunctionSolver> {
 
     witness_map: WitnessMap,
 
-    /// A list of foreign calls which must be resolved before the ACVM can resume execution.
-    pending_foreign_calls: Vec<UnresolvedBrilligCall>,
+    /// The foreign calls which must be resolved before the ACVM can resume execution, in the order they were made.
+    pending_foreign_calls: BTreeMap<ForeignCallId, UnresolvedBrilligCall>,
+
+    /// Map from a pending foreign call to the label of the Brillig opcode which made it.
+    pending_brillig_labels: HashMap<ForeignCallId, OpcodeLabel>,
+
+    /// The id to assign to the next foreign call.
+    next_foreign_call_id: ForeignCallId,
+
+    /// Statistics about the solving process, collected only if profiling has been enabled.
+    profile: Option<SolveProfile>,
+
+    /// Messages to report when the opcode at a given location fails.
+    assert_messages: Vec<(OpcodeLocation, String)>,
+
+    /// The maximum number of instructions which the bytecode of a Brillig opcode may execute.
+    brillig_instruction_budget: Option<usize>,
 
-    /// Map from a canonical hash of an unresolved Brillig call to its opcode label.
-    pending_brillig_label_maps: HashMap<UnresolvedBrilligCallHash, OpcodeLabel>,
+    /// Labels of the opcodes before which [`ACVM::solve`] should pause.
+    breakpoints: Vec<OpcodeLabel>,
+
+    /// The progress of the solving loop if execution has been paused part way through the circuit.
+    solve_state: Option<SolveState>,
 }
 
 impl<B: BlackBoxFunctionSolver> ACVM<B> {
@@ -128,11 +242,34 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
             block_solvers: HashMap::default(),
             opcodes_and_labels,
             witness_map: initial_witness,
-            pending_foreign_calls: Vec::new(),
-            pending_brillig_label_maps: HashMap::new(),
+            pending_foreign_calls: BTreeMap::new(),
+            pending_brillig_labels: HashMap::new(),
+            next_foreign_call_id: ForeignCallId(0),
+            profile: None,
+            assert_messages: Vec::new(),
+            brillig_instruction_budget: None,
+            breakpoints: Vec::new(),
+            solve_state: None,
         }
     }
 
+    /// Enables collection of a [`SolveProfile`] during subsequent calls to [`ACVM::solve`].
+    pub fn enable_profiling(&mut self) {
+        self.profile.get_or_insert_with(SolveProfile::default);
+    }
+
+    /// Returns the statistics collected so far if profiling has been enabled.
+    pub fn profile(&self) -> Option<&SolveProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Sets the messages to report when an opcode fails, usually taken from [`Circuit::assert_messages`][acir::circuit::Circuit::assert_messages].
+    ///
+    /// Failures of an opcode with a message are reported as [`OpcodeResolutionError::AssertionFailed`].
+    pub fn set_assert_messages(&mut self, assert_messages: Vec<(OpcodeLocation, String)>) {
+        self.assert_messages = assert_messages;
+    }
+
     /// Returns a reference to the current state of the ACVM's [`WitnessMap`].
     ///
     /// Once execution has completed, the witness map can be extracted using [`ACVM::finalize`]
@@ -140,6 +277,71 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
         &self.witness_map
     }
 
+    /// Limits the number of instructions which the bytecode of each Brillig opcode may execute.
+    ///
+    /// Brillig opcodes which exceed the budget fail with [`OpcodeResolutionError::BrilligFunctionFailed`],
+    /// rather than running forever if their bytecode doesn't terminate.
+    pub fn set_brillig_instruction_budget(&mut self, budget: Option<usize>) {
+        self.brillig_instruction_budget = budget;
+    }
+
+    /// Assigns `value` to `witness`, returning the value which it replaced.
+    ///
+    /// This can be used to alter the witness map while execution is paused. Opcodes which are waiting
+    /// on `witness` will be attempted again, however opcodes which have already been solved using its
+    /// previous value are not.
+    pub fn overwrite_witness(
+        &mut self,
+        witness: Witness,
+        value: FieldElement,
+    ) -> Option<FieldElement> {
+        if let Some(state) = &mut self.solve_state {
+            for &reader in state.graph.readers(&witness) {
+                if state.waiting[reader] {
+                    state.waiting[reader] = false;
+                    state.next_ready.insert(reader);
+                }
+            }
+        }
+        self.witness_map.insert(witness, value)
+    }
+
+    /// Pauses [`ACVM::solve`] before each attempt to solve the opcode with the label `opcode_label`.
+    ///
+    /// Execution is paused with the status [`ACVMStatus::InProgress`] and continues from the breakpoint
+    /// on the next call to [`ACVM::solve`] or [`ACVM::step`].
+    pub fn add_breakpoint(&mut self, opcode_label: OpcodeLabel) {
+        if !self.breakpoints.contains(&opcode_label) {
+            self.breakpoints.push(opcode_label);
+        }
+    }
+
+    /// Removes the breakpoint on the opcode with the label `opcode_label`, returning whether there was one.
+    pub fn remove_breakpoint(&mut self, opcode_label: OpcodeLabel) -> bool {
+        let num_breakpoints = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| *breakpoint != opcode_label);
+        self.breakpoints.len() != num_breakpoints
+    }
+
+    /// Returns the opcode which will be attempted next, if any remain to be attempted.
+    pub fn next_opcode(&self) -> Option<&(Opcode, OpcodeLabel)> {
+        let index = match &self.solve_state {
+            Some(state) => match &state.brillig {
+                Some(session) => session.index,
+                None => *state.ready.first().or_else(|| state.next_ready.first())?,
+            },
+            None => 0,
+        };
+        self.opcodes_and_labels.get(index)
+    }
+
+    /// Returns the VM executing the bytecode of the Brillig opcode which is being stepped through.
+    ///
+    /// See [`ACVM::step_into_brillig`].
+    pub fn brillig_vm(&self) -> Option<&VM> {
+        self.solve_state.as_ref()?.brillig.as_ref().map(|session| &*session.vm)
+    }
+
     /// Returns a slice containing the opcodes which remain to be solved.
     ///
     /// Note: this doesn't include any opcodes which are waiting on a pending foreign call.
@@ -157,9 +359,31 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
     /// Sets the VM status to [ACVMStatus::Failure] using the provided `error`.
     /// Returns the new status.
     fn fail(&mut self, error: OpcodeResolutionError) -> ACVMStatus {
+        let error = match self.assert_message(&error) {
+            Some(message) => OpcodeResolutionError::AssertionFailed {
+                message: message.to_owned(),
+                source: Box::new(error),
+            },
+            None => error,
+        };
         self.status(ACVMStatus::Failure(error))
     }
 
+    /// Returns the assertion message of the opcode which caused `error`, if it has one.
+    ///
+    /// The message of the failing instruction is preferred for Brillig opcodes, falling back to that of
+    /// the opcode as a whole.
+    fn assert_message(&self, error: &OpcodeResolutionError) -> Option<&str> {
+        let location = error.opcode_location()?;
+        let find_message = |location: OpcodeLocation| {
+            self.assert_messages
+                .iter()
+                .find(|(message_location, _)| *message_location == location)
+                .map(|(_, message)| message.as_str())
+        };
+        find_message(location).or_else(|| find_message(OpcodeLocation::Acir(location.acir_index())))
+    }
+
     /// Finalize the ACVM execution, returning the resulting [`WitnessMap`].
     pub fn finalize(self) -> WitnessMap {
         if self.status != ACVMStatus::Solved {
@@ -170,19 +394,56 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
 
     /// Return a reference to the arguments for the next pending foreign call, if one exists.
     pub fn get_pending_foreign_call(&self) -> Option<&ForeignCallWaitInfo> {
-        self.pending_foreign_calls.first().map(|foreign_call| &foreign_call.foreign_call_wait_info)
+        self.pending_foreign_calls
+            .values()
+            .next()
+            .map(|foreign_call| &foreign_call.foreign_call_wait_info)
+    }
+
+    /// Returns all of the pending foreign calls along with their ids, in the order they were made.
+    ///
+    /// The calls are independent of each other, so they may be computed together and then resolved
+    /// in any order using [`ACVM::resolve_foreign_call`].
+    pub fn pending_foreign_calls(
+        &self,
+    ) -> impl Iterator<Item = (ForeignCallId, &ForeignCallWaitInfo)> + '_ {
+        self.pending_foreign_calls
+            .iter()
+            .map(|(id, foreign_call)| (*id, &foreign_call.foreign_call_wait_info))
     }
 
-    /// Resolves a pending foreign call using a result calculated outside of the ACVM.
+    /// Resolves the pending foreign call returned by [`ACVM::get_pending_foreign_call`] using a result
+    /// calculated outside of the ACVM.
+    ///
+    /// The result may instead carry an [error][ForeignCallResult::error] if the caller failed to compute it,
+    /// in which case solving fails with [`OpcodeResolutionError::ForeignCallFailed`].
     pub fn resolve_pending_foreign_call(&mut self, foreign_call_result: ForeignCallResult) {
-        // Remove the first foreign call and inject the result to create a new opcode.
-        let foreign_call = self.pending_foreign_calls.remove(0);
+        let id = *self.pending_foreign_calls.keys().next().expect("no foreign call is pending");
+        self.resolve_foreign_call(id, foreign_call_result);
+    }
+
+    /// Resolves the pending foreign call with the given id, as with [`ACVM::resolve_pending_foreign_call`].
+    ///
+    /// Returns `false` if there is no pending foreign call with this id.
+    pub fn resolve_foreign_call(
+        &mut self,
+        id: ForeignCallId,
+        foreign_call_result: ForeignCallResult,
+    ) -> bool {
+        // Remove the foreign call and inject the result to create a new opcode.
+        let foreign_call = match self.pending_foreign_calls.remove(&id) {
+            Some(foreign_call) => foreign_call,
+            None => return false,
+        };
+        let opcode_label = self.pending_brillig_labels.remove(&id).expect("label of pending call");
         let resolved_brillig = foreign_call.resolve(foreign_call_result);
 
         // Mark this opcode to be executed next.
-        let hash = canonical_brillig_hash(&resolved_brillig);
-        self.opcodes_and_labels
-            .insert(0, (Opcode::Brillig(resolved_brillig), self.pending_brillig_label_maps[&hash]));
+        self.opcodes_and_labels.insert(0, (Opcode::Brillig(resolved_brillig), opcode_label));
+        if let Some(state) = &mut self.solve_state {
+            state.insert_first(&self.opcodes_and_labels);
+        }
+        true
     }
 
     /// Executes the ACVM's circuit until execution halts.
@@ -191,15 +452,199 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
     /// 1. All opcodes have been executed successfully.
     /// 2. The circuit has been found to be unsatisfiable.
     /// 2. A Brillig [foreign call][`UnresolvedBrilligCall`] has been encountered and must be resolved.
+    ///
+    /// Execution is also paused before attempting an opcode with a [breakpoint][`ACVM::add_breakpoint`],
+    /// in which case [`ACVMStatus::InProgress`] is returned.
+    ///
+    /// Opcodes are solved in the order in which their inputs become available. An opcode which is
+    /// missing inputs is set aside until one of the witnesses it reads has been assigned, rather than
+    /// being retried on every pass over the circuit.
     pub fn solve(&mut self) -> ACVMStatus {
+        self.solve_with(no_presolve)
+    }
+
+    /// Executes the ACVM's circuit as with [`ACVM::solve`], resolving each foreign call using `executor`
+    /// rather than returning [`ACVMStatus::RequiresForeignCall`].
+    ///
+    /// A foreign call which `executor` fails to resolve is resolved with its error, so that solving fails
+    /// with [`OpcodeResolutionError::ForeignCallFailed`].
+    pub fn solve_with_foreign_calls(
+        &mut self,
+        executor: &mut impl ForeignCallExecutor,
+    ) -> ACVMStatus {
+        loop {
+            match self.solve() {
+                ACVMStatus::RequiresForeignCall => {
+                    let results: Vec<_> = self
+                        .pending_foreign_calls()
+                        .map(|(id, foreign_call)| {
+                            let result = executor
+                                .execute(foreign_call)
+                                .unwrap_or_else(|err| ForeignCallResult::error(err.to_string()));
+                            (id, result)
+                        })
+                        .collect();
+                    for (id, result) in results {
+                        self.resolve_foreign_call(id, result);
+                    }
+                }
+                status => return status,
+            }
+        }
+    }
+
+    /// Attempts to solve the next opcode, returning [`ACVMStatus::InProgress`] if there are more opcodes
+    /// to attempt.
+    ///
+    /// While a Brillig opcode is being stepped through, this instead executes a single instruction of its
+    /// bytecode. Breakpoints are ignored.
+    pub fn step(&mut self) -> ACVMStatus {
+        let mut state = self.take_solve_state();
+        let index = match &mut state.brillig {
+            Some(session) => {
+                session.status = session.vm.process_opcode();
+                if session.status == VMStatus::InProgress {
+                    // No opcode has been solved by this instruction so there is nothing to compact.
+                    self.solve_state = Some(state);
+                    return self.status(ACVMStatus::InProgress);
+                }
+                session.index
+            }
+            None => match self.next_ready_opcode(&mut state, &mut no_presolve) {
+                Some(index) => index,
+                None => return self.finish_solve(state),
+            },
+        };
+        if let Err(status) = self.attempt_opcode(&mut state, index) {
+            return status;
+        }
+
+        if state.is_exhausted() {
+            self.finish_solve(state)
+        } else {
+            self.pause(state)
+        }
+    }
+
+    /// Starts stepping through the bytecode of the next opcode if it is a Brillig opcode whose inputs
+    /// have been assigned, returning whether a Brillig opcode is now being stepped through.
+    ///
+    /// Each subsequent call to [`ACVM::step`] executes a single instruction, with the state of the VM
+    /// available from [`ACVM::brillig_vm`], until the bytecode finishes executing and the opcode is solved.
+    pub fn step_into_brillig(&mut self) -> bool {
+        let mut state = self.take_solve_state();
+        if state.brillig.is_none() {
+            if let Some(index) = self.next_ready_opcode(&mut state, &mut no_presolve) {
+                if let Opcode::Brillig(brillig) = &self.opcodes_and_labels[index].0 {
+                    if let Ok(BrilligPreparation::Ready(vm)) = BrilligSolver::prepare(
+                        &self.witness_map,
+                        brillig,
+                        self.brillig_instruction_budget,
+                    ) {
+                        state.brillig =
+                            Some(BrilligSession { index, vm, status: VMStatus::InProgress });
+                    }
+                }
+            }
+        }
+        let stepping_into_brillig = state.brillig.is_some();
+        self.pause(state);
+        stepping_into_brillig
+    }
+
+    /// Runs the solving loop described in [`ACVM::solve`] until execution halts or reaches a breakpoint.
+    ///
+    /// At the start of each pass, `presolve` may solve any of the ready opcodes ahead of time. Their results
+    /// are then applied in circuit order as if the opcode had been solved during the pass.
+    fn solve_with(&mut self, mut presolve: impl Presolve<B>) -> ACVMStatus {
         // TODO: Prevent execution with outstanding foreign calls?
-        let mut unresolved_opcodes: Vec<(Opcode, OpcodeLabel)> = Vec::new();
-        while !self.opcodes_and_labels.is_empty() {
-            unresolved_opcodes.clear();
-            let mut stalled = true;
-            let mut opcode_not_solvable = None;
-            for (opcode, opcode_label) in &self.opcodes_and_labels {
-                let mut resolution = match opcode {
+        let mut state = self.take_solve_state();
+        loop {
+            let index = match &state.brillig {
+                // Finish executing the bytecode of the Brillig opcode which was being stepped through.
+                Some(session) => session.index,
+                None => match self.next_ready_opcode(&mut state, &mut presolve) {
+                    Some(index) => index,
+                    None => return self.finish_solve(state),
+                },
+            };
+            if state.brillig.is_none()
+                && state.breakpoint_hit != Some(index)
+                && self.breakpoints.contains(&self.opcodes_and_labels[index].1)
+            {
+                state.breakpoint_hit = Some(index);
+                return self.pause(state);
+            }
+            if let Err(status) = self.attempt_opcode(&mut state, index) {
+                return status;
+            }
+        }
+    }
+
+    /// Returns the progress of the paused solving loop, or starts a new run of it.
+    fn take_solve_state(&mut self) -> SolveState {
+        self.solve_state.take().unwrap_or_else(|| SolveState::new(&self.opcodes_and_labels))
+    }
+
+    /// Keeps the progress of the solving loop so that it can be resumed.
+    fn pause(&mut self, mut state: SolveState) -> ACVMStatus {
+        state.compact(&mut self.opcodes_and_labels);
+        self.solve_state = Some(state);
+        self.status(ACVMStatus::InProgress)
+    }
+
+    /// Returns the index of the next opcode to attempt, starting a new pass if the current one is complete.
+    ///
+    /// Opcodes are attempted in passes, with each pass containing the opcodes whose inputs
+    /// were assigned during the previous one. Within a pass, opcodes are attempted in circuit order.
+    fn next_ready_opcode(
+        &mut self,
+        state: &mut SolveState,
+        presolve: &mut impl Presolve<B>,
+    ) -> Option<usize> {
+        if state.ready.is_empty() {
+            if state.next_ready.is_empty() {
+                return None;
+            }
+            state.ready = std::mem::take(&mut state.next_ready);
+            if let Some(profile) = &mut self.profile {
+                profile.record_pass();
+            }
+            state.presolved = presolve(self, &state.graph, &state.ready);
+        }
+        state.ready.first().copied()
+    }
+
+    /// Attempts to solve the opcode at `index`, returning the failure status if it can't be satisfied.
+    fn attempt_opcode(&mut self, state: &mut SolveState, index: usize) -> Result<(), ACVMStatus> {
+        state.ready.remove(&index);
+        state.breakpoint_hit = None;
+        let (opcode, opcode_label) = &self.opcodes_and_labels[index];
+        let unassigned_witnesses = state.graph.unassigned_witnesses(index, &self.witness_map);
+
+        let (mut resolution, elapsed) = match (state.presolved.remove(&index), state.brillig.take())
+        {
+            (Some(presolved), _) => {
+                let elapsed = presolved.elapsed;
+                (presolved.merge_into(&mut self.witness_map), Some(elapsed))
+            }
+            (None, Some(BrilligSession { vm: mut brillig_vm, status, .. })) => {
+                let brillig = match opcode {
+                    Opcode::Brillig(brillig) => brillig,
+                    _ => unreachable!("stepping through the bytecode of a non brillig opcode"),
+                };
+                let start = self.profile.is_some().then(Instant::now);
+                let vm_status = match status {
+                    VMStatus::InProgress => brillig_vm.process_opcodes(),
+                    status => status,
+                };
+                let resolution =
+                    BrilligSolver::resolve(&mut self.witness_map, brillig, &brillig_vm, vm_status);
+                (resolution, start.map(|start| start.elapsed()))
+            }
+            (None, None) => {
+                let start = self.profile.is_some().then(Instant::now);
+                let resolution = match opcode {
                     Opcode::Arithmetic(expr) => {
                         ArithmeticSolver::solve(&mut self.witness_map, expr)
                     }
@@ -213,91 +658,160 @@ impl<B: BlackBoxFunctionSolver> ACVM<B> {
                         let solver = self.block_solvers.entry(block.id).or_default();
                         solver.solve(&mut self.witness_map, &block.trace)
                     }
-                    Opcode::Brillig(brillig) => {
-                        BrilligSolver::solve(&mut self.witness_map, brillig)
-                    }
+                    Opcode::Brillig(brillig) => BrilligSolver::solve(
+                        &mut self.witness_map,
+                        brillig,
+                        self.brillig_instruction_budget,
+                    ),
                 };
+                (resolution, start.map(|start| start.elapsed()))
+            }
+        };
+        if let (Some(profile), Some(elapsed)) = (&mut self.profile, elapsed) {
+            profile.record_attempt(opcode, elapsed);
+            if let Ok(OpcodeResolution::Stalled(_)) = &resolution {
+                profile.record_stall(*opcode_label);
+            }
+        }
 
-                // If we have an unsatisfied constraint, the opcode label will be unresolved
-                // because the solvers do not have knowledge of this information.
-                // We resolve, by setting this to the corresponding opcode that we just attempted to solve.
-                if let Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                    opcode_label: opcode_index,
-                }) = &mut resolution
-                {
-                    *opcode_index = *opcode_label;
-                }
-                // If a brillig function has failed, we return an unsatisfied constraint error
-                // We intentionally ignore the brillig failure message, as there is no way to
-                // propagate this to the caller.
-                if let Err(OpcodeResolutionError::BrilligFunctionFailed(_)) = &mut resolution {
-                    //
-                    // std::mem::swap(x, y)
-                    resolution = Err(OpcodeResolutionError::UnsatisfiedConstrain {
-                        opcode_label: *opcode_label,
-                    })
-                }
+        // If we have an unsatisfied constraint, the opcode label will be unresolved
+        // because the solvers do not have knowledge of this information.
+        // We resolve, by setting this to the corresponding opcode that we just attempted to solve.
+        match &mut resolution {
+            Err(OpcodeResolutionError::UnsatisfiedConstrain { opcode_label: label })
+            | Err(OpcodeResolutionError::BrilligFunctionFailed { opcode_label: label, .. })
+            | Err(OpcodeResolutionError::ForeignCallFailed { opcode_label: label, .. }) => {
+                *label = *opcode_label
+            }
+            Err(OpcodeResolutionError::UnsatisfiedArithmetic(unsatisfied)) => {
+                unsatisfied.opcode_label = *opcode_label;
+            }
+            _ => (),
+        }
 
-                match resolution {
-                    Ok(OpcodeResolution::Solved) => {
-                        stalled = false;
-                    }
-                    Ok(OpcodeResolution::InProgress) => {
-                        stalled = false;
-                        unresolved_opcodes.push((opcode.clone(), *opcode_label));
-                    }
-                    Ok(OpcodeResolution::InProgressBrillig(oracle_wait_info)) => {
-                        stalled = false;
-                        // InProgressBrillig Oracles must be externally re-solved
-                        let brillig = match &opcode {
-                            Opcode::Brillig(brillig) => brillig.clone(),
-                            _ => unreachable!("Brillig resolution for non brillig opcode"),
-                        };
-                        let hash = canonical_brillig_hash(&brillig);
-                        self.pending_brillig_label_maps.insert(hash, *opcode_label);
-                        self.pending_foreign_calls.push(UnresolvedBrilligCall {
-                            brillig,
-                            foreign_call_wait_info: oracle_wait_info,
-                        })
-                    }
-                    Ok(OpcodeResolution::Stalled(not_solvable)) => {
-                        if opcode_not_solvable.is_none() {
-                            // we keep track of the first unsolvable opcode
-                            opcode_not_solvable = Some(not_solvable);
-                        }
-                        // We push those opcodes not solvable to the back as
-                        // it could be because the opcodes are out of order, i.e. this assignment
-                        // relies on a later opcodes' results
-                        unresolved_opcodes.push((opcode.clone(), *opcode_label));
-                    }
-                    Err(OpcodeResolutionError::OpcodeNotSolvable(_)) => {
-                        unreachable!("ICE - Result should have been converted to GateResolution")
-                    }
-                    Err(error) => return self.fail(error),
+        match resolution {
+            Ok(OpcodeResolution::Solved) => {
+                state.finished[index] = true;
+            }
+            Ok(OpcodeResolution::InProgress) => {
+                state.next_ready.insert(index);
+            }
+            Ok(OpcodeResolution::InProgressBrillig(oracle_wait_info)) => {
+                state.finished[index] = true;
+                // InProgressBrillig Oracles must be externally re-solved
+                let brillig = match &opcode {
+                    Opcode::Brillig(brillig) => brillig.clone(),
+                    _ => unreachable!("Brillig resolution for non brillig opcode"),
+                };
+                let id = self.next_foreign_call_id;
+                self.next_foreign_call_id = ForeignCallId(id.0 + 1);
+                self.pending_brillig_labels.insert(id, *opcode_label);
+                self.pending_foreign_calls.insert(
+                    id,
+                    UnresolvedBrilligCall { brillig, foreign_call_wait_info: oracle_wait_info },
+                );
+            }
+            Ok(OpcodeResolution::Stalled(_)) => {
+                // The opcode relies on the results of opcodes which have not been solved yet
+                // so we set it aside until one of its inputs is assigned.
+                state.waiting[index] = true;
+            }
+            Err(OpcodeResolutionError::OpcodeNotSolvable(_)) => {
+                unreachable!("ICE - Result should have been converted to GateResolution")
+            }
+            Err(error) => return Err(self.fail(error)),
+        }
+
+        // Any waiting opcodes which read a witness that has just been assigned may now be solvable.
+        for witness in unassigned_witnesses {
+            if !self.witness_map.contains_key(&witness) {
+                continue;
+            }
+            for &reader in state.graph.readers(&witness) {
+                if state.waiting[reader] {
+                    state.waiting[reader] = false;
+                    state.next_ready.insert(reader);
                 }
             }
+        }
+        Ok(())
+    }
 
-            // Before potentially ending execution, we must save the list of opcodes which remain to be solved.
-            std::mem::swap(&mut self.opcodes_and_labels, &mut unresolved_opcodes);
+    /// Ends the run of the solving loop once there are no more opcodes to attempt.
+    fn finish_solve(&mut self, state: SolveState) -> ACVMStatus {
+        // Before potentially ending execution, we must save the list of opcodes which remain to be solved.
+        let unsolvable_opcodes: Vec<UnsolvableOpcode> = state
+            .waiting
+            .iter()
+            .enumerate()
+            .filter(|(_, is_waiting)| **is_waiting)
+            .map(|(index, _)| UnsolvableOpcode {
+                opcode_label: self.opcodes_and_labels[index].1,
+                missing_witnesses: state.graph.missing_inputs(index, &self.witness_map),
+            })
+            .collect();
+        let mut finished = state.finished.into_iter();
+        self.opcodes_and_labels.retain(|_| !finished.next().expect("one entry per opcode"));
 
-            // We have oracles that must be externally resolved
-            if self.get_pending_foreign_call().is_some() {
-                return self.status(ACVMStatus::RequiresForeignCall);
-            }
+        // We have oracles that must be externally resolved
+        if self.get_pending_foreign_call().is_some() {
+            return self.status(ACVMStatus::RequiresForeignCall);
+        }
 
-            // We are stalled because of an opcode being bad
-            if stalled && !self.opcodes_and_labels.is_empty() {
-                let error = OpcodeResolutionError::OpcodeNotSolvable(
-                    opcode_not_solvable
-                        .expect("infallible: cannot be stalled and None at the same time"),
-                );
-                return self.fail(error);
-            }
+        // We are stalled because some opcodes are waiting on witnesses which are never assigned
+        if !unsolvable_opcodes.is_empty() {
+            return self.fail(OpcodeResolutionError::UnsolvableOpcodes(unsolvable_opcodes));
         }
         self.status(ACVMStatus::Solved)
     }
 }
 
+/// Solves any of the ready opcodes ahead of their turn in a pass of the solving loop.
+trait Presolve<B: BlackBoxFunctionSolver>:
+    FnMut(&ACVM<B>, &DependencyGraph, &BTreeSet<usize>) -> BTreeMap<usize, PresolvedOpcode>
+{
+}
+
+impl<B, F> Presolve<B> for F
+where
+    B: BlackBoxFunctionSolver,
+    F: FnMut(&ACVM<B>, &DependencyGraph, &BTreeSet<usize>) -> BTreeMap<usize, PresolvedOpcode>,
+{
+}
+
+/// Solves opcodes only during their turn in the solving loop.
+fn no_presolve<B: BlackBoxFunctionSolver>(
+    _: &ACVM<B>,
+    _: &DependencyGraph,
+    _: &BTreeSet<usize>,
+) -> BTreeMap<usize, PresolvedOpcode> {
+    BTreeMap::new()
+}
+
+#[cfg(feature = "parallel")]
+impl<B: BlackBoxFunctionSolver + Sync> ACVM<B> {
+    /// Executes the ACVM's circuit until execution halts, as with [`ACVM::solve`], but solves independent
+    /// black box function calls on multiple threads.
+    ///
+    /// At the start of each pass, the black box function calls which have all of their inputs assigned are
+    /// solved in parallel. Their results are then applied in circuit order so the resulting [`WitnessMap`]
+    /// and [`ACVMStatus`] are identical to those of [`ACVM::solve`].
+    ///
+    /// The calls are solved on the current rayon thread pool, so the number of threads can be controlled
+    /// by calling this from within [`rayon::ThreadPool::install`].
+    pub fn solve_parallel(&mut self) -> ACVMStatus {
+        self.solve_with(|acvm, graph, ready| {
+            parallel::presolve_black_box_calls(
+                &acvm.backend,
+                &acvm.opcodes_and_labels,
+                &acvm.witness_map,
+                graph,
+                ready,
+            )
+        })
+    }
+}
+
 // Returns the concrete value for a particular witness
 // If the witness has no assignment, then
 // an error is returned
@@ -353,6 +867,12 @@ pub fn insert_value(
     Ok(())
 }
 
+/// Identifies a pending foreign call until it is resolved.
+///
+/// Ids are assigned in the order in which foreign calls are made and are never reused by the same [ACVM].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ForeignCallId(pub u64);
+
 /// A Brillig VM process has requested the caller to solve a [foreign call][brillig_vm::Opcode::ForeignCall] externally
 /// and to re-run the process with the foreign call's resolved outputs.
 #[derive(Debug, PartialEq, Clone)]
@@ -409,9 +929,9 @@ pub fn default_is_opcode_supported(language: Language) -> fn(&Opcode) -> bool {
 /// Some Brillig instances may or may not be resolved, so we do
 /// not hash the `foreign_call_results`.
 ///
-/// This gives us a consistent hash that will be used to track `Brillig`
-/// even when it is put back into the list of opcodes out of order.
-/// This happens when we resolve a Brillig opcode call.
+/// This gives us a consistent hash for a `Brillig` opcode as it is resolved.
+/// Identical opcodes share the same hash, so the ACVM tracks pending
+/// foreign calls by [`ForeignCallId`] instead.
 pub fn canonical_brillig_hash(brillig: &Brillig) -> UnresolvedBrilligCallHash {
     let mut serialized_vector = rmp_serde::to_vec(&brillig.inputs).unwrap();
     serialized_vector.extend(rmp_serde::to_vec(&brillig.outputs).unwrap());
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use acir::native_types::WitnessMap;
+
+use super::{insert_value, OpcodeResolution, OpcodeResolutionError};
+
+/// The result of solving an opcode ahead of its turn in the solving loop, against a copy of the
+/// witnesses which it references.
+// Opcodes are only solved ahead of time by `ACVM::solve_parallel`.
+#[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+pub(super) struct PresolvedOpcode {
+    resolution: Result<OpcodeResolution, OpcodeResolutionError>,
+    /// The witnesses referenced by the opcode, including those assigned by solving it.
+    witness_map: WitnessMap,
+    pub(super) elapsed: Duration,
+}
+
+#[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+impl PresolvedOpcode {
+    /// Inserts the witnesses assigned by solving the opcode into `witness_map`, returning the
+    /// resolution of the opcode.
+    ///
+    /// The opcode's outputs may have been assigned since it was solved, in which case they must agree.
+    pub(super) fn merge_into(
+        self,
+        witness_map: &mut WitnessMap,
+    ) -> Result<OpcodeResolution, OpcodeResolutionError> {
+        let resolution = self.resolution?;
+        for (witness, value) in self.witness_map {
+            insert_value(&witness, value, witness_map)?;
+        }
+        Ok(resolution)
+    }
+}
+
+#[cfg(feature = "parallel")]
+pub(super) use threaded::presolve_black_box_calls;
+
+#[cfg(feature = "parallel")]
+mod threaded {
+    use std::{
+        collections::{BTreeMap, BTreeSet, HashSet},
+        time::Instant,
+    };
+
+    use acir::{
+        circuit::{opcodes::BlackBoxFuncCall, Opcode, OpcodeLabel},
+        native_types::WitnessMap,
+    };
+
+    use rayon::prelude::*;
+
+    use super::PresolvedOpcode;
+    use crate::{
+        pwg::{blackbox, graph::DependencyGraph},
+        BlackBoxFunctionSolver,
+    };
+
+    /// Solves the black box function calls among the `ready` opcodes which have all of their inputs
+    /// assigned, spreading them across the threads of rayon's global pool.
+    ///
+    /// Calls which would assign the same witness are not solved together as the solving loop must
+    /// detect any disagreement between them, so only the first such call in circuit order is solved ahead of time.
+    pub(in crate::pwg) fn presolve_black_box_calls<B: BlackBoxFunctionSolver + Sync>(
+        backend: &B,
+        opcodes_and_labels: &[(Opcode, OpcodeLabel)],
+        witness_map: &WitnessMap,
+        graph: &DependencyGraph,
+        ready: &BTreeSet<usize>,
+    ) -> BTreeMap<usize, PresolvedOpcode> {
+        let mut claimed_outputs = HashSet::new();
+        let mut independent_calls = Vec::new();
+        for &index in ready {
+            let bb_func = match &opcodes_and_labels[index].0 {
+                Opcode::BlackBoxFuncCall(bb_func) => bb_func,
+                _ => continue,
+            };
+            if !graph.missing_inputs(index, witness_map).is_empty() {
+                continue;
+            }
+            let outputs = graph.unassigned_witnesses(index, witness_map);
+            if outputs.iter().any(|output| claimed_outputs.contains(output)) {
+                continue;
+            }
+            claimed_outputs.extend(outputs);
+            independent_calls.push((index, bb_func));
+        }
+
+        // There's nothing to gain from solving a single call on another thread.
+        if independent_calls.len() < 2 {
+            return BTreeMap::new();
+        }
+
+        // Each task solves a chunk of the calls so that the pool isn't flooded with many small tasks.
+        let chunk_size = (independent_calls.len() + rayon::current_num_threads() - 1)
+            / rayon::current_num_threads();
+        independent_calls
+            .par_chunks(chunk_size)
+            .flat_map_iter(|calls| {
+                calls.iter().map(|(index, bb_func)| {
+                    (*index, presolve(backend, witness_map, graph, *index, bb_func))
+                })
+            })
+            .collect()
+    }
+
+    fn presolve<B: BlackBoxFunctionSolver>(
+        backend: &B,
+        witness_map: &WitnessMap,
+        graph: &DependencyGraph,
+        index: usize,
+        bb_func: &BlackBoxFuncCall,
+    ) -> PresolvedOpcode {
+        let mut local_witness_map = WitnessMap::new();
+        for witness in graph.witnesses(index) {
+            if let Some(value) = witness_map.get(witness) {
+                local_witness_map.insert(*witness, *value);
+            }
+        }
+
+        let start = Instant::now();
+        let resolution = blackbox::solve(backend, &mut local_witness_map, bb_func);
+        PresolvedOpcode { resolution, witness_map: local_witness_map, elapsed: start.elapsed() }
+    }
+}
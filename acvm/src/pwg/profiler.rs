@@ -0,0 +1,73 @@
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use acir::circuit::{Opcode, OpcodeLabel};
+use serde::{Deserialize, Serialize};
+
+/// Statistics collected by [`ACVM::solve`][super::ACVM::solve] when profiling is enabled with
+/// [`ACVM::enable_profiling`][super::ACVM::enable_profiling].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolveProfile {
+    /// Number of passes made over the opcodes whose inputs became available during the previous pass.
+    pub passes: usize,
+    /// Number of times each opcode was stalled waiting on the result of another opcode, keyed by opcode label.
+    ///
+    /// Opcodes which never stalled are omitted.
+    pub stalls: BTreeMap<u64, usize>,
+    /// Time spent solving each kind of opcode, keyed by [name][Opcode::name].
+    pub opcodes: BTreeMap<String, OpcodeProfile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpcodeProfile {
+    /// The family of opcodes this kind belongs to, e.g. `black_box` for `sha256`.
+    pub category: String,
+    /// Number of times an opcode of this kind was passed to its solver.
+    pub attempts: usize,
+    pub total_nanos: u64,
+}
+
+impl SolveProfile {
+    pub(super) fn record_pass(&mut self) {
+        self.passes += 1;
+    }
+
+    pub(super) fn record_attempt(&mut self, opcode: &Opcode, elapsed: Duration) {
+        let profile = self.opcodes.entry(opcode.name().to_owned()).or_insert_with(|| {
+            OpcodeProfile { category: category(opcode).to_owned(), ..OpcodeProfile::default() }
+        });
+        profile.attempts += 1;
+        profile.total_nanos += elapsed.as_nanos() as u64;
+    }
+
+    pub(super) fn record_stall(&mut self, opcode_label: OpcodeLabel) {
+        if let OpcodeLabel::Resolved(label) = opcode_label {
+            *self.stalls.entry(label).or_default() += 1;
+        }
+    }
+
+    /// Serializes the profile as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("profiles should always be serializable")
+    }
+
+    /// Returns the time spent on each kind of opcode in the folded stack format consumed by flamegraph tools,
+    /// with one `solve;<category>;<name> <nanoseconds>` line per kind of opcode.
+    pub fn to_folded_stacks(&self) -> String {
+        let mut folded = String::new();
+        for (name, profile) in &self.opcodes {
+            writeln!(folded, "solve;{};{} {}", profile.category, name, profile.total_nanos)
+                .expect("writing to a string cannot fail");
+        }
+        folded
+    }
+}
+
+fn category(opcode: &Opcode) -> &'static str {
+    match opcode {
+        Opcode::Arithmetic(_) => "arithmetic",
+        Opcode::BlackBoxFuncCall(_) => "black_box",
+        Opcode::Directive(_) => "directive",
+        Opcode::Block(_) | Opcode::ROM(_) | Opcode::RAM(_) => "memory",
+        Opcode::Brillig(_) => "brillig",
+    }
+}
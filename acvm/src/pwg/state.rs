@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use acir::{
+    brillig_vm::{VMStatus, VM},
+    circuit::{Opcode, OpcodeLabel},
+};
+
+use super::{graph::DependencyGraph, parallel::PresolvedOpcode};
+
+/// The progress of the solving loop, which is kept between calls so that execution can be paused part way
+/// through the circuit and later resumed.
+///
+/// Indices refer to the opcodes in [`ACVM::unresolved_opcodes`][super::ACVM::unresolved_opcodes].
+pub(super) struct SolveState {
+    pub(super) graph: DependencyGraph,
+    /// Opcodes which remain to be attempted during the current pass, in circuit order.
+    pub(super) ready: BTreeSet<usize>,
+    /// Opcodes which are to be attempted during the next pass.
+    pub(super) next_ready: BTreeSet<usize>,
+    /// Opcodes which have been attempted and are now waiting on one of their inputs to be assigned.
+    pub(super) waiting: Vec<bool>,
+    /// Opcodes which no longer need to be solved, either because they have been solved or
+    /// because they are waiting on a foreign call.
+    pub(super) finished: Vec<bool>,
+    /// Opcodes of the current pass which have been solved ahead of time.
+    pub(super) presolved: BTreeMap<usize, PresolvedOpcode>,
+    /// The opcode at which execution has paused on a breakpoint, so that it isn't hit again on resuming.
+    pub(super) breakpoint_hit: Option<usize>,
+    /// The Brillig opcode whose bytecode is being stepped through.
+    pub(super) brillig: Option<BrilligSession>,
+}
+
+/// A Brillig VM which is part way through executing the bytecode of the opcode at `index`.
+pub(super) struct BrilligSession {
+    pub(super) index: usize,
+    pub(super) vm: Box<VM>,
+    /// The status returned by the last instruction executed by `vm`.
+    pub(super) status: VMStatus,
+}
+
+impl SolveState {
+    /// Creates the state for a new run of the solving loop, in which every opcode will be attempted in the first pass.
+    pub(super) fn new(opcodes_and_labels: &[(Opcode, OpcodeLabel)]) -> Self {
+        SolveState {
+            graph: DependencyGraph::new(opcodes_and_labels.iter().map(|(opcode, _)| opcode)),
+            ready: BTreeSet::new(),
+            next_ready: (0..opcodes_and_labels.len()).collect(),
+            waiting: vec![false; opcodes_and_labels.len()],
+            finished: vec![false; opcodes_and_labels.len()],
+            presolved: BTreeMap::new(),
+            breakpoint_hit: None,
+            brillig: None,
+        }
+    }
+
+    /// Returns whether there are no more opcodes to attempt during this run of the solving loop.
+    pub(super) fn is_exhausted(&self) -> bool {
+        self.ready.is_empty() && self.next_ready.is_empty() && self.brillig.is_none()
+    }
+
+    /// Removes the opcodes which no longer need to be solved from `opcodes_and_labels`, updating the state
+    /// to refer to the remaining opcodes.
+    pub(super) fn compact(&mut self, opcodes_and_labels: &mut Vec<(Opcode, OpcodeLabel)>) {
+        if !self.finished.contains(&true) {
+            return;
+        }
+
+        let mut remaining = 0;
+        let new_indices: Vec<Option<usize>> = self
+            .finished
+            .iter()
+            .map(|finished| {
+                (!finished).then(|| {
+                    remaining += 1;
+                    remaining - 1
+                })
+            })
+            .collect();
+        let remap = |indices: &BTreeSet<usize>| -> BTreeSet<usize> {
+            indices.iter().filter_map(|index| new_indices[*index]).collect()
+        };
+
+        let mut finished = self.finished.iter();
+        opcodes_and_labels.retain(|_| !finished.next().expect("one entry per opcode"));
+
+        self.graph.remap(&new_indices);
+        self.ready = remap(&self.ready);
+        self.next_ready = remap(&self.next_ready);
+        self.waiting = self
+            .waiting
+            .iter()
+            .zip(&self.finished)
+            .filter(|(_, finished)| !**finished)
+            .map(|(waiting, _)| *waiting)
+            .collect();
+        self.finished = vec![false; opcodes_and_labels.len()];
+        self.presolved = std::mem::take(&mut self.presolved)
+            .into_iter()
+            .filter_map(|(index, presolved)| Some((new_indices[index]?, presolved)))
+            .collect();
+        self.breakpoint_hit = self.breakpoint_hit.and_then(|index| new_indices[index]);
+        if let Some(session) = &mut self.brillig {
+            session.index = new_indices[session.index]
+                .expect("Brillig opcode being stepped through is unsolved");
+        }
+    }
+
+    /// Updates the state after an opcode has been inserted at the start of `opcodes_and_labels`,
+    /// scheduling it to be attempted next.
+    pub(super) fn insert_first(&mut self, opcodes_and_labels: &[(Opcode, OpcodeLabel)]) {
+        let shift = |indices: &BTreeSet<usize>| -> BTreeSet<usize> {
+            indices.iter().map(|index| index + 1).collect()
+        };
+
+        self.graph.insert_first(&opcodes_and_labels[0].0);
+        self.ready = shift(&self.ready);
+        self.ready.insert(0);
+        self.next_ready = shift(&self.next_ready);
+        self.waiting.insert(0, false);
+        self.finished.insert(0, false);
+        self.presolved = std::mem::take(&mut self.presolved)
+            .into_iter()
+            .map(|(index, presolved)| (index + 1, presolved))
+            .collect();
+        self.breakpoint_hit = self.breakpoint_hit.map(|index| index + 1);
+        if let Some(session) = &mut self.brillig {
+            session.index += 1;
+        }
+    }
+}
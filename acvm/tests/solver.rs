@@ -1,18 +1,22 @@
 use std::collections::BTreeMap;
 
 use acir::{
-    brillig_vm::{self, BinaryFieldOp, RegisterIndex, RegisterOrMemory, Value},
+    brillig_vm::{self, BinaryFieldOp, ForeignCallResult, RegisterIndex, RegisterOrMemory, Value},
     circuit::{
         brillig::{Brillig, BrilligInputs, BrilligOutputs},
         directives::Directive,
-        Opcode, OpcodeLabel,
+        Opcode, OpcodeLabel, OpcodeLocation,
     },
     native_types::{Expression, Witness, WitnessMap},
     FieldElement,
 };
 
 use acvm::{
-    pwg::{ACVMStatus, ForeignCallWaitInfo, OpcodeResolutionError, ACVM},
+    pwg::{
+        ACVMStatus, ForeignCallExecutor, ForeignCallId, ForeignCallTranscript, ForeignCallWaitInfo,
+        MapExecutor, OpcodeResolutionError, RecordingExecutor, ReplayExecutor,
+        UnsatisfiedArithmetic, UnsolvableOpcode, ACVM,
+    },
     BlackBoxFunctionSolver,
 };
 
@@ -545,14 +549,19 @@ fn unsatisfied_opcode_resolved() {
     values.insert(c, FieldElement::from(1_i128));
     values.insert(d, FieldElement::from(2_i128));
 
-    let opcodes = vec![Opcode::Arithmetic(gate_a)];
-    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    let opcodes = vec![Opcode::Arithmetic(gate_a.clone())];
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values.clone());
     let solver_status = acvm.solve();
     assert_eq!(
         solver_status,
-        ACVMStatus::Failure(OpcodeResolutionError::UnsatisfiedConstrain {
-            opcode_label: OpcodeLabel::Resolved(0)
-        }),
+        ACVMStatus::Failure(OpcodeResolutionError::UnsatisfiedArithmetic(Box::new(
+            UnsatisfiedArithmetic {
+                opcode_label: OpcodeLabel::Resolved(0),
+                opcode: gate_a,
+                residual: Expression::from_field(-FieldElement::one()),
+                witness_values: [a, b, c, d].map(|witness| (witness, values[&witness])).into(),
+            }
+        ))),
         "The first gate is not satisfiable, expected an error indicating this"
     );
 }
@@ -634,9 +643,471 @@ fn unsatisfied_opcode_resolved_brillig() {
     let solver_status = acvm.solve();
     assert_eq!(
         solver_status,
-        ACVMStatus::Failure(OpcodeResolutionError::UnsatisfiedConstrain {
-            opcode_label: OpcodeLabel::Resolved(0)
+        ACVMStatus::Failure(OpcodeResolutionError::BrilligFunctionFailed {
+            opcode_label: OpcodeLabel::Resolved(0),
+            message: "explicit trap hit in brillig".to_owned(),
+            program_counter: 2,
         }),
         "The first gate is not satisfiable, expected an error indicating this"
     );
 }
+
+#[test]
+fn profiles_solve_loop() {
+    let a = Witness(1);
+    let b = Witness(2);
+    let c = Witness(3);
+
+    // b = 2a is solved before c = a + b, so the first opcode must wait for the second and the
+    // inversion of c must wait for the first.
+    let gate_c = &(&Expression::from(a) + &Expression::from(b)) - &Expression::from(c);
+    let gate_b = &(&Expression::from(a) * FieldElement::from(2_i128)) - &Expression::from(b);
+    let opcodes = vec![
+        Opcode::Arithmetic(gate_c),
+        Opcode::Arithmetic(gate_b),
+        Opcode::Directive(Directive::Invert { x: c, result: Witness(4) }),
+    ];
+
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::from(3_i128));
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    acvm.enable_profiling();
+    assert_eq!(acvm.solve(), ACVMStatus::Solved);
+
+    let profile = acvm.profile().expect("profiling was enabled");
+    assert_eq!(profile.passes, 3);
+    assert_eq!(profile.stalls, BTreeMap::from([(0, 1), (2, 1)]));
+    assert_eq!(profile.opcodes["arithmetic"].attempts, 3);
+    assert_eq!(profile.opcodes["invert"].attempts, 2);
+    assert_eq!(profile.opcodes["invert"].category, "directive");
+
+    let folded = profile.to_folded_stacks();
+    let lines: Vec<&str> = folded.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("solve;arithmetic;arithmetic "));
+    assert!(lines[1].starts_with("solve;directive;invert "));
+
+    let json = profile.to_json();
+    assert!(json.contains("\"passes\": 3"));
+}
+
+#[test]
+fn solves_opcodes_in_dependency_order() {
+    // w_{i+1} = w_i + 1 for a chain of witnesses, with the opcodes in reverse order.
+    let chain_length = 50;
+    let opcodes: Vec<Opcode> = (1..chain_length)
+        .rev()
+        .map(|i| {
+            let next = &Expression::from(Witness(i)) + &Expression::one();
+            Opcode::Arithmetic(&next - &Expression::from(Witness(i + 1)))
+        })
+        .collect();
+
+    let mut values = WitnessMap::new();
+    values.insert(Witness(1), FieldElement::one());
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    acvm.enable_profiling();
+    assert_eq!(acvm.solve(), ACVMStatus::Solved);
+
+    // Each opcode is only retried once the opcode which it depends on has been solved.
+    let profile = acvm.profile().expect("profiling was enabled");
+    assert_eq!(profile.opcodes["arithmetic"].attempts, 2 * (chain_length as usize - 1) - 1);
+
+    let witness_map = acvm.finalize();
+    assert_eq!(witness_map[&Witness(chain_length)], FieldElement::from(chain_length as u128));
+}
+
+#[test]
+fn reports_missing_witnesses_of_unsolvable_opcodes() {
+    let a = Witness(1);
+    let b = Witness(2);
+    let c = Witness(3);
+    let d = Witness(4);
+    let e = Witness(5);
+
+    // c = a + b can be solved but nothing ever assigns d or e.
+    let opcodes = vec![
+        Opcode::Directive(Directive::Invert { x: d, result: Witness(6) }),
+        Opcode::Arithmetic(&(&Expression::from(a) + &Expression::from(b)) - &Expression::from(c)),
+        Opcode::Arithmetic(&(&Expression::from(d) + &Expression::from(e)) - &Expression::from(c)),
+    ];
+
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::from(3_i128));
+    values.insert(b, FieldElement::from(4_i128));
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    assert_eq!(
+        acvm.solve(),
+        ACVMStatus::Failure(OpcodeResolutionError::UnsolvableOpcodes(vec![
+            UnsolvableOpcode { opcode_label: OpcodeLabel::Resolved(0), missing_witnesses: vec![d] },
+            UnsolvableOpcode {
+                opcode_label: OpcodeLabel::Resolved(2),
+                missing_witnesses: vec![d, e]
+            },
+        ]))
+    );
+    assert_eq!(acvm.unresolved_opcodes().len(), 2);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_solve_matches_sequential_solve() {
+    use acir::circuit::opcodes::{BlackBoxFuncCall, FunctionInput};
+
+    let byte = |witness: Witness| FunctionInput { witness, num_bits: 8 };
+
+    // Each round hashes the output of the previous round, with many independent hashes per round.
+    let num_hashes = 16;
+    let mut values = WitnessMap::new();
+    let mut opcodes = Vec::new();
+    let mut next_witness = 1;
+    let mut inputs: Vec<Vec<Witness>> = (0..num_hashes)
+        .map(|i| {
+            let witness = Witness(next_witness);
+            next_witness += 1;
+            values.insert(witness, FieldElement::from(i as u128));
+            vec![witness]
+        })
+        .collect();
+    for round in 0..3 {
+        inputs = inputs
+            .into_iter()
+            .map(|hash_inputs| {
+                let outputs: Vec<Witness> =
+                    (next_witness..next_witness + 32).map(Witness).collect();
+                next_witness += 32;
+                let inputs = hash_inputs.into_iter().map(byte).collect();
+                opcodes.push(Opcode::BlackBoxFuncCall(if round % 2 == 0 {
+                    BlackBoxFuncCall::SHA256 { inputs, outputs: outputs.clone() }
+                } else {
+                    BlackBoxFuncCall::Keccak256 { inputs, outputs: outputs.clone() }
+                }));
+                outputs
+            })
+            .collect();
+    }
+    // Solve in reverse order so that hashes are only ready once their inputs have been solved.
+    opcodes.reverse();
+
+    let mut sequential = ACVM::new(StubbedBackend, opcodes.clone(), values.clone());
+    assert_eq!(sequential.solve(), ACVMStatus::Solved);
+    let mut parallel = ACVM::new(StubbedBackend, opcodes, values);
+    assert_eq!(parallel.solve_parallel(), ACVMStatus::Solved);
+
+    assert_eq!(parallel.finalize(), sequential.finalize());
+}
+
+#[test]
+fn surfaces_assert_messages() {
+    let x = Witness(1);
+    let y = Witness(2);
+
+    // assert(x == y, "x must equal y")
+    let opcodes = vec![Opcode::Arithmetic(&Expression::from(x) - &Expression::from(y))];
+    let mut values = WitnessMap::new();
+    values.insert(x, FieldElement::from(1_i128));
+    values.insert(y, FieldElement::from(2_i128));
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    acvm.set_assert_messages(vec![(OpcodeLocation::Acir(0), "x must equal y".to_owned())]);
+    let error = match acvm.solve() {
+        ACVMStatus::Failure(error) => error,
+        status => panic!("expected failure, got {status:?}"),
+    };
+    assert_eq!(error.to_string(), "assertion failed: x must equal y");
+    match error {
+        OpcodeResolutionError::AssertionFailed { source, .. } => {
+            assert!(matches!(*source, OpcodeResolutionError::UnsatisfiedArithmetic(_)));
+        }
+        error => panic!("expected assertion failure, got {error:?}"),
+    }
+}
+
+#[test]
+fn pauses_on_breakpoints_and_steps_through_opcodes() {
+    let a = Witness(1);
+    let b = Witness(2);
+    let c = Witness(3);
+
+    // b = a + 1, c = 2b
+    let gate_b = &(&Expression::from(a) + &Expression::one()) - &Expression::from(b);
+    let gate_c = &(&Expression::from(b) * FieldElement::from(2_i128)) - &Expression::from(c);
+    let opcodes = vec![Opcode::Arithmetic(gate_b), Opcode::Arithmetic(gate_c.clone())];
+
+    let mut values = WitnessMap::new();
+    values.insert(a, FieldElement::from(3_i128));
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+    acvm.add_breakpoint(OpcodeLabel::Resolved(1));
+    assert_eq!(acvm.solve(), ACVMStatus::InProgress);
+    assert_eq!(acvm.next_opcode(), Some(&(Opcode::Arithmetic(gate_c), OpcodeLabel::Resolved(1))));
+    assert_eq!(acvm.unresolved_opcodes().len(), 1);
+    assert_eq!(acvm.witness_map()[&b], FieldElement::from(4_i128));
+
+    // Change the value of b before c is solved.
+    assert_eq!(
+        acvm.overwrite_witness(b, FieldElement::from(10_i128)),
+        Some(FieldElement::from(4_i128))
+    );
+    assert_eq!(acvm.step(), ACVMStatus::Solved);
+    assert_eq!(acvm.next_opcode(), None);
+    assert_eq!(acvm.finalize()[&c], FieldElement::from(20_i128));
+}
+
+#[test]
+fn steps_through_brillig_bytecode() {
+    let x = Witness(1);
+    let y = Witness(2);
+    let inverse = Witness(3);
+
+    // inverse = Oracle("invert", x + y), computing the sum within Brillig.
+    let brillig = Brillig {
+        inputs: vec![BrilligInputs::Single(x.into()), BrilligInputs::Single(y.into())],
+        outputs: vec![BrilligOutputs::Simple(inverse)],
+        foreign_call_results: vec![],
+        bytecode: vec![
+            brillig_vm::Opcode::BinaryFieldOp {
+                op: BinaryFieldOp::Add,
+                lhs: RegisterIndex::from(0),
+                rhs: RegisterIndex::from(1),
+                destination: RegisterIndex::from(2),
+            },
+            brillig_vm::Opcode::ForeignCall {
+                function: "invert".into(),
+                destinations: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+                inputs: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(2))],
+            },
+        ],
+        predicate: None,
+    };
+    // inverse * (x + y) = 1
+    let inverse_check = Expression {
+        mul_terms: vec![(FieldElement::one(), inverse, x), (FieldElement::one(), inverse, y)],
+        linear_combinations: vec![],
+        q_c: -FieldElement::one(),
+    };
+    let opcodes = vec![Opcode::Brillig(brillig), Opcode::Arithmetic(inverse_check)];
+
+    let values =
+        BTreeMap::from([(x, FieldElement::from(2_i128)), (y, FieldElement::from(3_i128))]).into();
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, values);
+
+    assert!(acvm.step_into_brillig());
+    assert_eq!(acvm.brillig_vm().unwrap().program_counter(), 0);
+    assert_eq!(acvm.step(), ACVMStatus::InProgress);
+    let vm = acvm.brillig_vm().unwrap();
+    assert_eq!(vm.program_counter(), 1);
+    assert_eq!(
+        vm.get_registers().get(RegisterIndex::from(2)).to_field(),
+        FieldElement::from(5_i128)
+    );
+
+    // Executing the foreign call pauses the Brillig opcode until the call is resolved.
+    assert_eq!(acvm.step(), ACVMStatus::InProgress);
+    assert!(acvm.brillig_vm().is_none());
+    let foreign_call =
+        acvm.get_pending_foreign_call().expect("should be waiting on a foreign call");
+    let result = Value::from(foreign_call.inputs[0][0].to_field().inverse());
+    acvm.resolve_pending_foreign_call(result.into());
+
+    // The resolved Brillig opcode is attempted next, and can be stepped through again.
+    assert!(acvm.step_into_brillig());
+    assert_eq!(acvm.brillig_vm().unwrap().program_counter(), 0);
+    assert_eq!(acvm.solve(), ACVMStatus::Solved);
+    assert_eq!(acvm.finalize()[&inverse], FieldElement::from(5_i128).inverse());
+}
+
+#[test]
+fn brillig_instruction_budget_stops_runaway_loops() {
+    // A Brillig function which never terminates.
+    let brillig = Brillig {
+        inputs: vec![],
+        outputs: vec![BrilligOutputs::Simple(Witness(1))],
+        foreign_call_results: vec![],
+        bytecode: vec![brillig_vm::Opcode::Jump { location: 0 }],
+        predicate: None,
+    };
+
+    let mut acvm = ACVM::new(StubbedBackend, vec![Opcode::Brillig(brillig)], WitnessMap::new());
+    acvm.set_brillig_instruction_budget(Some(1000));
+    assert_eq!(
+        acvm.solve(),
+        ACVMStatus::Failure(OpcodeResolutionError::BrilligFunctionFailed {
+            opcode_label: OpcodeLabel::Resolved(0),
+            message: "instruction budget of 1000 opcodes exhausted".to_owned(),
+            program_counter: 0,
+        })
+    );
+}
+
+#[test]
+fn foreign_calls_can_be_resolved_with_an_error() {
+    let brillig = Brillig {
+        inputs: vec![BrilligInputs::Single(Witness(1).into())],
+        outputs: vec![BrilligOutputs::Simple(Witness(2))],
+        foreign_call_results: vec![],
+        bytecode: vec![brillig_vm::Opcode::ForeignCall {
+            function: "lookup".into(),
+            destinations: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+            inputs: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+        }],
+        predicate: None,
+    };
+    let witness_assignments = BTreeMap::from([(Witness(1), FieldElement::from(7u128))]).into();
+
+    let mut acvm = ACVM::new(StubbedBackend, vec![Opcode::Brillig(brillig)], witness_assignments);
+    assert_eq!(acvm.solve(), ACVMStatus::RequiresForeignCall);
+    acvm.resolve_pending_foreign_call(ForeignCallResult::error("no entry for key 7"));
+
+    let error = OpcodeResolutionError::ForeignCallFailed {
+        opcode_label: OpcodeLabel::Resolved(0),
+        function: "lookup".to_owned(),
+        message: "no entry for key 7".to_owned(),
+        program_counter: 0,
+    };
+    assert_eq!(
+        error.opcode_location(),
+        Some(OpcodeLocation::Brillig { acir_index: 0, brillig_index: 0 })
+    );
+    assert_eq!(acvm.solve(), ACVMStatus::Failure(error));
+}
+
+/// Solves `w2 = double(w1)` followed by `w3 = double(w2)`, each using a foreign call, for `w1 = 3`.
+fn solve_doubling(executor: &mut impl ForeignCallExecutor) -> (ACVMStatus, WitnessMap) {
+    let double = |input: Witness, output: Witness| {
+        Opcode::Brillig(Brillig {
+            inputs: vec![BrilligInputs::Single(input.into())],
+            outputs: vec![BrilligOutputs::Simple(output)],
+            foreign_call_results: vec![],
+            bytecode: vec![brillig_vm::Opcode::ForeignCall {
+                function: "double".into(),
+                destinations: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+                inputs: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+            }],
+            predicate: None,
+        })
+    };
+    let opcodes = vec![double(Witness(1), Witness(2)), double(Witness(2), Witness(3))];
+    let witness_assignments = BTreeMap::from([(Witness(1), FieldElement::from(3u128))]).into();
+
+    let mut acvm = ACVM::new(StubbedBackend, opcodes, witness_assignments);
+    let status = acvm.solve_with_foreign_calls(executor);
+    (status, acvm.witness_map().clone())
+}
+
+fn double_executor() -> MapExecutor {
+    MapExecutor::new().with_function("double", |inputs| {
+        Value::from(inputs[0][0].to_field() * FieldElement::from(2u128)).into()
+    })
+}
+
+#[test]
+fn resolves_foreign_calls_with_an_executor() {
+    let (status, witness_map) = solve_doubling(&mut double_executor());
+    assert_eq!(status, ACVMStatus::Solved);
+    assert_eq!(witness_map[&Witness(3)], FieldElement::from(12u128));
+}
+
+#[test]
+#[cfg(feature = "test-utils")]
+fn resolves_foreign_calls_with_a_json_rpc_server() {
+    use acvm::pwg::{JsonRpcExecutor, JsonRpcMockServer};
+
+    // The same results are computed as when the foreign calls are resolved in process.
+    let server = JsonRpcMockServer::start(double_executor()).unwrap();
+    let mut executor = JsonRpcExecutor::new(&server.url()).unwrap();
+    assert_eq!(solve_doubling(&mut executor), solve_doubling(&mut double_executor()));
+}
+
+#[test]
+fn foreign_calls_which_the_executor_cannot_resolve_fail() {
+    let (status, _) = solve_doubling(&mut MapExecutor::new());
+    assert_eq!(
+        status,
+        ACVMStatus::Failure(OpcodeResolutionError::ForeignCallFailed {
+            opcode_label: OpcodeLabel::Resolved(0),
+            function: "double".to_owned(),
+            message: "no handler is registered for foreign call double".to_owned(),
+            program_counter: 0,
+        })
+    );
+}
+
+#[test]
+fn resolves_pending_foreign_calls_in_any_order() {
+    // Two identical Brillig opcodes which make the same foreign call.
+    let brillig = Opcode::Brillig(Brillig {
+        inputs: vec![BrilligInputs::Single(Witness(1).into())],
+        outputs: vec![BrilligOutputs::Simple(Witness(2))],
+        foreign_call_results: vec![],
+        bytecode: vec![brillig_vm::Opcode::ForeignCall {
+            function: "get".into(),
+            destinations: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+            inputs: vec![RegisterOrMemory::RegisterIndex(RegisterIndex::from(0))],
+        }],
+        predicate: None,
+    });
+    let new_acvm = || {
+        let witness_assignments = BTreeMap::from([(Witness(1), FieldElement::one())]).into();
+        let mut acvm =
+            ACVM::new(StubbedBackend, vec![brillig.clone(), brillig.clone()], witness_assignments);
+        assert_eq!(acvm.solve(), ACVMStatus::RequiresForeignCall);
+        let ids: Vec<_> = acvm.pending_foreign_calls().map(|(id, _)| id).collect();
+        (acvm, ids)
+    };
+
+    let (mut acvm, ids) = new_acvm();
+    assert_eq!(ids, vec![ForeignCallId(0), ForeignCallId(1)]);
+    let expected_call =
+        ForeignCallWaitInfo { function: "get".into(), inputs: vec![vec![Value::from(1u128)]] };
+    assert!(acvm.pending_foreign_calls().all(|(_, foreign_call)| *foreign_call == expected_call));
+    assert!(acvm.resolve_foreign_call(ids[1], Value::from(2u128).into()));
+    assert!(!acvm.resolve_foreign_call(ids[1], Value::from(2u128).into()));
+    assert_eq!(acvm.pending_foreign_calls().count(), 1);
+    assert!(acvm.resolve_foreign_call(ids[0], Value::from(2u128).into()));
+    assert_eq!(acvm.solve(), ACVMStatus::Solved);
+
+    // Failures are attributed to the opcode which made the call, even though the opcodes are identical.
+    let (mut acvm, ids) = new_acvm();
+    assert!(acvm.resolve_foreign_call(ids[1], Value::from(2u128).into()));
+    assert!(acvm.resolve_foreign_call(ids[0], ForeignCallResult::error("not found")));
+    assert_eq!(
+        acvm.solve(),
+        ACVMStatus::Failure(OpcodeResolutionError::ForeignCallFailed {
+            opcode_label: OpcodeLabel::Resolved(0),
+            function: "get".to_owned(),
+            message: "not found".to_owned(),
+            program_counter: 0,
+        })
+    );
+}
+
+#[test]
+fn replays_recorded_foreign_calls() {
+    let mut recorder = RecordingExecutor::new(double_executor());
+    let recorded = solve_doubling(&mut recorder);
+    assert_eq!(recorded.0, ACVMStatus::Solved);
+    let transcript = ForeignCallTranscript::from_json(&recorder.transcript().to_json()).unwrap();
+    assert_eq!(transcript.calls.len(), 2);
+
+    let mut replay = ReplayExecutor::new(transcript.clone());
+    assert_eq!(solve_doubling(&mut replay), recorded);
+    assert!(replay.remaining().is_empty());
+
+    // A transcript of calls to another function can't be used to solve the circuit.
+    let mut diverging = transcript;
+    diverging.calls[1].foreign_call.function = "triple".to_owned();
+    let (status, _) = solve_doubling(&mut ReplayExecutor::new(diverging));
+    assert_eq!(
+        status,
+        ACVMStatus::Failure(OpcodeResolutionError::ForeignCallFailed {
+            opcode_label: OpcodeLabel::Resolved(1),
+            function: "double".to_owned(),
+            message: "foreign call 1 diverged from the transcript: expected a call to triple but found a call to double".to_owned(),
+            program_counter: 0,
+        })
+    );
+}
@@ -0,0 +1,287 @@
+//! Assembler and disassembler for Brillig programs written in a text assembly format.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::{
+    parser::{parse_opcode, Symbols},
+    Label, Opcode, RegisterIndex,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}: {message}")]
+pub struct AssemblyError {
+    /// The line of the source at which the error occurred, starting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+/// A line of source with its comment removed, split into the labels it defines and what follows them.
+struct SourceLine<'a> {
+    number: usize,
+    labels: Vec<&'a str>,
+    rest: &'a str,
+}
+
+/// Assembles a program written in a text assembly format.
+///
+/// Each line holds a single instruction in the format accepted by the `FromStr` implementation of [`Opcode`],
+/// optionally preceded by labels. Jumps and calls may target a label rather than an absolute location,
+/// and registers may be given names using the `.alias` directive. Text following `//` is ignored.
+///
+/// ```text
+/// .alias counter r0
+/// .alias one r1
+///     const one, 1
+/// loop:
+///     jmp_if_not counter, end
+///     sub.32 counter, counter, one
+///     foreign_call log counter ->
+///     jmp loop
+/// end:
+///     stop
+/// ```
+///
+/// Aliases apply to the lines following their definition, so a register can be renamed part way through
+/// a program, whereas a label can be used before the line which defines it.
+pub fn assemble(source: &str) -> Result<Vec<Opcode>, AssemblyError> {
+    let lines: Vec<SourceLine> = source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| split_labels(index + 1, strip_comment(line)))
+        .collect::<Result<_, _>>()?;
+
+    // Labels refer to the instruction which follows them, so they must all be known before any jumps are parsed.
+    let mut symbols = Symbols::default();
+    let mut location = 0;
+    for line in &lines {
+        for label in &line.labels {
+            if symbols.labels.insert(label.to_string(), location).is_some() {
+                return Err(AssemblyError {
+                    line: line.number,
+                    message: format!("label `{label}` is defined more than once"),
+                });
+            }
+        }
+        if !line.rest.is_empty() && !line.rest.starts_with('.') {
+            location += 1;
+        }
+    }
+
+    let mut bytecode = Vec::new();
+    for line in &lines {
+        let error = |message| AssemblyError { line: line.number, message };
+        if line.rest.is_empty() {
+            continue;
+        }
+        match line.rest.strip_prefix('.') {
+            Some(directive) => {
+                let (name, register) = parse_alias(directive).map_err(error)?;
+                symbols.aliases.insert(name.to_owned(), register);
+            }
+            None => bytecode.push(parse_opcode(line.rest, &symbols).map_err(error)?),
+        }
+    }
+    Ok(bytecode)
+}
+
+/// Writes `bytecode` in the format accepted by [`assemble`], replacing the locations targeted by jumps and
+/// calls with labels.
+pub fn disassemble(bytecode: &[Opcode]) -> String {
+    let targets: BTreeSet<Label> = bytecode
+        .iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::Jump { location }
+            | Opcode::JumpIf { location, .. }
+            | Opcode::JumpIfNot { location, .. }
+            | Opcode::Call { location } => Some(*location),
+            _ => None,
+        })
+        // Locations past the end of the program can't be labelled, so are left as they are.
+        .filter(|location| *location <= bytecode.len())
+        .collect();
+    let label = |location: Label| format!("L{location}");
+
+    let mut source = String::new();
+    for location in 0..=bytecode.len() {
+        if targets.contains(&location) {
+            writeln!(source, "{}:", label(location)).expect("writing to a string cannot fail");
+        }
+        let opcode = match bytecode.get(location) {
+            Some(opcode) => opcode,
+            None => break,
+        };
+        let instruction = match opcode {
+            Opcode::Jump { location } if targets.contains(location) => {
+                format!("jmp {}", label(*location))
+            }
+            Opcode::Call { location } if targets.contains(location) => {
+                format!("call {}", label(*location))
+            }
+            Opcode::JumpIf { condition, location } if targets.contains(location) => {
+                format!("jmp_if {condition}, {}", label(*location))
+            }
+            Opcode::JumpIfNot { condition, location } if targets.contains(location) => {
+                format!("jmp_if_not {condition}, {}", label(*location))
+            }
+            opcode => opcode.to_string(),
+        };
+        writeln!(source, "    {instruction}").expect("writing to a string cannot fail");
+    }
+    source
+}
+
+/// Removes any `//` comment from `line`, ignoring slashes within strings.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                chars.next();
+            }
+            '/' if !in_string && matches!(chars.peek(), Some((_, '/'))) => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn split_labels(number: usize, line: &str) -> Result<SourceLine<'_>, AssemblyError> {
+    let mut labels = Vec::new();
+    let mut rest = line.trim();
+    while let Some((label, after)) = rest.split_once(':') {
+        // Anything which isn't a name, such as a foreign call whose name contains a colon, is not a label.
+        if !is_identifier(label) {
+            break;
+        }
+        if is_register(label) {
+            return Err(AssemblyError {
+                line: number,
+                message: format!("label `{label}` is indistinguishable from a register"),
+            });
+        }
+        labels.push(label);
+        rest = after.trim();
+    }
+    Ok(SourceLine { number, labels, rest })
+}
+
+/// Parses the `alias <name> <register>` directive.
+fn parse_alias(directive: &str) -> Result<(&str, RegisterIndex), String> {
+    let words: Vec<&str> = directive.split_whitespace().collect();
+    let (name, register) = match words.as_slice() {
+        ["alias", name, register] => (*name, *register),
+        ["alias", ..] => return Err("expected `.alias <name> <register>`".to_owned()),
+        _ => return Err(format!("unknown directive `.{directive}`")),
+    };
+    if !is_identifier(name) || is_register(name) {
+        return Err(format!("`{name}` can't be used as the name of a register"));
+    }
+    let register = register
+        .strip_prefix('r')
+        .and_then(|index| index.parse::<usize>().ok())
+        .ok_or_else(|| format!("expected a register but found `{register}`"))?;
+    Ok((name, RegisterIndex::from(register)))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_register(name: &str) -> bool {
+    name.strip_prefix('r').map_or(false, |index| index.parse::<usize>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, disassemble, AssemblyError};
+    use crate::{BinaryIntOp, Opcode, RegisterIndex, RegisterOrMemory};
+
+    fn r(index: usize) -> RegisterIndex {
+        RegisterIndex::from(index)
+    }
+
+    #[test]
+    fn assembles_labels_and_aliases() {
+        let source = r#"
+            // Counts down from r0, logging each value.
+            .alias counter r0
+            .alias one r1
+                const one, 1
+            loop:
+                jmp_if_not counter, end // exits once the counter reaches zero
+                sub.32 counter, counter, one
+                foreign_call "log//" counter ->
+                jmp loop
+            end: stop
+        "#;
+        assert_eq!(
+            assemble(source),
+            Ok(vec![
+                Opcode::Const { destination: r(1), value: 1u128.into() },
+                Opcode::JumpIfNot { condition: r(0), location: 5 },
+                Opcode::BinaryIntOp {
+                    destination: r(0),
+                    op: BinaryIntOp::Sub,
+                    bit_size: 32,
+                    lhs: r(0),
+                    rhs: r(1)
+                },
+                Opcode::ForeignCall {
+                    function: "log//".to_owned(),
+                    destinations: vec![],
+                    inputs: vec![RegisterOrMemory::RegisterIndex(r(0))],
+                },
+                Opcode::Jump { location: 1 },
+                Opcode::Stop,
+            ])
+        );
+    }
+
+    #[test]
+    fn disassembly_roundtrip() {
+        let bytecode = vec![
+            Opcode::Call { location: 3 },
+            Opcode::JumpIf { condition: r(0), location: 4 },
+            Opcode::Jump { location: 10 },
+            Opcode::Return,
+            Opcode::Stop,
+        ];
+        let source = disassemble(&bytecode);
+        assert_eq!(
+            source,
+            "    call L3\n    jmp_if r0, L4\n    jmp 10\nL3:\n    return\nL4:\n    stop\n"
+        );
+        assert_eq!(assemble(&source), Ok(bytecode));
+
+        // Jumps to the end of the program are labelled too.
+        let bytecode = vec![Opcode::JumpIfNot { condition: r(0), location: 1 }];
+        assert_eq!(assemble(&disassemble(&bytecode)), Ok(bytecode));
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let error = |line, message: &str| Err(AssemblyError { line, message: message.to_owned() });
+        assert_eq!(assemble("a:\n  stop\na:"), error(3, "label `a` is defined more than once"));
+        assert_eq!(
+            assemble("  stop\n  jmp nowhere"),
+            error(2, "expected a label or location but found `nowhere`")
+        );
+        assert_eq!(
+            assemble(".alias r1 r0"),
+            error(1, "`r1` can't be used as the name of a register")
+        );
+        assert_eq!(assemble(".org 5"), error(1, "unknown directive `.org 5`"));
+        assert_eq!(
+            assemble("r2: stop"),
+            error(1, "label `r2` is indistinguishable from a register")
+        );
+        assert!(assemble("  mov counter, r0").is_err());
+    }
+}
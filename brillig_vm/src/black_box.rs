@@ -1,4 +1,6 @@
-use crate::{memory::Memory, opcodes::HeapVector, HeapArray, RegisterIndex, Registers, Value};
+use crate::{
+    memory::Memory, opcodes::HeapVector, FailureKind, HeapArray, RegisterIndex, Registers, Value,
+};
 use acir_field::FieldElement;
 use blake2::digest::generic_array::GenericArray;
 use blake2::{Blake2s256, Digest};
@@ -41,19 +43,35 @@ pub enum BlackBoxOp {
 }
 
 impl BlackBoxOp {
-    pub(crate) fn evaluate(&self, registers: &mut Registers, memory: &mut Memory) {
+    /// Name of the operation in the textual format.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlackBoxOp::Sha256 { .. } => "sha256",
+            BlackBoxOp::Blake2s { .. } => "blake2s",
+            BlackBoxOp::Keccak256 { .. } => "keccak256",
+            BlackBoxOp::HashToField128Security { .. } => "hash_to_field_128_security",
+            BlackBoxOp::EcdsaSecp256k1 { .. } => "ecdsa_secp256k1",
+            BlackBoxOp::EcdsaSecp256r1 { .. } => "ecdsa_secp256r1",
+        }
+    }
+
+    pub(crate) fn evaluate(
+        &self,
+        registers: &mut Registers,
+        memory: &mut Memory,
+    ) -> Result<(), FailureKind> {
         match self {
             BlackBoxOp::Sha256 { message, output } => {
-                generic_hash_256::<Sha256>(message, output, registers, memory);
+                generic_hash_256::<Sha256>(self.name(), message, output, registers, memory)
             }
             BlackBoxOp::Blake2s { message, output } => {
-                generic_hash_256::<Blake2s256>(message, output, registers, memory);
+                generic_hash_256::<Blake2s256>(self.name(), message, output, registers, memory)
             }
             BlackBoxOp::Keccak256 { message, output } => {
-                generic_hash_256::<Keccak256>(message, output, registers, memory);
+                generic_hash_256::<Keccak256>(self.name(), message, output, registers, memory)
             }
             BlackBoxOp::HashToField128Security { message, output } => {
-                generic_hash_to_field::<Blake2s256>(message, output, registers, memory);
+                generic_hash_to_field::<Blake2s256>(message, output, registers, memory)
             }
             BlackBoxOp::EcdsaSecp256k1 {
                 hashed_msg,
@@ -69,33 +87,27 @@ impl BlackBoxOp {
                 signature,
                 result: result_register,
             } => {
-                let message_values = memory.read_slice(
-                    registers.get(hashed_msg.pointer).to_usize(),
-                    registers.get(hashed_msg.size).to_usize(),
-                );
-                let message_bytes = to_u8_vec(message_values);
+                let failure = |reason: &str| FailureKind::BlackBoxFailed {
+                    function: self.name(),
+                    reason: reason.to_owned(),
+                };
+
+                let message_bytes = to_u8_vec(read_heap_vector(hashed_msg, registers, memory)?);
 
                 let public_key_x_bytes: [u8; 32] =
-                    to_u8_vec(memory.read_slice(
-                        registers.get(public_key_x.pointer).to_usize(),
-                        public_key_x.size,
-                    ))
-                    .try_into()
-                    .expect("Expected a 32-element public key x array");
+                    to_u8_vec(read_heap_array(public_key_x, registers, memory)?)
+                        .try_into()
+                        .map_err(|_| failure("expected a 32-element public key x array"))?;
 
                 let public_key_y_bytes: [u8; 32] =
-                    to_u8_vec(memory.read_slice(
-                        registers.get(public_key_y.pointer).to_usize(),
-                        public_key_y.size,
-                    ))
-                    .try_into()
-                    .expect("Expected a 32-element public key y array");
-
-                let signature_bytes: [u8; 64] = to_u8_vec(
-                    memory.read_slice(registers.get(signature.pointer).to_usize(), signature.size),
-                )
-                .try_into()
-                .expect("Expected a 64-element signature array");
+                    to_u8_vec(read_heap_array(public_key_y, registers, memory)?)
+                        .try_into()
+                        .map_err(|_| failure("expected a 32-element public key y array"))?;
+
+                let signature_bytes: [u8; 64] =
+                    to_u8_vec(read_heap_array(signature, registers, memory)?)
+                        .try_into()
+                        .map_err(|_| failure("expected a 64-element signature array"))?;
 
                 let result = match self {
                     BlackBoxOp::EcdsaSecp256k1 { .. } => verify_secp256k1_ecdsa_signature(
@@ -111,59 +123,68 @@ impl BlackBoxOp {
                         &signature_bytes,
                     ),
                     _ => unreachable!(),
-                };
+                }
+                .map_err(failure)?;
 
-                registers.set(*result_register, (result as u128).into())
+                registers.try_set(*result_register, (result as u128).into())
             }
         }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use crate::{
-        black_box::to_u8_vec, BlackBoxOp, HeapArray, HeapVector, Memory, Registers, Value,
-    };
-
-    fn to_value_vec(input: &[u8]) -> Vec<Value> {
-        input.iter().map(|x| Value::from(*x as usize)).collect()
+/// Writes the black box call in the textual format used for Brillig instructions.
+impl std::fmt::Display for BlackBoxOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.name();
+        match self {
+            BlackBoxOp::Sha256 { message, output }
+            | BlackBoxOp::Blake2s { message, output }
+            | BlackBoxOp::Keccak256 { message, output } => {
+                write!(f, "{name} {message} -> {output}")
+            }
+            BlackBoxOp::HashToField128Security { message, output } => {
+                write!(f, "{name} {message} -> {output}")
+            }
+            BlackBoxOp::EcdsaSecp256k1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            }
+            | BlackBoxOp::EcdsaSecp256r1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            } => write!(
+                f,
+                "{name} {hashed_msg}, {public_key_x}, {public_key_y}, {signature} -> {result}"
+            ),
+        }
     }
+}
 
-    #[test]
-    fn sha256() {
-        let message: Vec<u8> = b"hello world".to_vec();
-        let message_length = message.len();
-
-        let mut memory = Memory::from(vec![]);
-        let message_pointer = 0;
-        let result_pointer = message_pointer + message_length;
-        memory.write_slice(message_pointer, to_value_vec(&message).as_slice());
-
-        let mut registers = Registers {
-            inner: vec![
-                Value::from(message_pointer),
-                Value::from(message_length),
-                Value::from(result_pointer),
-            ],
-        };
-
-        let op = BlackBoxOp::Sha256 {
-            message: HeapVector { pointer: 0.into(), size: 1.into() },
-            output: HeapArray { pointer: 2.into(), size: 32 },
-        };
-
-        op.evaluate(&mut registers, &mut memory);
-
-        let result = memory.read_slice(result_pointer, 32);
+/// Reads the values of a [`HeapVector`] from memory.
+fn read_heap_vector<'a>(
+    vector: &HeapVector,
+    registers: &Registers,
+    memory: &'a Memory,
+) -> Result<&'a [Value], FailureKind> {
+    let pointer = registers.try_get(vector.pointer)?.try_to_usize()?;
+    let size = registers.try_get(vector.size)?.try_to_usize()?;
+    memory.try_read_slice(pointer, size)
+}
 
-        assert_eq!(
-            to_u8_vec(result),
-            vec![
-                185, 77, 39, 185, 147, 77, 62, 8, 165, 46, 82, 215, 218, 125, 171, 250, 196, 132,
-                239, 227, 122, 83, 128, 238, 144, 136, 247, 172, 226, 239, 205, 233
-            ]
-        );
-    }
+/// Reads the values of a [`HeapArray`] from memory.
+fn read_heap_array<'a>(
+    array: &HeapArray,
+    registers: &Registers,
+    memory: &'a Memory,
+) -> Result<&'a [Value], FailureKind> {
+    let pointer = registers.try_get(array.pointer)?.try_to_usize()?;
+    memory.try_read_slice(pointer, array.size)
 }
 
 /// Extracts the last byte of every value
@@ -179,24 +200,26 @@ fn to_u8_vec(inputs: &[Value]) -> Vec<u8> {
 
 /// Does a generic hash of the inputs storing the resulting 32 bytes as items in the output array.
 fn generic_hash_256<D: Digest>(
+    function: &'static str,
     message: &HeapVector,
     output: &HeapArray,
     registers: &Registers,
     memory: &mut Memory,
-) {
-    let message_values = memory.read_slice(
-        registers.get(message.pointer).to_usize(),
-        registers.get(message.size).to_usize(),
-    );
-    let message_bytes = to_u8_vec(message_values);
-
-    assert!(output.size == 32, "Expected a 32-element result array");
+) -> Result<(), FailureKind> {
+    let message_bytes = to_u8_vec(read_heap_vector(message, registers, memory)?);
+
+    if output.size != 32 {
+        return Err(FailureKind::BlackBoxFailed {
+            function,
+            reason: format!("expected a 32-element result array, found {}", output.size),
+        });
+    }
 
     let output_bytes: [u8; 32] =
         D::digest(message_bytes).as_slice().try_into().expect("digest should be 256 bits");
     let output_values: Vec<Value> = output_bytes.iter().map(|b| (*b as u128).into()).collect();
 
-    memory.write_slice(registers.get(output.pointer).to_usize(), &output_values);
+    memory.try_write_slice(registers.try_get(output.pointer)?.try_to_usize()?, &output_values)
 }
 
 /// Does a generic hash of the entire inputs storing the resulting hash into a single output register.
@@ -205,11 +228,8 @@ fn generic_hash_to_field<D: Digest>(
     output: &RegisterIndex,
     registers: &mut Registers,
     memory: &Memory,
-) {
-    let message_values = memory.read_slice(
-        registers.get(message.pointer).to_usize(),
-        registers.get(message.size).to_usize(),
-    );
+) -> Result<(), FailureKind> {
+    let message_values = read_heap_vector(message, registers, memory)?;
     let mut message_bytes = Vec::new();
 
     for value in message_values {
@@ -222,7 +242,7 @@ fn generic_hash_to_field<D: Digest>(
 
     let reduced_res = FieldElement::from_be_bytes_reduce(&output_bytes);
 
-    registers.set(*output, reduced_res.into());
+    registers.try_set(*output, reduced_res.into())
 }
 
 // TODO(https://github.com/noir-lang/acvm/issues/402): remove from here and use the one from acvm
@@ -231,7 +251,7 @@ fn verify_secp256k1_ecdsa_signature(
     public_key_x_bytes: &[u8; 32],
     public_key_y_bytes: &[u8; 32],
     signature: &[u8; 64],
-) -> bool {
+) -> Result<bool, &'static str> {
     use k256::elliptic_curve::sec1::FromEncodedPoint;
     use k256::elliptic_curve::PrimeField;
 
@@ -245,16 +265,22 @@ fn verify_secp256k1_ecdsa_signature(
     };
     // Convert the inputs into k256 data structures
 
-    let signature = Signature::try_from(signature.as_slice()).unwrap();
+    let signature =
+        Signature::try_from(signature.as_slice()).map_err(|_| "signature is not valid")?;
 
     let point = EncodedPoint::from_affine_coordinates(
         public_key_x_bytes.into(),
         public_key_y_bytes.into(),
         true,
     );
-    let pubkey = PublicKey::from_encoded_point(&point).unwrap();
+    let pubkey = Option::<PublicKey>::from(PublicKey::from_encoded_point(&point))
+        .ok_or("public key is not a point on the curve")?;
 
-    let z = Scalar::from_repr(*GenericArray::from_slice(hashed_msg)).unwrap();
+    if hashed_msg.len() != 32 {
+        return Err("expected a 32-byte hashed message");
+    }
+    let z = Option::<Scalar>::from(Scalar::from_repr(*GenericArray::from_slice(hashed_msg)))
+        .ok_or("hashed message is not a valid scalar")?;
 
     // Finished converting bytes into data structures
 
@@ -263,10 +289,10 @@ fn verify_secp256k1_ecdsa_signature(
 
     // Ensure signature is "low S" normalized ala BIP 0062
     if s.is_high().into() {
-        return false;
+        return Ok(false);
     }
 
-    let s_inv = s.invert().unwrap();
+    let s_inv = Option::<Scalar>::from(s.invert()).ok_or("signature is not valid")?;
     let u1 = z * s_inv;
     let u2 = *r * s_inv;
 
@@ -276,8 +302,11 @@ fn verify_secp256k1_ecdsa_signature(
         .to_affine();
 
     match R.to_encoded_point(false).coordinates() {
-        Coordinates::Uncompressed { x, y: _ } => Scalar::from_repr(*x).unwrap().eq(&r),
-        _ => unreachable!("Point is uncompressed"),
+        Coordinates::Uncompressed { x, y: _ } => {
+            Ok(Option::<Scalar>::from(Scalar::from_repr(*x)).map_or(false, |x| x.eq(&r)))
+        }
+        // The point at infinity doesn't have an x coordinate to compare against `r`.
+        _ => Ok(false),
     }
 }
 
@@ -287,7 +316,7 @@ fn verify_secp256r1_ecdsa_signature(
     public_key_x_bytes: &[u8; 32],
     public_key_y_bytes: &[u8; 32],
     signature: &[u8; 64],
-) -> bool {
+) -> Result<bool, &'static str> {
     use p256::elliptic_curve::sec1::FromEncodedPoint;
     use p256::elliptic_curve::PrimeField;
 
@@ -302,16 +331,22 @@ fn verify_secp256r1_ecdsa_signature(
 
     // Convert the inputs into k256 data structures
 
-    let signature = Signature::try_from(signature.as_slice()).unwrap();
+    let signature =
+        Signature::try_from(signature.as_slice()).map_err(|_| "signature is not valid")?;
 
     let point = EncodedPoint::from_affine_coordinates(
         public_key_x_bytes.into(),
         public_key_y_bytes.into(),
         true,
     );
-    let pubkey = PublicKey::from_encoded_point(&point).unwrap();
+    let pubkey = Option::<PublicKey>::from(PublicKey::from_encoded_point(&point))
+        .ok_or("public key is not a point on the curve")?;
 
-    let z = Scalar::from_repr(*GenericArray::from_slice(hashed_msg)).unwrap();
+    if hashed_msg.len() != 32 {
+        return Err("expected a 32-byte hashed message");
+    }
+    let z = Option::<Scalar>::from(Scalar::from_repr(*GenericArray::from_slice(hashed_msg)))
+        .ok_or("hashed message is not a valid scalar")?;
 
     // Finished converting bytes into data structures
 
@@ -320,10 +355,10 @@ fn verify_secp256r1_ecdsa_signature(
 
     // Ensure signature is "low S" normalized ala BIP 0062
     if s.is_high().into() {
-        return false;
+        return Ok(false);
     }
 
-    let s_inv = s.invert().unwrap();
+    let s_inv = Option::<Scalar>::from(s.invert()).ok_or("signature is not valid")?;
     let u1 = z * s_inv;
     let u2 = *r * s_inv;
 
@@ -333,7 +368,57 @@ fn verify_secp256r1_ecdsa_signature(
         .to_affine();
 
     match R.to_encoded_point(false).coordinates() {
-        Coordinates::Uncompressed { x, y: _ } => Scalar::from_repr(*x).unwrap().eq(&r),
-        _ => unreachable!("Point is uncompressed"),
+        Coordinates::Uncompressed { x, y: _ } => {
+            Ok(Option::<Scalar>::from(Scalar::from_repr(*x)).map_or(false, |x| x.eq(&r)))
+        }
+        // The point at infinity doesn't have an x coordinate to compare against `r`.
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        black_box::to_u8_vec, BlackBoxOp, HeapArray, HeapVector, Memory, Registers, Value,
+    };
+
+    fn to_value_vec(input: &[u8]) -> Vec<Value> {
+        input.iter().map(|x| Value::from(*x as usize)).collect()
+    }
+
+    #[test]
+    fn sha256() {
+        let message: Vec<u8> = b"hello world".to_vec();
+        let message_length = message.len();
+
+        let mut memory = Memory::from(vec![]);
+        let message_pointer = 0;
+        let result_pointer = message_pointer + message_length;
+        memory.write_slice(message_pointer, to_value_vec(&message).as_slice());
+
+        let mut registers = Registers {
+            inner: vec![
+                Value::from(message_pointer),
+                Value::from(message_length),
+                Value::from(result_pointer),
+            ],
+        };
+
+        let op = BlackBoxOp::Sha256 {
+            message: HeapVector { pointer: 0.into(), size: 1.into() },
+            output: HeapArray { pointer: 2.into(), size: 32 },
+        };
+
+        op.evaluate(&mut registers, &mut memory).unwrap();
+
+        let result = memory.read_slice(result_pointer, 32);
+
+        assert_eq!(
+            to_u8_vec(result),
+            vec![
+                185, 77, 39, 185, 147, 77, 62, 8, 165, 46, 82, 215, 218, 125, 171, 250, 196, 132,
+                239, 227, 122, 83, 128, 238, 144, 136, 247, 172, 226, 239, 205, 233
+            ]
+        );
     }
 }
@@ -0,0 +1,31 @@
+use acir_field::FieldElement;
+use thiserror::Error;
+
+use crate::RegisterIndex;
+
+/// The reason for which the [VM][crate::VM] failed to execute its bytecode.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FailureKind {
+    #[error("explicit trap hit in brillig")]
+    Trap,
+    #[error("return opcode hit, but callstack already empty")]
+    EmptyCallStack,
+    #[error("register {0} is past the maximum register index")]
+    RegisterOutOfBounds(RegisterIndex),
+    #[error("access of {size} values at memory address {pointer} is out of bounds")]
+    MemoryOutOfBounds { pointer: usize, size: usize },
+    #[error("value {0} is too large to be used as a memory address or size")]
+    ValueTooLarge(FieldElement),
+    #[error("attempted to divide by zero")]
+    DivisionByZero,
+    #[error("integer operations do not support a bit size of {0}")]
+    InvalidBitSize(u32),
+    #[error("invalid foreign call result: {0}")]
+    InvalidForeignCallResult(String),
+    #[error("foreign call {function} failed: {message}")]
+    ForeignCallFailed { function: String, message: String },
+    #[error("failed to evaluate black box function {function}: {reason}")]
+    BlackBoxFailed { function: &'static str, reason: String },
+    #[error("instruction budget of {0} opcodes exhausted")]
+    InstructionBudgetExhausted(usize),
+}
@@ -15,22 +15,35 @@ pub enum ForeignCallOutput {
 pub struct ForeignCallResult {
     /// Resolved output values of the foreign call.
     pub values: Vec<ForeignCallOutput>,
+    /// The reason for which the caller failed to resolve the foreign call, if it did.
+    ///
+    /// The [VM][crate::VM] fails with [`FailureKind::ForeignCallFailed`][crate::FailureKind::ForeignCallFailed]
+    /// on reaching a foreign call which was resolved with an error, ignoring `values`.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ForeignCallResult {
+    /// Creates the result of a foreign call which the caller failed to resolve.
+    pub fn error(message: impl Into<String>) -> Self {
+        ForeignCallResult { values: Vec::new(), error: Some(message.into()) }
+    }
 }
 
 impl From<Value> for ForeignCallResult {
     fn from(value: Value) -> Self {
-        ForeignCallResult { values: vec![ForeignCallOutput::Single(value)] }
+        ForeignCallResult { values: vec![ForeignCallOutput::Single(value)], error: None }
     }
 }
 
 impl From<Vec<Value>> for ForeignCallResult {
     fn from(values: Vec<Value>) -> Self {
-        ForeignCallResult { values: vec![ForeignCallOutput::Array(values)] }
+        ForeignCallResult { values: vec![ForeignCallOutput::Array(values)], error: None }
     }
 }
 
 impl From<Vec<ForeignCallOutput>> for ForeignCallResult {
     fn from(values: Vec<ForeignCallOutput>) -> Self {
-        ForeignCallResult { values }
+        ForeignCallResult { values, error: None }
     }
 }
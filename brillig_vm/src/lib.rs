@@ -9,19 +9,33 @@
 //! [acir]: https://crates.io/crates/acir
 //! [acvm]: https://crates.io/crates/acvm
 
+mod assembler;
 mod black_box;
+mod failure;
 mod foreign_call;
 mod memory;
 mod opcodes;
+mod optimizer;
+mod parser;
 mod registers;
+mod trace;
+mod validation;
 mod value;
 
+pub use assembler::{assemble, disassemble, AssemblyError};
 pub use black_box::BlackBoxOp;
+pub use failure::FailureKind;
 pub use foreign_call::{ForeignCallOutput, ForeignCallResult};
 pub use memory::Memory;
 pub use opcodes::{BinaryFieldOp, BinaryIntOp, HeapArray, HeapVector, RegisterOrMemory};
 pub use opcodes::{Label, Opcode};
+pub use optimizer::optimize;
+pub use parser::ParseOpcodeError;
+#[doc(hidden)]
+pub use parser::{tokenize, Token};
 pub use registers::{RegisterIndex, Registers};
+pub use trace::TraceStep;
+pub use validation::{validate, ValidationError};
 pub use value::Typ;
 pub use value::Value;
 
@@ -29,8 +43,11 @@ pub use value::Value;
 pub enum VMStatus {
     Finished,
     InProgress,
+    /// The VM has failed to execute its bytecode and can not progress.
     Failure {
-        message: String,
+        kind: FailureKind,
+        /// The program counter of the opcode which failed.
+        program_counter: usize,
     },
     /// The VM process is not solvable as a [foreign call][Opcode::ForeignCall] has been
     /// reached where the outputs are yet to be resolved.  
@@ -68,6 +85,16 @@ pub struct VM {
     memory: Memory,
     /// Call stack
     call_stack: Vec<Value>,
+    /// The number of opcodes executed so far
+    instructions_executed: usize,
+    /// The maximum number of opcodes which may be executed, if limited
+    instruction_budget: Option<usize>,
+    /// Program counters at which [`VM::process_opcodes`] pauses before executing the opcode
+    breakpoints: Vec<usize>,
+    /// Whether execution has paused at the breakpoint at the current program counter
+    breakpoint_hit: bool,
+    /// The opcodes executed so far, if tracing has been enabled
+    trace: Option<Vec<TraceStep>>,
 }
 
 impl VM {
@@ -87,9 +114,54 @@ impl VM {
             status: VMStatus::InProgress,
             memory: memory.into(),
             call_stack: Vec::new(),
+            instructions_executed: 0,
+            instruction_budget: None,
+            breakpoints: Vec::new(),
+            breakpoint_hit: false,
+            trace: None,
         }
     }
 
+    /// Limits the total number of opcodes which the VM may execute.
+    ///
+    /// Once `budget` opcodes have been executed, attempting to execute another fails the VM. This prevents
+    /// bytecode which doesn't terminate from running forever.
+    pub fn set_instruction_budget(&mut self, budget: Option<usize>) {
+        self.instruction_budget = budget;
+    }
+
+    /// Returns the number of opcodes which have been executed.
+    pub fn instructions_executed(&self) -> usize {
+        self.instructions_executed
+    }
+
+    /// Pauses [`VM::process_opcodes`] before executing the opcode at `program_counter`.
+    ///
+    /// Execution is paused with the status [`VMStatus::InProgress`] and continues from the breakpoint
+    /// on the next call to [`VM::process_opcodes`] or [`VM::process_opcode`].
+    pub fn add_breakpoint(&mut self, program_counter: usize) {
+        if !self.breakpoints.contains(&program_counter) {
+            self.breakpoints.push(program_counter);
+        }
+    }
+
+    /// Removes the breakpoint at `program_counter`, returning whether there was one.
+    pub fn remove_breakpoint(&mut self, program_counter: usize) -> bool {
+        let num_breakpoints = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| *breakpoint != program_counter);
+        self.breakpoints.len() != num_breakpoints
+    }
+
+    /// Records each opcode executed from now on, along with the registers and memory which it writes.
+    pub fn enable_tracing(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the opcodes executed since tracing was enabled, if it has been.
+    pub fn trace(&self) -> Option<&[TraceStep]> {
+        self.trace.as_deref()
+    }
+
     /// Updates the current status of the VM.
     /// Returns the given status.
     fn status(&mut self, status: VMStatus) -> VMStatus {
@@ -111,18 +183,26 @@ impl VM {
     /// Sets the current status of the VM to `fail`.
     /// Indicating that the VM encountered a `Trap` Opcode
     /// or an invalid state.
-    fn fail(&mut self, message: String) -> VMStatus {
-        self.status(VMStatus::Failure { message });
-        self.status.clone()
+    fn fail(&mut self, kind: FailureKind) -> VMStatus {
+        self.status(VMStatus::Failure { kind, program_counter: self.program_counter })
     }
 
     /// Loop over the bytecode and update the program counter
+    ///
+    /// Returns [`VMStatus::InProgress`] if execution pauses at a [breakpoint][VM::add_breakpoint].
     pub fn process_opcodes(&mut self) -> VMStatus {
-        while !matches!(
-            self.process_opcode(),
-            VMStatus::Finished | VMStatus::Failure { .. } | VMStatus::ForeignCallWait { .. }
-        ) {}
-        self.status.clone()
+        loop {
+            if !self.breakpoint_hit && self.breakpoints.contains(&self.program_counter) {
+                self.breakpoint_hit = true;
+                return self.status.clone();
+            }
+            if matches!(
+                self.process_opcode(),
+                VMStatus::Finished | VMStatus::Failure { .. } | VMStatus::ForeignCallWait { .. }
+            ) {
+                return self.status.clone();
+            }
+        }
     }
 
     /// Returns all of the registers in the VM.
@@ -135,41 +215,79 @@ impl VM {
     }
 
     /// Process a single opcode and modify the program counter.
+    ///
+    /// Any failure to execute the opcode is reported as a [`VMStatus::Failure`].
     pub fn process_opcode(&mut self) -> VMStatus {
+        if self.program_counter >= self.bytecode.len() {
+            return self.finish();
+        }
+        if let Some(budget) = self.instruction_budget {
+            if self.instructions_executed >= budget {
+                return self.fail(FailureKind::InstructionBudgetExhausted(budget));
+            }
+        }
+        self.instructions_executed += 1;
+        self.breakpoint_hit = false;
+
+        if self.trace.is_none() {
+            return self.execute_opcode().unwrap_or_else(|kind| self.fail(kind));
+        }
+        let program_counter = self.program_counter;
+        let registers = self.registers.clone();
+        self.memory.start_journal();
+        let status = self.execute_opcode().unwrap_or_else(|kind| self.fail(kind));
+
+        let num_registers = std::cmp::max(registers.inner.len(), self.registers.inner.len());
+        let register_writes = (0..num_registers)
+            .map(RegisterIndex::from)
+            .filter_map(|index| {
+                let value = self.registers.get(index);
+                (value != registers.get(index)).then_some((index, value))
+            })
+            .collect();
+        let step = TraceStep {
+            program_counter,
+            opcode: self.bytecode[program_counter].clone(),
+            register_writes,
+            memory_writes: self.memory.take_journal(),
+        };
+        self.trace.as_mut().expect("tracing is enabled").push(step);
+        status
+    }
+
+    /// Executes the opcode at the program counter.
+    fn execute_opcode(&mut self) -> Result<VMStatus, FailureKind> {
         let opcode = &self.bytecode[self.program_counter];
         match opcode {
             Opcode::BinaryFieldOp { op, lhs, rhs, destination: result } => {
-                self.process_binary_field_op(*op, *lhs, *rhs, *result);
-                self.increment_program_counter()
+                self.process_binary_field_op(*op, *lhs, *rhs, *result)?;
+                Ok(self.increment_program_counter())
             }
             Opcode::BinaryIntOp { op, bit_size, lhs, rhs, destination: result } => {
-                self.process_binary_int_op(*op, *bit_size, *lhs, *rhs, *result);
-                self.increment_program_counter()
+                self.process_binary_int_op(*op, *bit_size, *lhs, *rhs, *result)?;
+                Ok(self.increment_program_counter())
             }
-            Opcode::Jump { location: destination } => self.set_program_counter(*destination),
+            Opcode::Jump { location: destination } => Ok(self.set_program_counter(*destination)),
             Opcode::JumpIf { condition, location: destination } => {
                 // Check if condition is true
                 // We use 0 to mean false and any other value to mean true
-                let condition_value = self.registers.get(*condition);
+                let condition_value = self.registers.try_get(*condition)?;
                 if !condition_value.is_zero() {
-                    return self.set_program_counter(*destination);
+                    return Ok(self.set_program_counter(*destination));
                 }
-                self.increment_program_counter()
+                Ok(self.increment_program_counter())
             }
             Opcode::JumpIfNot { condition, location: destination } => {
-                let condition_value = self.registers.get(*condition);
+                let condition_value = self.registers.try_get(*condition)?;
                 if condition_value.is_zero() {
-                    return self.set_program_counter(*destination);
-                }
-                self.increment_program_counter()
-            }
-            Opcode::Return => {
-                if let Some(register) = self.call_stack.pop() {
-                    self.set_program_counter(register.to_usize())
-                } else {
-                    self.fail("return opcode hit, but callstack already empty".to_string())
+                    return Ok(self.set_program_counter(*destination));
                 }
+                Ok(self.increment_program_counter())
             }
+            Opcode::Return => match self.call_stack.pop() {
+                Some(register) => Ok(self.set_program_counter(register.try_to_usize()?)),
+                None => Err(FailureKind::EmptyCallStack),
+            },
             Opcode::ForeignCall { function, destinations, inputs } => {
                 if self.foreign_call_counter >= self.foreign_call_results.len() {
                     // When this opcode is called, it is possible that the results of a foreign call are
@@ -182,109 +300,120 @@ impl VM {
                     let resolved_inputs = inputs
                         .iter()
                         .map(|input| self.get_register_value_or_memory_values(*input))
-                        .collect::<Vec<_>>();
-                    return self.wait_for_foreign_call(function.clone(), resolved_inputs);
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return Ok(self.wait_for_foreign_call(function.clone(), resolved_inputs));
                 }
 
-                let values = &self.foreign_call_results[self.foreign_call_counter].values;
+                let result = &self.foreign_call_results[self.foreign_call_counter];
+                if let Some(message) = &result.error {
+                    return Err(FailureKind::ForeignCallFailed {
+                        function: function.clone(),
+                        message: message.clone(),
+                    });
+                }
+                let values = &result.values;
+                if destinations.len() != values.len() {
+                    return Err(FailureKind::InvalidForeignCallResult(format!(
+                        "{} output values were provided as a foreign call result for {} destination slots",
+                        values.len(),
+                        destinations.len()
+                    )));
+                }
 
-                let mut invalid_foreign_call_result = false;
                 for (destination, output) in destinations.iter().zip(values) {
-                    match destination {
-                        RegisterOrMemory::RegisterIndex(value_index) => match output {
-                            ForeignCallOutput::Single(value) => {
-                                self.registers.set(*value_index, *value)
-                            }
-                            _ => unreachable!(
-                                "Function result size does not match brillig bytecode (expected 1 result)"
-                            ),
-                        },
-                        RegisterOrMemory::HeapArray(HeapArray { pointer: pointer_index, size }) => {
-                            match output {
-                                ForeignCallOutput::Array(values) => {
-                                    if values.len() != *size {
-                                        invalid_foreign_call_result = true;
-                                        break;
-                                    }
-                                    // Convert the destination pointer to a usize
-                                    let destination = self.registers.get(*pointer_index).to_usize();
-                                    // Write to our destination memory
-                                    self.memory.write_slice(destination, values);
-                                }
-                                _ => {
-                                    unreachable!("Function result size does not match brillig bytecode size")
-                                }
+                    match (destination, output) {
+                        (
+                            RegisterOrMemory::RegisterIndex(value_index),
+                            ForeignCallOutput::Single(value),
+                        ) => self.registers.try_set(*value_index, *value)?,
+                        (
+                            RegisterOrMemory::HeapArray(HeapArray { pointer: pointer_index, size }),
+                            ForeignCallOutput::Array(values),
+                        ) => {
+                            if values.len() != *size {
+                                return Err(FailureKind::InvalidForeignCallResult(format!(
+                                    "expected an array of {size} values but {} were provided",
+                                    values.len()
+                                )));
                             }
+                            // Convert the destination pointer to a usize
+                            let destination =
+                                self.registers.try_get(*pointer_index)?.try_to_usize()?;
+                            // Write to our destination memory
+                            self.memory.try_write_slice(destination, values)?;
                         }
-                        RegisterOrMemory::HeapVector(HeapVector { pointer: pointer_index, size: size_index }) => {
-                            match output {
-                                ForeignCallOutput::Array(values) => {
-                                    // Set our size in the size register
-                                    self.registers.set(*size_index, Value::from(values.len()));
-                                    // Convert the destination pointer to a usize
-                                    let destination = self.registers.get(*pointer_index).to_usize();
-                                    // Write to our destination memory
-                                    self.memory.write_slice(destination, values);
-                                }
-                                _ => {
-                                    unreachable!("Function result size does not match brillig bytecode size")
-                                }
-                            }
+                        (
+                            RegisterOrMemory::HeapVector(HeapVector {
+                                pointer: pointer_index,
+                                size: size_index,
+                            }),
+                            ForeignCallOutput::Array(values),
+                        ) => {
+                            // Set our size in the size register
+                            self.registers.try_set(*size_index, Value::from(values.len()))?;
+                            // Convert the destination pointer to a usize
+                            let destination =
+                                self.registers.try_get(*pointer_index)?.try_to_usize()?;
+                            // Write to our destination memory
+                            self.memory.try_write_slice(destination, values)?;
+                        }
+                        (RegisterOrMemory::RegisterIndex(_), ForeignCallOutput::Array(_)) => {
+                            return Err(FailureKind::InvalidForeignCallResult(
+                                "expected a single value but an array was provided".to_owned(),
+                            ))
+                        }
+                        (_, ForeignCallOutput::Single(_)) => {
+                            return Err(FailureKind::InvalidForeignCallResult(
+                                "expected an array but a single value was provided".to_owned(),
+                            ))
                         }
                     }
                 }
 
-                // These checks must come after resolving the foreign call outputs as `fail` uses a mutable reference
-                if destinations.len() != values.len() {
-                    self.fail(format!("{} output values were provided as a foreign call result for {} destination slots", values.len(), destinations.len()));
-                }
-                if invalid_foreign_call_result {
-                    self.fail("Function result size does not match brillig bytecode".to_owned());
-                }
-
                 self.foreign_call_counter += 1;
-                self.increment_program_counter()
+                Ok(self.increment_program_counter())
             }
             Opcode::Mov { destination: destination_register, source: source_register } => {
-                let source_value = self.registers.get(*source_register);
-                self.registers.set(*destination_register, source_value);
-                self.increment_program_counter()
+                let source_value = self.registers.try_get(*source_register)?;
+                self.registers.try_set(*destination_register, source_value)?;
+                Ok(self.increment_program_counter())
             }
-            Opcode::Trap => self.fail("explicit trap hit in brillig".to_string()),
-            Opcode::Stop => self.finish(),
+            Opcode::Trap => Err(FailureKind::Trap),
+            Opcode::Stop => Ok(self.finish()),
             Opcode::Load { destination: destination_register, source_pointer } => {
                 // Convert our source_pointer to a usize
-                let source = self.registers.get(*source_pointer);
+                let source = self.registers.try_get(*source_pointer)?.try_to_usize()?;
                 // Use our usize source index to lookup the value in memory
-                let value = &self.memory.read(source.to_usize());
-                self.registers.set(*destination_register, *value);
-                self.increment_program_counter()
+                let value = self.memory.try_read(source)?;
+                self.registers.try_set(*destination_register, value)?;
+                Ok(self.increment_program_counter())
             }
             Opcode::Store { destination_pointer, source: source_register } => {
                 // Convert our destination_pointer to a usize
-                let destination = self.registers.get(*destination_pointer).to_usize();
+                let destination = self.registers.try_get(*destination_pointer)?.try_to_usize()?;
                 // Use our usize destination index to set the value in memory
-                self.memory.write(destination, self.registers.get(*source_register));
-                self.increment_program_counter()
+                let value = self.registers.try_get(*source_register)?;
+                self.memory.try_write_slice(destination, &[value])?;
+                Ok(self.increment_program_counter())
             }
             Opcode::Call { location } => {
                 // Push a return location
                 self.call_stack.push(Value::from(self.program_counter + 1));
-                self.set_program_counter(*location)
+                Ok(self.set_program_counter(*location))
             }
             Opcode::Const { destination, value } => {
-                self.registers.set(*destination, *value);
-                self.increment_program_counter()
+                self.registers.try_set(*destination, *value)?;
+                Ok(self.increment_program_counter())
             }
             Opcode::BlackBox(black_box_op) => {
-                black_box_op.evaluate(&mut self.registers, &mut self.memory);
-                self.increment_program_counter()
+                black_box_op.evaluate(&mut self.registers, &mut self.memory)?;
+                Ok(self.increment_program_counter())
             }
         }
     }
 
     /// Returns the current value of the program counter.
-    pub fn program_counter(self) -> usize {
+    pub fn program_counter(&self) -> usize {
         self.program_counter
     }
 
@@ -305,22 +434,25 @@ impl VM {
         self.status.clone()
     }
 
-    fn get_register_value_or_memory_values(&self, input: RegisterOrMemory) -> Vec<Value> {
+    fn get_register_value_or_memory_values(
+        &self,
+        input: RegisterOrMemory,
+    ) -> Result<Vec<Value>, FailureKind> {
         match input {
             RegisterOrMemory::RegisterIndex(value_index) => {
-                vec![self.registers.get(value_index)]
+                Ok(vec![self.registers.try_get(value_index)?])
             }
             RegisterOrMemory::HeapArray(HeapArray { pointer: pointer_index, size }) => {
-                let start = self.registers.get(pointer_index);
-                self.memory.read_slice(start.to_usize(), size).to_vec()
+                let start = self.registers.try_get(pointer_index)?.try_to_usize()?;
+                Ok(self.memory.try_read_slice(start, size)?.to_vec())
             }
             RegisterOrMemory::HeapVector(HeapVector {
                 pointer: pointer_index,
                 size: size_index,
             }) => {
-                let start = self.registers.get(pointer_index);
-                let size = self.registers.get(size_index);
-                self.memory.read_slice(start.to_usize(), size.to_usize()).to_vec()
+                let start = self.registers.try_get(pointer_index)?.try_to_usize()?;
+                let size = self.registers.try_get(size_index)?.try_to_usize()?;
+                Ok(self.memory.try_read_slice(start, size)?.to_vec())
             }
         }
     }
@@ -333,13 +465,13 @@ impl VM {
         lhs: RegisterIndex,
         rhs: RegisterIndex,
         result: RegisterIndex,
-    ) {
-        let lhs_value = self.registers.get(lhs);
-        let rhs_value = self.registers.get(rhs);
+    ) -> Result<(), FailureKind> {
+        let lhs_value = self.registers.try_get(lhs)?;
+        let rhs_value = self.registers.try_get(rhs)?;
 
-        let result_value = op.evaluate_field(lhs_value.to_field(), rhs_value.to_field());
+        let result_value = op.evaluate_field(lhs_value.to_field(), rhs_value.to_field())?;
 
-        self.registers.set(result, result_value.into())
+        self.registers.try_set(result, result_value.into())
     }
 
     /// Process a binary operation.
@@ -351,12 +483,12 @@ impl VM {
         lhs: RegisterIndex,
         rhs: RegisterIndex,
         result: RegisterIndex,
-    ) {
-        let lhs_value = self.registers.get(lhs);
-        let rhs_value = self.registers.get(rhs);
+    ) -> Result<(), FailureKind> {
+        let lhs_value = self.registers.try_get(lhs)?;
+        let rhs_value = self.registers.try_get(rhs)?;
 
-        let result_value = op.evaluate_int(lhs_value.to_u128(), rhs_value.to_u128(), bit_size);
-        self.registers.set(result, result_value.into());
+        let result_value = op.evaluate_int(lhs_value.to_u128(), rhs_value.to_u128(), bit_size)?;
+        self.registers.try_set(result, result_value.into())
     }
 }
 
@@ -486,10 +618,7 @@ mod tests {
         assert_eq!(status, VMStatus::InProgress);
 
         let status = vm.process_opcode();
-        assert_eq!(
-            status,
-            VMStatus::Failure { message: "explicit trap hit in brillig".to_string() }
-        );
+        assert_eq!(status, VMStatus::Failure { kind: FailureKind::Trap, program_counter: 1 });
 
         // The register at index `2` should have not changed as we jumped over the add opcode
         let VM { registers, .. } = vm;
@@ -994,6 +1123,7 @@ mod tests {
         // Push result we're waiting for
         vm.foreign_call_results.push(ForeignCallResult {
             values: vec![ForeignCallOutput::Array(output_string.clone())],
+            error: None,
         });
 
         // Resume VM
@@ -1154,4 +1284,194 @@ mod tests {
         // Ensure the foreign call counter has been incremented
         assert_eq!(vm.foreign_call_counter, 1);
     }
+
+    /// Bytecode which counts register 0 up to the value of register 1, storing each count in memory.
+    fn counting_loop() -> Vec<Opcode> {
+        let counter = RegisterIndex::from(0);
+        let limit = RegisterIndex::from(1);
+        let one = RegisterIndex::from(2);
+        let condition = RegisterIndex::from(3);
+        vec![
+            Opcode::Const { destination: one, value: Value::from(1u128) },
+            Opcode::Store { destination_pointer: counter, source: counter },
+            Opcode::BinaryIntOp {
+                op: BinaryIntOp::Add,
+                bit_size: 32,
+                lhs: counter,
+                rhs: one,
+                destination: counter,
+            },
+            Opcode::BinaryIntOp {
+                op: BinaryIntOp::LessThan,
+                bit_size: 32,
+                lhs: counter,
+                rhs: limit,
+                destination: condition,
+            },
+            Opcode::JumpIf { condition, location: 1 },
+        ]
+    }
+
+    #[test]
+    fn traces_register_and_memory_writes() {
+        let registers = Registers::load(vec![Value::from(0u128), Value::from(2u128)]);
+        let mut vm = VM::new(registers, vec![], counting_loop(), vec![]);
+        vm.enable_tracing();
+        assert_eq!(vm.process_opcodes(), VMStatus::Finished);
+
+        let trace = vm.trace().expect("tracing was enabled");
+        let program_counters: Vec<usize> = trace.iter().map(|step| step.program_counter).collect();
+        assert_eq!(program_counters, vec![0, 1, 2, 3, 4, 1, 2, 3, 4]);
+        assert_eq!(vm.instructions_executed(), trace.len());
+
+        assert_eq!(trace[0].register_writes, vec![(RegisterIndex::from(2), Value::from(1u128))]);
+        assert_eq!(trace[5].opcode, counting_loop()[1]);
+        assert_eq!(trace[5].register_writes, vec![]);
+        assert_eq!(trace[5].memory_writes, vec![(1, Value::from(1u128))]);
+        assert_eq!(trace[6].register_writes, vec![(RegisterIndex::from(0), Value::from(2u128))]);
+    }
+
+    #[test]
+    fn pauses_at_breakpoints() {
+        let registers = Registers::load(vec![Value::from(0u128), Value::from(3u128)]);
+        let mut vm = VM::new(registers, vec![], counting_loop(), vec![]);
+        vm.add_breakpoint(2);
+
+        for count in 0..3u128 {
+            assert_eq!(vm.process_opcodes(), VMStatus::InProgress);
+            assert_eq!(vm.program_counter(), 2);
+            assert_eq!(vm.get_registers().get(RegisterIndex::from(0)), Value::from(count));
+        }
+
+        assert!(vm.remove_breakpoint(2));
+        assert!(!vm.remove_breakpoint(2));
+        assert_eq!(vm.process_opcodes(), VMStatus::Finished);
+        assert_eq!(
+            vm.get_memory(),
+            &vec![Value::from(0u128), Value::from(1u128), Value::from(2u128)]
+        );
+    }
+
+    #[test]
+    fn fails_once_instruction_budget_is_exhausted() {
+        let mut bytecode = counting_loop();
+        // Loop forever by never updating the counter.
+        bytecode[2] =
+            Opcode::Mov { destination: RegisterIndex::from(4), source: RegisterIndex::from(0) };
+
+        let registers = Registers::load(vec![Value::from(0u128), Value::from(3u128)]);
+        let mut vm = VM::new(registers, vec![], bytecode, vec![]);
+        vm.set_instruction_budget(Some(100));
+        assert_eq!(
+            vm.process_opcodes(),
+            VMStatus::Failure {
+                kind: FailureKind::InstructionBudgetExhausted(100),
+                program_counter: 4
+            }
+        );
+        assert_eq!(vm.instructions_executed(), 100);
+    }
+
+    #[test]
+    fn reports_failures_instead_of_panicking() {
+        let failure = |registers: Vec<Value>, bytecode: Vec<Opcode>, foreign_call_results| {
+            let mut vm =
+                VM::new(Registers::load(registers), vec![], bytecode, foreign_call_results);
+            match vm.process_opcodes() {
+                VMStatus::Failure { kind, program_counter } => (kind, program_counter),
+                status => panic!("expected failure, got {status:?}"),
+            }
+        };
+        let r = RegisterIndex::from;
+
+        let past_max_register = r(registers::MAX_REGISTERS);
+        assert_eq!(
+            failure(
+                vec![],
+                vec![Opcode::Mov { destination: past_max_register, source: r(0) }],
+                vec![]
+            ),
+            (FailureKind::RegisterOutOfBounds(past_max_register), 0)
+        );
+        assert_eq!(
+            failure(
+                vec![Value::from(3u128)],
+                vec![Opcode::Load { destination: r(1), source_pointer: r(0) }],
+                vec![]
+            ),
+            (FailureKind::MemoryOutOfBounds { pointer: 3, size: 1 }, 0)
+        );
+        let huge_pointer = -acir_field::FieldElement::one();
+        assert_eq!(
+            failure(
+                vec![Value::from(huge_pointer)],
+                vec![Opcode::Store { destination_pointer: r(0), source: r(0) }],
+                vec![]
+            ),
+            (FailureKind::ValueTooLarge(huge_pointer), 0)
+        );
+        assert_eq!(
+            failure(
+                vec![Value::from(u32::MAX as usize)],
+                vec![Opcode::Store { destination_pointer: r(0), source: r(0) }],
+                vec![]
+            ),
+            (FailureKind::MemoryOutOfBounds { pointer: u32::MAX as usize, size: 1 }, 0)
+        );
+        assert_eq!(
+            failure(
+                vec![Value::from(1u128), Value::from(0u128)],
+                vec![
+                    Opcode::Const { destination: r(2), value: Value::from(1u128) },
+                    Opcode::BinaryFieldOp {
+                        op: BinaryFieldOp::Div,
+                        lhs: r(0),
+                        rhs: r(1),
+                        destination: r(2),
+                    }
+                ],
+                vec![]
+            ),
+            (FailureKind::DivisionByZero, 1)
+        );
+        assert_eq!(
+            failure(
+                vec![],
+                vec![Opcode::ForeignCall {
+                    function: "oracle".to_owned(),
+                    destinations: vec![RegisterOrMemory::RegisterIndex(r(0))],
+                    inputs: vec![],
+                }],
+                vec![ForeignCallResult {
+                    values: vec![ForeignCallOutput::Array(vec![])],
+                    error: None
+                }]
+            ),
+            (
+                FailureKind::InvalidForeignCallResult(
+                    "expected a single value but an array was provided".to_owned()
+                ),
+                0
+            )
+        );
+        assert_eq!(
+            failure(
+                vec![],
+                vec![Opcode::ForeignCall {
+                    function: "oracle".to_owned(),
+                    destinations: vec![RegisterOrMemory::RegisterIndex(r(0))],
+                    inputs: vec![],
+                }],
+                vec![ForeignCallResult::error("value not found")]
+            ),
+            (
+                FailureKind::ForeignCallFailed {
+                    function: "oracle".to_owned(),
+                    message: "value not found".to_owned()
+                },
+                0
+            )
+        );
+        assert_eq!(failure(vec![], vec![Opcode::Return], vec![]), (FailureKind::EmptyCallStack, 0));
+    }
 }
@@ -1,19 +1,49 @@
-use crate::Value;
+use crate::{FailureKind, Value};
+
+/// The maximum number of values which the memory may grow to hold.
+///
+/// This catches writes to erroneous addresses, which would otherwise attempt to allocate huge amounts of memory.
+const MAX_MEMORY_SIZE: usize = 2_usize.pow(24);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Memory {
     // Memory is a vector of values.
     // We grow the memory when values past the end are set, extending with 0s.
     inner: Vec<Value>,
+    // Writes made since the journal was started, if it has been.
+    journal: Option<Vec<(usize, Value)>>,
 }
 
 impl From<Vec<Value>> for Memory {
     fn from(values: Vec<Value>) -> Self {
-        Memory { inner: values }
+        Memory { inner: values, journal: None }
     }
 }
 
 impl Memory {
+    /// Gets the value at pointer, failing if it is past the end of memory.
+    pub fn try_read(&self, ptr: usize) -> Result<Value, FailureKind> {
+        self.try_read_slice(ptr, 1).map(|values| values[0])
+    }
+
+    /// Gets the `len` values after pointer, failing if they extend past the end of memory.
+    pub fn try_read_slice(&self, ptr: usize, len: usize) -> Result<&[Value], FailureKind> {
+        ptr.checked_add(len)
+            .and_then(|end| self.inner.get(ptr..end))
+            .ok_or(FailureKind::MemoryOutOfBounds { pointer: ptr, size: len })
+    }
+
+    /// Sets the values after pointer `ptr` to `values`, failing if memory would need to grow past its maximum size.
+    pub fn try_write_slice(&mut self, ptr: usize, values: &[Value]) -> Result<(), FailureKind> {
+        match ptr.checked_add(values.len()) {
+            Some(end) if end <= MAX_MEMORY_SIZE => {
+                self.write_slice(ptr, values);
+                Ok(())
+            }
+            _ => Err(FailureKind::MemoryOutOfBounds { pointer: ptr, size: values.len() }),
+        }
+    }
+
     /// Gets the value at pointer
     pub fn read(&self, ptr: usize) -> Value {
         self.inner[ptr]
@@ -36,6 +66,19 @@ impl Memory {
         self.inner.resize(new_size, Value::from(0_usize));
 
         self.inner[ptr..ptr + values.len()].copy_from_slice(values);
+        if let Some(journal) = &mut self.journal {
+            journal.extend(values.iter().enumerate().map(|(offset, value)| (ptr + offset, *value)));
+        }
+    }
+
+    /// Starts recording the writes made to memory.
+    pub(crate) fn start_journal(&mut self) {
+        self.journal = Some(Vec::new());
+    }
+
+    /// Stops recording writes, returning those made since [`Memory::start_journal`] was called.
+    pub(crate) fn take_journal(&mut self) -> Vec<(usize, Value)> {
+        self.journal.take().unwrap_or_default()
     }
 
     /// Returns the values of the memory
@@ -1,4 +1,4 @@
-use crate::{black_box::BlackBoxOp, RegisterIndex, Value};
+use crate::{black_box::BlackBoxOp, FailureKind, RegisterIndex, Value};
 use acir_field::FieldElement;
 use serde::{Deserialize, Serialize};
 
@@ -140,6 +140,79 @@ impl Opcode {
     }
 }
 
+impl std::fmt::Display for HeapArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "array({}, {})", self.pointer, self.size)
+    }
+}
+
+impl std::fmt::Display for HeapVector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "vector({}, {})", self.pointer, self.size)
+    }
+}
+
+impl std::fmt::Display for RegisterOrMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegisterOrMemory::RegisterIndex(index) => write!(f, "{index}"),
+            RegisterOrMemory::HeapArray(array) => write!(f, "{array}"),
+            RegisterOrMemory::HeapVector(vector) => write!(f, "{vector}"),
+        }
+    }
+}
+
+/// Writes a single Brillig instruction in the textual format accepted by its `FromStr` implementation.
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Opcode::BinaryFieldOp { destination, op, lhs, rhs } => {
+                write!(f, "{} {destination}, {lhs}, {rhs}", op.mnemonic())
+            }
+            Opcode::BinaryIntOp { destination, op, bit_size, lhs, rhs } => {
+                write!(f, "{}.{bit_size} {destination}, {lhs}, {rhs}", op.mnemonic())
+            }
+            Opcode::JumpIfNot { condition, location } => {
+                write!(f, "jmp_if_not {condition}, {location}")
+            }
+            Opcode::JumpIf { condition, location } => write!(f, "jmp_if {condition}, {location}"),
+            Opcode::Jump { location } => write!(f, "jmp {location}"),
+            Opcode::Call { location } => write!(f, "call {location}"),
+            Opcode::Const { destination, value } => {
+                write!(f, "const {destination}, {}", value.to_field())
+            }
+            Opcode::Return => write!(f, "return"),
+            Opcode::ForeignCall { function, destinations, inputs } => {
+                write!(f, "foreign_call {function:?}")?;
+                write_operands(f, inputs)?;
+                write!(f, " ->")?;
+                write_operands(f, destinations)
+            }
+            Opcode::Mov { destination, source } => write!(f, "mov {destination}, {source}"),
+            Opcode::Load { destination, source_pointer } => {
+                write!(f, "load {destination}, {source_pointer}")
+            }
+            Opcode::Store { destination_pointer, source } => {
+                write!(f, "store {destination_pointer}, {source}")
+            }
+            Opcode::BlackBox(op) => write!(f, "{op}"),
+            Opcode::Trap => write!(f, "trap"),
+            Opcode::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+fn write_operands(
+    f: &mut std::fmt::Formatter<'_>,
+    operands: &[RegisterOrMemory],
+) -> std::fmt::Result {
+    for (index, operand) in operands.iter().enumerate() {
+        let separator = if index == 0 { " " } else { ", " };
+        write!(f, "{separator}{operand}")?;
+    }
+    Ok(())
+}
+
 /// Binary fixed-length field expressions
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinaryFieldOp {
@@ -178,33 +251,78 @@ pub enum BinaryIntOp {
 }
 
 impl BinaryFieldOp {
-    /// Evaluate a binary operation on two FieldElements and return the result as a FieldElement.
-    pub fn evaluate_field(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+    /// Name of the operation in the textual format.
+    pub fn mnemonic(&self) -> &'static str {
         match self {
+            BinaryFieldOp::Add => "fadd",
+            BinaryFieldOp::Sub => "fsub",
+            BinaryFieldOp::Mul => "fmul",
+            BinaryFieldOp::Div => "fdiv",
+            BinaryFieldOp::Equals => "feq",
+        }
+    }
+
+    /// Evaluate a binary operation on two FieldElements and return the result as a FieldElement.
+    pub fn evaluate_field(
+        &self,
+        a: FieldElement,
+        b: FieldElement,
+    ) -> Result<FieldElement, FailureKind> {
+        let result = match self {
             // Perform addition, subtraction, multiplication, and division based on the BinaryOp variant.
             BinaryFieldOp::Add => a + b,
             BinaryFieldOp::Sub => a - b,
             BinaryFieldOp::Mul => a * b,
-            BinaryFieldOp::Div => a / b,
+            BinaryFieldOp::Div => a * b.try_inverse().ok_or(FailureKind::DivisionByZero)?,
             BinaryFieldOp::Equals => (a == b).into(),
-        }
+        };
+        Ok(result)
     }
 }
 
 impl BinaryIntOp {
+    /// Name of the operation in the textual format.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            BinaryIntOp::Add => "add",
+            BinaryIntOp::Sub => "sub",
+            BinaryIntOp::Mul => "mul",
+            BinaryIntOp::SignedDiv => "sdiv",
+            BinaryIntOp::UnsignedDiv => "udiv",
+            BinaryIntOp::Equals => "eq",
+            BinaryIntOp::LessThan => "lt",
+            BinaryIntOp::LessThanEquals => "lte",
+            BinaryIntOp::And => "and",
+            BinaryIntOp::Or => "or",
+            BinaryIntOp::Xor => "xor",
+            BinaryIntOp::Shl => "shl",
+            BinaryIntOp::Shr => "shr",
+        }
+    }
+
     /// Evaluate a binary operation on two unsigned integers (u128) with a given bit size and return the result as a u128.
-    pub fn evaluate_int(&self, a: u128, b: u128, bit_size: u32) -> u128 {
+    ///
+    /// Bit sizes from 1 to 127 are supported.
+    pub fn evaluate_int(&self, a: u128, b: u128, bit_size: u32) -> Result<u128, FailureKind> {
+        if bit_size == 0 || bit_size >= 128 {
+            return Err(FailureKind::InvalidBitSize(bit_size));
+        }
         let bit_modulo = 1_u128 << bit_size;
-        match self {
+        let result = match self {
             // Perform addition, subtraction, and multiplication, applying a modulo operation to keep the result within the bit size.
             BinaryIntOp::Add => a.wrapping_add(b) % bit_modulo,
             BinaryIntOp::Sub => a.wrapping_sub(b) % bit_modulo,
             BinaryIntOp::Mul => a.wrapping_mul(b) % bit_modulo,
             // Perform unsigned division using the modulo operation on a and b.
-            BinaryIntOp::UnsignedDiv => (a % bit_modulo) / (b % bit_modulo),
+            BinaryIntOp::UnsignedDiv => {
+                (a % bit_modulo).checked_div(b % bit_modulo).ok_or(FailureKind::DivisionByZero)?
+            }
             // Perform signed division by first converting a and b to signed integers and then back to unsigned after the operation.
             BinaryIntOp::SignedDiv => {
-                to_unsigned(to_signed(a, bit_size) / to_signed(b, bit_size), bit_size)
+                let quotient = to_signed(a % bit_modulo, bit_size)
+                    .checked_div(to_signed(b % bit_modulo, bit_size))
+                    .ok_or(FailureKind::DivisionByZero)?;
+                to_unsigned(quotient, bit_size)
             }
             // Perform a == operation, returning 0 or 1
             BinaryIntOp::Equals => ((a % bit_modulo) == (b % bit_modulo)).into(),
@@ -216,12 +334,19 @@ impl BinaryIntOp {
             BinaryIntOp::And => (a & b) % bit_modulo,
             BinaryIntOp::Or => (a | b) % bit_modulo,
             BinaryIntOp::Xor => (a ^ b) % bit_modulo,
-            BinaryIntOp::Shl => (a << b) % bit_modulo,
-            BinaryIntOp::Shr => (a >> b) % bit_modulo,
-        }
+            // Shifting by the full width of a u128 or more shifts out every bit.
+            BinaryIntOp::Shl => (a.checked_shl(shift_amount(b)).unwrap_or(0)) % bit_modulo,
+            BinaryIntOp::Shr => (a.checked_shr(shift_amount(b)).unwrap_or(0)) % bit_modulo,
+        };
+        Ok(result)
     }
 }
 
+/// Saturates a shift amount into the range accepted by the shift operations of `u128`.
+fn shift_amount(b: u128) -> u32 {
+    u32::try_from(b).unwrap_or(u32::MAX)
+}
+
 fn to_signed(a: u128, bit_size: u32) -> i128 {
     assert!(bit_size < 128);
     let pow_2 = 2_u128.pow(bit_size - 1);
@@ -236,7 +361,7 @@ fn to_unsigned(a: i128, bit_size: u32) -> u128 {
     if a >= 0 {
         a as u128
     } else {
-        (a + 2_i128.pow(bit_size)) as u128
+        (a as u128).wrapping_add(1_u128 << bit_size)
     }
 }
 
@@ -258,7 +383,7 @@ mod tests {
 
     fn evaluate_int_ops(test_params: Vec<TestParams>, op: BinaryIntOp, bit_size: u32) {
         for test in test_params {
-            assert_eq!(op.evaluate_int(test.a, test.b, bit_size), test.result);
+            assert_eq!(op.evaluate_int(test.a, test.b, bit_size), Ok(test.result));
         }
     }
 
@@ -316,7 +441,7 @@ mod tests {
         let b = 3;
 
         // ( 2**(n-1) - 1 ) * 3 = 2*2**(n-1) - 2 + (2**(n-1) - 1) => wraps to (2**(n-1) - 1) - 2
-        assert_eq!(BinaryIntOp::Mul.evaluate_int(a, b, bit_size), a - 2);
+        assert_eq!(BinaryIntOp::Mul.evaluate_int(a, b, bit_size), Ok(a - 2));
     }
 
     #[test]
@@ -348,4 +473,30 @@ mod tests {
 
         evaluate_int_ops(test_ops, BinaryIntOp::SignedDiv, bit_size);
     }
+
+    #[test]
+    fn division_by_zero_fails() {
+        for op in [BinaryIntOp::UnsignedDiv, BinaryIntOp::SignedDiv] {
+            assert_eq!(op.evaluate_int(5, 0, 32), Err(FailureKind::DivisionByZero));
+            // The divisor is zero once reduced to the bit size.
+            assert_eq!(op.evaluate_int(5, 1 << 32, 32), Err(FailureKind::DivisionByZero));
+        }
+        assert_eq!(
+            BinaryFieldOp::Div.evaluate_field(FieldElement::one(), FieldElement::zero()),
+            Err(FailureKind::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_bit_sizes() {
+        for bit_size in [0, 128, 200] {
+            assert_eq!(
+                BinaryIntOp::Add.evaluate_int(1, 1, bit_size),
+                Err(FailureKind::InvalidBitSize(bit_size))
+            );
+        }
+        // Shifting past the width of the integer doesn't overflow.
+        assert_eq!(BinaryIntOp::Shl.evaluate_int(1, 1000, 127), Ok(0));
+        assert_eq!(BinaryIntOp::Shr.evaluate_int(1, u128::MAX, 127), Ok(0));
+    }
 }
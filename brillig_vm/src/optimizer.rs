@@ -0,0 +1,660 @@
+//! Optimization of Brillig bytecode.
+//!
+//! The optimizations performed are local to the functions entered using `call`, which are analysed as part of
+//! a control flow graph of the whole program without assuming anything about the registers they modify.
+
+use std::collections::{hash_map, BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    registers::MAX_REGISTERS, BlackBoxOp, HeapVector, Label, Opcode, RegisterIndex,
+    RegisterOrMemory, Value,
+};
+
+/// Optimizes `bytecode`, returning bytecode which behaves identically when executed by the [VM][crate::VM].
+///
+/// The optimizations performed are:
+/// - jump threading, where jumps to an unconditional jump target its destination instead,
+/// - constant folding of binary operations on registers holding known constants, along with conditional
+///   jumps on known conditions,
+/// - copy propagation, where registers holding a copy of another register are read from the original,
+/// - elimination of `mov` and `const` instructions which write a value the register already holds, and
+/// - elimination of unreachable instructions and of jumps to the instruction which follows them.
+///
+/// As the registers are the outputs of Brillig bytecode, every register written by the original bytecode
+/// is written with the same value by the optimized bytecode. However fewer instructions are executed, and
+/// the program counter of any failure refers to the optimized bytecode.
+pub fn optimize(bytecode: &[Opcode]) -> Vec<Opcode> {
+    // Each optimization can expose opportunities for the others, such as a constant condition turning a
+    // branch into unreachable code, so they are repeated until the bytecode stops changing.
+    let mut bytecode = bytecode.to_vec();
+    loop {
+        let optimized = optimize_once(bytecode.clone());
+        if optimized == bytecode {
+            return optimized;
+        }
+        bytecode = optimized;
+    }
+}
+
+fn optimize_once(mut bytecode: Vec<Opcode>) -> Vec<Opcode> {
+    thread_jumps(&mut bytecode);
+
+    let cfg = ControlFlowGraph::new(&bytecode);
+    let entry_facts = cfg.entry_facts(&bytecode);
+
+    // Unreachable instructions are left as `None` along with those which have been eliminated.
+    let mut simplified = vec![None; bytecode.len()];
+    for (start, block) in &cfg.blocks {
+        let mut facts = match entry_facts.get(start) {
+            Some(facts) => facts.clone(),
+            None => continue,
+        };
+        for location in block.start..block.end {
+            simplified[location] = simplify(&bytecode[location], &mut facts);
+        }
+    }
+    compact(simplified)
+}
+
+/// A sequence of instructions which is only entered at its first instruction and only left from its last.
+#[derive(Debug)]
+struct BasicBlock {
+    start: Label,
+    /// The location following the last instruction of the block.
+    end: Label,
+    /// The start of the blocks which may be executed after this one, including the function entered by
+    /// a `call` and the instruction it returns to.
+    successors: Vec<Label>,
+}
+
+#[derive(Debug)]
+struct ControlFlowGraph {
+    /// The blocks of the bytecode, keyed by their start.
+    blocks: BTreeMap<Label, BasicBlock>,
+    /// The start of the program, along with the start of functions and the locations which calls return to.
+    ///
+    /// Nothing is known about the registers at these locations, as functions may be called from anywhere and
+    /// may write to any register.
+    entries: BTreeSet<Label>,
+}
+
+impl ControlFlowGraph {
+    fn new(bytecode: &[Opcode]) -> Self {
+        let mut leaders = BTreeSet::from([0]);
+        let mut entries = BTreeSet::from([0]);
+        for (location, opcode) in bytecode.iter().enumerate() {
+            match opcode {
+                Opcode::Jump { location: target }
+                | Opcode::JumpIf { location: target, .. }
+                | Opcode::JumpIfNot { location: target, .. } => {
+                    leaders.extend([*target, location + 1]);
+                }
+                Opcode::Call { location: target } => {
+                    leaders.extend([*target, location + 1]);
+                    entries.extend([*target, location + 1]);
+                }
+                Opcode::Return | Opcode::Stop | Opcode::Trap => {
+                    leaders.insert(location + 1);
+                }
+                _ => {}
+            }
+        }
+        // Execution finishes at locations past the end of the bytecode, so no block starts there.
+        leaders.retain(|leader| *leader < bytecode.len());
+        entries.retain(|entry| *entry < bytecode.len());
+
+        let starts: Vec<Label> = leaders.into_iter().collect();
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(index, start)| {
+                let end = starts.get(index + 1).copied().unwrap_or(bytecode.len());
+                let mut successors = match &bytecode[end - 1] {
+                    Opcode::Jump { location } => vec![*location],
+                    Opcode::JumpIf { location, .. }
+                    | Opcode::JumpIfNot { location, .. }
+                    | Opcode::Call { location } => vec![end, *location],
+                    Opcode::Return | Opcode::Stop | Opcode::Trap => vec![],
+                    _ => vec![end],
+                };
+                successors.retain(|successor| *successor < bytecode.len());
+                successors.dedup();
+                (*start, BasicBlock { start: *start, end, successors })
+            })
+            .collect();
+
+        ControlFlowGraph { blocks, entries }
+    }
+
+    /// Returns what is known about the registers at the start of each reachable block.
+    ///
+    /// Blocks which are missing from the result can't be reached from the start of the program.
+    fn entry_facts(&self, bytecode: &[Opcode]) -> HashMap<Label, Facts> {
+        let mut entry_facts = HashMap::new();
+        if self.blocks.is_empty() {
+            return entry_facts;
+        }
+        entry_facts.insert(0, Facts::default());
+
+        let mut to_visit = vec![0];
+        while let Some(start) = to_visit.pop() {
+            let block = &self.blocks[&start];
+            let mut facts = entry_facts[&start].clone();
+            for opcode in &bytecode[block.start..block.end] {
+                simplify(opcode, &mut facts);
+            }
+
+            for successor in &block.successors {
+                let changed = if self.entries.contains(successor) {
+                    entry_facts.insert(*successor, Facts::default()).is_none()
+                } else {
+                    match entry_facts.entry(*successor) {
+                        hash_map::Entry::Vacant(entry) => {
+                            entry.insert(facts.clone());
+                            true
+                        }
+                        hash_map::Entry::Occupied(mut entry) => entry.get_mut().intersect(&facts),
+                    }
+                };
+                if changed {
+                    to_visit.push(*successor);
+                }
+            }
+        }
+        entry_facts
+    }
+}
+
+/// What is known about the value of a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Known {
+    Constant(Value),
+    /// The register holds the same value as another register.
+    CopyOf(RegisterIndex),
+}
+
+/// What is known about the values of the registers at some point in the bytecode.
+///
+/// Registers which have a known value have been written to, so are within the maximum register index.
+#[derive(Debug, Clone, Default)]
+struct Facts(HashMap<RegisterIndex, Known>);
+
+impl Facts {
+    fn constant(&self, register: RegisterIndex) -> Option<Value> {
+        match self.0.get(&register) {
+            Some(Known::Constant(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the register which `register` is a copy of, or `register` itself if it isn't a copy.
+    fn canonical(&self, register: RegisterIndex) -> RegisterIndex {
+        match self.0.get(&register) {
+            Some(Known::CopyOf(original)) => *original,
+            _ => register,
+        }
+    }
+
+    fn holds(&self, register: RegisterIndex, known: Known) -> bool {
+        self.0.get(&register) == Some(&known)
+    }
+
+    /// Records that `register` has been written, along with what is known about its new value.
+    fn write(&mut self, register: RegisterIndex, known: Option<Known>) {
+        self.0.remove(&register);
+        self.0.retain(|_, other| *other != Known::CopyOf(register));
+        if let Some(known) = known {
+            self.0.insert(register, known);
+        }
+    }
+
+    /// Forgets anything which isn't also known by `other`, returning whether anything was forgotten.
+    fn intersect(&mut self, other: &Facts) -> bool {
+        let num_facts = self.0.len();
+        self.0.retain(|register, known| other.0.get(register) == Some(known));
+        self.0.len() != num_facts
+    }
+}
+
+/// Returns an instruction equivalent to `opcode` given what is known about the registers, or `None` if it
+/// has no effect, and updates `facts` with the effects of the instruction.
+fn simplify(opcode: &Opcode, facts: &mut Facts) -> Option<Opcode> {
+    match opcode {
+        Opcode::BinaryFieldOp { destination, op, lhs, rhs } => {
+            let (lhs, rhs) = (facts.canonical(*lhs), facts.canonical(*rhs));
+            if let (Some(a), Some(b)) = (facts.constant(lhs), facts.constant(rhs)) {
+                // Failures, such as a division by zero, are left for the VM to report.
+                if let Ok(result) = op.evaluate_field(a.to_field(), b.to_field()) {
+                    return simplify_const(*destination, result.into(), facts);
+                }
+            }
+            facts.write(*destination, None);
+            Some(Opcode::BinaryFieldOp { destination: *destination, op: *op, lhs, rhs })
+        }
+        Opcode::BinaryIntOp { destination, op, bit_size, lhs, rhs } => {
+            let (lhs, rhs) = (facts.canonical(*lhs), facts.canonical(*rhs));
+            if let (Some(a), Some(b)) = (facts.constant(lhs), facts.constant(rhs)) {
+                if let Ok(result) = op.evaluate_int(a.to_u128(), b.to_u128(), *bit_size) {
+                    return simplify_const(*destination, result.into(), facts);
+                }
+            }
+            facts.write(*destination, None);
+            Some(Opcode::BinaryIntOp {
+                destination: *destination,
+                op: *op,
+                bit_size: *bit_size,
+                lhs,
+                rhs,
+            })
+        }
+        Opcode::JumpIf { condition, location } => {
+            let condition = facts.canonical(*condition);
+            match facts.constant(condition) {
+                Some(value) if value.is_zero() => None,
+                Some(_) => Some(Opcode::Jump { location: *location }),
+                None => Some(Opcode::JumpIf { condition, location: *location }),
+            }
+        }
+        Opcode::JumpIfNot { condition, location } => {
+            let condition = facts.canonical(*condition);
+            match facts.constant(condition) {
+                Some(value) if value.is_zero() => Some(Opcode::Jump { location: *location }),
+                Some(_) => None,
+                None => Some(Opcode::JumpIfNot { condition, location: *location }),
+            }
+        }
+        Opcode::Const { destination, value } => simplify_const(*destination, *value, facts),
+        Opcode::Mov { destination, source } => {
+            let source = facts.canonical(*source);
+            if source == *destination {
+                // Moving a register to itself only has an effect if the register is out of bounds,
+                // in which case the VM fails.
+                return (destination.to_usize() >= MAX_REGISTERS)
+                    .then_some(Opcode::Mov { destination: *destination, source });
+            }
+            let known = match facts.constant(source) {
+                Some(value) => Known::Constant(value),
+                None => Known::CopyOf(source),
+            };
+            if facts.holds(*destination, known) {
+                return None;
+            }
+            facts.write(*destination, Some(known));
+            Some(Opcode::Mov { destination: *destination, source })
+        }
+        Opcode::Load { destination, source_pointer } => {
+            let source_pointer = facts.canonical(*source_pointer);
+            facts.write(*destination, None);
+            Some(Opcode::Load { destination: *destination, source_pointer })
+        }
+        Opcode::Store { destination_pointer, source } => Some(Opcode::Store {
+            destination_pointer: facts.canonical(*destination_pointer),
+            source: facts.canonical(*source),
+        }),
+        Opcode::ForeignCall { destinations, .. } => {
+            for destination in destinations {
+                match destination {
+                    RegisterOrMemory::RegisterIndex(register)
+                    | RegisterOrMemory::HeapVector(HeapVector { size: register, .. }) => {
+                        facts.write(*register, None);
+                    }
+                    RegisterOrMemory::HeapArray(_) => {}
+                }
+            }
+            Some(opcode.clone())
+        }
+        Opcode::BlackBox(op) => {
+            match op {
+                BlackBoxOp::HashToField128Security { output: register, .. }
+                | BlackBoxOp::EcdsaSecp256k1 { result: register, .. }
+                | BlackBoxOp::EcdsaSecp256r1 { result: register, .. } => {
+                    facts.write(*register, None);
+                }
+                // These write their output to memory.
+                BlackBoxOp::Sha256 { .. }
+                | BlackBoxOp::Blake2s { .. }
+                | BlackBoxOp::Keccak256 { .. } => {}
+            }
+            Some(opcode.clone())
+        }
+        Opcode::Jump { .. }
+        | Opcode::Call { .. }
+        | Opcode::Return
+        | Opcode::Trap
+        | Opcode::Stop => Some(opcode.clone()),
+    }
+}
+
+fn simplify_const(destination: RegisterIndex, value: Value, facts: &mut Facts) -> Option<Opcode> {
+    if facts.holds(destination, Known::Constant(value)) {
+        return None;
+    }
+    facts.write(destination, Some(Known::Constant(value)));
+    Some(Opcode::Const { destination, value })
+}
+
+fn location_mut(opcode: &mut Opcode) -> Option<&mut Label> {
+    match opcode {
+        Opcode::Jump { location }
+        | Opcode::JumpIf { location, .. }
+        | Opcode::JumpIfNot { location, .. }
+        | Opcode::Call { location } => Some(location),
+        _ => None,
+    }
+}
+
+/// Replaces jumps and calls to an unconditional jump with jumps and calls to its destination.
+fn thread_jumps(bytecode: &mut [Opcode]) {
+    let destination = |bytecode: &[Opcode], location: Label| {
+        let mut visited = BTreeSet::new();
+        let mut destination = location;
+        while let Some(Opcode::Jump { location: next }) = bytecode.get(destination) {
+            // Jumps which form a loop never reach a destination, so are left as they are.
+            if !visited.insert(destination) {
+                return location;
+            }
+            destination = *next;
+        }
+        destination
+    };
+
+    for index in 0..bytecode.len() {
+        let mut opcode = bytecode[index].clone();
+        if let Some(location) = location_mut(&mut opcode) {
+            *location = destination(bytecode, *location);
+            bytecode[index] = opcode;
+        }
+    }
+}
+
+/// Removes the eliminated instructions, along with jumps to the instruction which follows them, and updates
+/// the locations of jumps and calls to match.
+fn compact(mut simplified: Vec<Option<Opcode>>) -> Vec<Opcode> {
+    let len = simplified.len();
+
+    // The first location at or after each location holding an instruction which is kept. Locations which are
+    // jumped to are replaced with this, as the instructions before it have no effect.
+    let mut next_kept = vec![len; len + 1];
+    for location in (0..len).rev() {
+        let following = next_kept[location + 1];
+        let jumps_to_following = match &simplified[location] {
+            // Only forward jumps can target the following instruction, and the instructions after this one
+            // have already been visited.
+            Some(Opcode::Jump { location: target }) if *target > location => {
+                next_kept[(*target).min(len)] == following
+            }
+            _ => false,
+        };
+        if simplified[location].is_none() || jumps_to_following {
+            simplified[location] = None;
+            next_kept[location] = following;
+        } else {
+            next_kept[location] = location;
+        }
+    }
+
+    let mut new_locations = Vec::with_capacity(len + 1);
+    let mut num_kept = 0;
+    for opcode in &simplified {
+        new_locations.push(num_kept);
+        num_kept += usize::from(opcode.is_some());
+    }
+    new_locations.push(num_kept);
+
+    simplified
+        .into_iter()
+        .flatten()
+        .map(|mut opcode| {
+            if let Some(location) = location_mut(&mut opcode) {
+                *location = new_locations[next_kept[(*location).min(len)]];
+            }
+            opcode
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize;
+    use crate::{
+        assemble, BinaryFieldOp, BinaryIntOp, FailureKind, ForeignCallResult, Opcode,
+        RegisterIndex, Registers, VMStatus, Value, VM,
+    };
+
+    /// Executes `bytecode`, returning its status along with the resulting registers and memory.
+    ///
+    /// Failures are reported without their program counter, which differs once bytecode is optimized.
+    fn execute(
+        bytecode: Vec<Opcode>,
+        registers: &[u128],
+        foreign_call_results: Vec<ForeignCallResult>,
+    ) -> (VMStatus, Vec<Value>, Vec<Value>) {
+        let registers = Registers::load(registers.iter().map(|value| (*value).into()).collect());
+        let memory = (0..8u128).map(Value::from).collect();
+        let mut vm = VM::new(registers, memory, bytecode, foreign_call_results);
+        vm.set_instruction_budget(Some(1000));
+
+        let status = match vm.process_opcodes() {
+            VMStatus::Failure { kind, .. } => VMStatus::Failure { kind, program_counter: 0 },
+            status => status,
+        };
+        let registers = (0..16).map(|index| vm.get_registers().get(RegisterIndex::from(index)));
+        (status, registers.collect(), vm.get_memory().clone())
+    }
+
+    /// Checks that `bytecode` behaves identically once optimized when started with each of `inputs` in
+    /// its registers.
+    fn assert_equivalent(
+        bytecode: &[Opcode],
+        inputs: &[&[u128]],
+        foreign_call_results: &[ForeignCallResult],
+    ) {
+        let optimized = optimize(bytecode);
+        for registers in inputs {
+            let expected = execute(bytecode.to_vec(), registers, foreign_call_results.to_vec());
+            if matches!(
+                expected.0,
+                VMStatus::Failure { kind: FailureKind::InstructionBudgetExhausted(_), .. }
+            ) {
+                continue;
+            }
+            assert_eq!(
+                execute(optimized.clone(), registers, foreign_call_results.to_vec()),
+                expected,
+                "optimizing {bytecode:?} into {optimized:?} changed its behaviour with registers {registers:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn eliminates_redundant_and_unreachable_instructions() {
+        let bytecode = assemble(
+            r#"
+                const r1, 1
+                mov r2, r0
+                const r1, 1       // r1 already holds 1
+                mov r2, r0        // r2 already holds a copy of r0
+                add.32 r3, r2, r1 // reads r0 rather than its copy
+                jmp_if r3, done
+                stop
+                trap              // unreachable
+            done:
+                mov r0, r2        // r2 is a copy of r0
+                stop
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            optimize(&bytecode),
+            assemble(
+                r#"
+                    const r1, 1
+                    mov r2, r0
+                    add.32 r3, r0, r1
+                    jmp_if r3, done
+                    stop
+                done:
+                    stop
+                "#
+            )
+            .unwrap()
+        );
+        assert_equivalent(&bytecode, &[&[0], &[5], &[u32::MAX as u128]], &[]);
+    }
+
+    #[test]
+    fn folds_constants() {
+        let bytecode = assemble(
+            r#"
+                const r0, 6
+                const r1, 7
+                mul.8 r2, r0, r1
+                fdiv r3, r2, r1
+                fdiv r4, r2, r5     // r5 is unknown, so this can't be folded
+                lt.8 r5, r2, r0
+                jmp_if_not r5, big
+                trap
+            big:
+                udiv.8 r6, r0, r7   // r7 is zero but the division must still fail
+                stop
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            optimize(&bytecode),
+            assemble(
+                r#"
+                    const r0, 6
+                    const r1, 7
+                    const r2, 42
+                    const r3, 6
+                    fdiv r4, r2, r5
+                    const r5, 0
+                    udiv.8 r6, r0, r7
+                    stop
+                "#
+            )
+            .unwrap()
+        );
+        assert_equivalent(&bytecode, &[&[], &[0, 0, 0, 0, 0, 3]], &[]);
+    }
+
+    #[test]
+    fn threads_jumps_and_rewrites_labels() {
+        let bytecode = assemble(
+            r#"
+                call function
+                jmp_if r0, skip
+                mov r1, r0
+            skip:
+                jmp end
+            function:
+                jmp body
+            unused:
+                stop
+            body:
+                const r2, 3
+                return
+            end:
+                stop
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            optimize(&bytecode),
+            vec![
+                Opcode::Call { location: 4 },
+                Opcode::JumpIf { condition: RegisterIndex::from(0), location: 6 },
+                Opcode::Mov { destination: RegisterIndex::from(1), source: RegisterIndex::from(0) },
+                Opcode::Jump { location: 6 },
+                Opcode::Const { destination: RegisterIndex::from(2), value: 3u128.into() },
+                Opcode::Return,
+                Opcode::Stop,
+            ]
+        );
+        assert_equivalent(&bytecode, &[&[0], &[1]], &[]);
+    }
+
+    #[test]
+    fn forgets_registers_written_by_foreign_calls() {
+        let bytecode = assemble(
+            r#"
+                const r0, 1
+                const r1, 2
+                foreign_call double r1 -> r0
+                const r0, 1
+                mov r2, r0
+                stop
+            "#,
+        )
+        .unwrap();
+        assert_eq!(optimize(&bytecode), bytecode);
+        assert_equivalent(&bytecode, &[&[]], &[Value::from(4u128).into()]);
+        assert_equivalent(&bytecode, &[&[]], &[]);
+    }
+
+    /// A small pseudorandom number generator, so that generated programs are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    /// Generates a program using few registers and values, so that it contains many opportunities for
+    /// optimization.
+    fn generate_program(rng: &mut XorShift) -> Vec<Opcode> {
+        let len = 4 + rng.below(20);
+        (0..len)
+            .map(|_| {
+                let mut register = || RegisterIndex::from(rng.below(4));
+                let (destination, lhs, rhs) = (register(), register(), register());
+                match rng.below(16) {
+                    0..=2 => Opcode::Const { destination, value: (rng.below(3) as u128).into() },
+                    3..=5 => Opcode::Mov { destination, source: lhs },
+                    6..=7 => Opcode::BinaryIntOp {
+                        destination,
+                        op: [
+                            BinaryIntOp::Add,
+                            BinaryIntOp::Sub,
+                            BinaryIntOp::Mul,
+                            BinaryIntOp::UnsignedDiv,
+                            BinaryIntOp::Equals,
+                            BinaryIntOp::LessThan,
+                        ][rng.below(6)],
+                        bit_size: 8,
+                        lhs,
+                        rhs,
+                    },
+                    8 => Opcode::BinaryFieldOp {
+                        destination,
+                        op: [BinaryFieldOp::Add, BinaryFieldOp::Div][rng.below(2)],
+                        lhs,
+                        rhs,
+                    },
+                    9 => Opcode::Load { destination, source_pointer: lhs },
+                    10 => Opcode::Store { destination_pointer: lhs, source: rhs },
+                    11 => Opcode::Jump { location: rng.below(len + 1) },
+                    12 => Opcode::JumpIf { condition: lhs, location: rng.below(len + 1) },
+                    13 => Opcode::JumpIfNot { condition: lhs, location: rng.below(len + 1) },
+                    14 => [Opcode::Call { location: rng.below(len) }, Opcode::Return][rng.below(2)]
+                        .clone(),
+                    _ => [Opcode::Stop, Opcode::Trap][rng.below(2)].clone(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generated_programs_behave_identically_once_optimized() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1000 {
+            let bytecode = generate_program(&mut rng);
+            assert_equivalent(&bytecode, &[&[], &[1, 0, 2, 7], &[3, 3, 255, 1]], &[]);
+        }
+    }
+}
@@ -0,0 +1,568 @@
+//! Parser for the textual format of Brillig instructions produced by the `Display` implementation
+//! of [`Opcode`].
+
+use std::{collections::HashMap, str::FromStr};
+
+use acir_field::FieldElement;
+use thiserror::Error;
+
+use crate::{
+    black_box::BlackBoxOp,
+    opcodes::{BinaryFieldOp, BinaryIntOp, HeapArray, HeapVector, RegisterOrMemory},
+    Label, Opcode, RegisterIndex,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid brillig instruction `{instruction}`: {message}")]
+pub struct ParseOpcodeError {
+    instruction: String,
+    message: String,
+}
+
+/// A token of the textual formats of Brillig instructions and of ACIR, which share a tokenizer so that
+/// they agree on what makes up a word or a string.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(String),
+    String(String),
+    Comma,
+    Colon,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+}
+
+impl Token {
+    fn punctuation(c: char) -> Option<Token> {
+        match c {
+            ',' => Some(Token::Comma),
+            ':' => Some(Token::Colon),
+            '(' => Some(Token::OpenParen),
+            ')' => Some(Token::CloseParen),
+            '[' => Some(Token::OpenBracket),
+            ']' => Some(Token::CloseBracket),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{word}`"),
+            Token::String(string) => write!(f, "{string:?}"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::OpenParen => write!(f, "`(`"),
+            Token::CloseParen => write!(f, "`)`"),
+            Token::OpenBracket => write!(f, "`[`"),
+            Token::CloseBracket => write!(f, "`]`"),
+        }
+    }
+}
+
+/// Parses a string literal which has been escaped using the `Debug` implementation of `str`.
+///
+/// `chars` should be positioned just after the opening quote.
+fn parse_escaped_string(chars: &mut std::str::Chars<'_>) -> Result<String, String> {
+    let mut string = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(string),
+            '\\' => {
+                let escaped = match chars.next().ok_or("unterminated string")? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    'u' => {
+                        let code = chars
+                            .as_str()
+                            .strip_prefix('{')
+                            .and_then(|rest| rest.split_once('}'))
+                            .map(|(code, _)| code)
+                            .ok_or("invalid unicode escape")?;
+                        let escaped = u32::from_str_radix(code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or("invalid unicode escape")?;
+                        // Skip over `{code}`
+                        chars.nth(code.len() + 1);
+                        escaped
+                    }
+                    c @ ('\\' | '"' | '\'') => c,
+                    c => return Err(format!("unknown escape sequence `\\{c}`")),
+                };
+                string.push(escaped);
+            }
+            c => string.push(c),
+        }
+    }
+}
+
+/// Splits `input` into words, strings and the characters of `punctuation`, discarding whitespace.
+///
+/// Any other characters, including punctuation which isn't listed, are part of words.
+#[doc(hidden)]
+pub fn tokenize(input: &str, punctuation: &[char]) -> Result<Vec<Token>, String> {
+    let is_delimiter = |c: char| c.is_whitespace() || c == '"' || punctuation.contains(&c);
+    let mut tokens = Vec::new();
+    let mut chars = input.chars();
+    loop {
+        let rest = chars.as_str();
+        let word_len = rest.find(is_delimiter).unwrap_or(rest.len());
+        if word_len > 0 {
+            tokens.push(Token::Word(rest[..word_len].to_owned()));
+            chars = rest[word_len..].chars();
+        }
+        match chars.next() {
+            None => return Ok(tokens),
+            Some('"') => tokens.push(Token::String(parse_escaped_string(&mut chars)?)),
+            Some(c) => tokens.extend(Token::punctuation(c)),
+        }
+    }
+}
+
+/// The punctuation of Brillig instructions. Colons are left within words as they may appear in the
+/// unquoted name of a foreign call.
+const PUNCTUATION: &[char] = &[',', '(', ')'];
+
+/// Names which may be used in place of registers and jump locations, used when [assembling][crate::assemble]
+/// a program.
+#[derive(Debug, Default)]
+pub(crate) struct Symbols {
+    pub(crate) aliases: HashMap<String, RegisterIndex>,
+    pub(crate) labels: HashMap<String, Label>,
+}
+
+struct Parser<'a> {
+    tokens: std::vec::IntoIter<Token>,
+    symbols: &'a Symbols,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Result<Token, String> {
+        self.tokens.next().ok_or_else(|| "unexpected end of instruction".to_owned())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.as_slice().first()
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected {expected} but found {token}")),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            token => Err(format!("expected a word but found {token}")),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match self.next() {
+            Ok(token) => Err(format!("unexpected trailing {token}")),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn register(&mut self) -> Result<RegisterIndex, String> {
+        let word = self.word()?;
+        if let Some(register) = self.symbols.aliases.get(&word) {
+            return Ok(*register);
+        }
+        word.strip_prefix('r')
+            .and_then(|index| index.parse::<usize>().ok())
+            .map(RegisterIndex::from)
+            .ok_or_else(|| format!("expected a register but found `{word}`"))
+    }
+
+    fn usize(&mut self) -> Result<usize, String> {
+        let word = self.word()?;
+        word.parse().map_err(|_| format!("expected an integer but found `{word}`"))
+    }
+
+    /// Parses the target of a jump or call, given either as a label or as an absolute location.
+    fn location(&mut self) -> Result<Label, String> {
+        let word = self.word()?;
+        if let Some(location) = self.symbols.labels.get(&word) {
+            return Ok(*location);
+        }
+        word.parse().map_err(|_| format!("expected a label or location but found `{word}`"))
+    }
+
+    fn field(&mut self) -> Result<FieldElement, String> {
+        let word = self.word()?;
+        FieldElement::try_from_display_str(&word)
+            .ok_or_else(|| format!("expected a field element but found `{word}`"))
+    }
+
+    /// Parses the `(pointer, size)` arguments of a heap array or vector.
+    fn heap_arguments<T>(
+        &mut self,
+        size: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<(RegisterIndex, T), String> {
+        self.expect(Token::OpenParen)?;
+        let pointer = self.register()?;
+        self.expect(Token::Comma)?;
+        let size = size(self)?;
+        self.expect(Token::CloseParen)?;
+        Ok((pointer, size))
+    }
+
+    fn heap_array(&mut self) -> Result<HeapArray, String> {
+        match self.word()?.as_str() {
+            "array" => {
+                let (pointer, size) = self.heap_arguments(Self::usize)?;
+                Ok(HeapArray { pointer, size })
+            }
+            word => Err(format!("expected a heap array but found `{word}`")),
+        }
+    }
+
+    fn heap_vector(&mut self) -> Result<HeapVector, String> {
+        match self.word()?.as_str() {
+            "vector" => {
+                let (pointer, size) = self.heap_arguments(Self::register)?;
+                Ok(HeapVector { pointer, size })
+            }
+            word => Err(format!("expected a heap vector but found `{word}`")),
+        }
+    }
+
+    fn register_or_memory(&mut self) -> Result<RegisterOrMemory, String> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == "array" => {
+                self.heap_array().map(RegisterOrMemory::HeapArray)
+            }
+            Some(Token::Word(word)) if word == "vector" => {
+                self.heap_vector().map(RegisterOrMemory::HeapVector)
+            }
+            _ => self.register().map(RegisterOrMemory::RegisterIndex),
+        }
+    }
+
+    /// Parses a possibly empty, comma separated list of foreign call operands.
+    fn operands(&mut self) -> Result<Vec<RegisterOrMemory>, String> {
+        let mut operands = Vec::new();
+        match self.peek() {
+            None => return Ok(operands),
+            Some(Token::Word(word)) if word == "->" => return Ok(operands),
+            _ => {}
+        }
+        operands.push(self.register_or_memory()?);
+        while self.peek() == Some(&Token::Comma) {
+            self.next()?;
+            operands.push(self.register_or_memory()?);
+        }
+        Ok(operands)
+    }
+
+    fn arrow(&mut self) -> Result<(), String> {
+        self.expect(Token::Word("->".to_owned()))
+    }
+
+    /// Parses the `destination, lhs, rhs` operands of a binary operation.
+    fn binary_operands(&mut self) -> Result<(RegisterIndex, RegisterIndex, RegisterIndex), String> {
+        let destination = self.register()?;
+        self.expect(Token::Comma)?;
+        let lhs = self.register()?;
+        self.expect(Token::Comma)?;
+        let rhs = self.register()?;
+        Ok((destination, lhs, rhs))
+    }
+
+    /// Parses two comma separated operands.
+    fn pair<A, B>(
+        &mut self,
+        first: impl FnOnce(&mut Self) -> Result<A, String>,
+        second: impl FnOnce(&mut Self) -> Result<B, String>,
+    ) -> Result<(A, B), String> {
+        let first = first(self)?;
+        self.expect(Token::Comma)?;
+        Ok((first, second(self)?))
+    }
+
+    fn opcode(&mut self) -> Result<Opcode, String> {
+        let mnemonic = self.word()?;
+        let opcode = match mnemonic.as_str() {
+            "jmp_if_not" => {
+                let (condition, location) = self.pair(Self::register, Self::location)?;
+                Opcode::JumpIfNot { condition, location }
+            }
+            "jmp_if" => {
+                let (condition, location) = self.pair(Self::register, Self::location)?;
+                Opcode::JumpIf { condition, location }
+            }
+            "jmp" => Opcode::Jump { location: self.location()? },
+            "call" => Opcode::Call { location: self.location()? },
+            "const" => {
+                let (destination, value) = self.pair(Self::register, Self::field)?;
+                Opcode::Const { destination, value: value.into() }
+            }
+            "return" => Opcode::Return,
+            "foreign_call" => {
+                let function = match self.next()? {
+                    // Names which are valid words may be written without quotes.
+                    Token::String(function) | Token::Word(function) if function != "->" => function,
+                    token => return Err(format!("expected a function name but found {token}")),
+                };
+                let inputs = self.operands()?;
+                self.arrow()?;
+                let destinations = self.operands()?;
+                Opcode::ForeignCall { function, destinations, inputs }
+            }
+            "mov" => {
+                let (destination, source) = self.pair(Self::register, Self::register)?;
+                Opcode::Mov { destination, source }
+            }
+            "load" => {
+                let (destination, source_pointer) = self.pair(Self::register, Self::register)?;
+                Opcode::Load { destination, source_pointer }
+            }
+            "store" => {
+                let (destination_pointer, source) = self.pair(Self::register, Self::register)?;
+                Opcode::Store { destination_pointer, source }
+            }
+            "trap" => Opcode::Trap,
+            "stop" => Opcode::Stop,
+            _ => {
+                if let Some(op) = parse_binary_field_op(&mnemonic) {
+                    let (destination, lhs, rhs) = self.binary_operands()?;
+                    Opcode::BinaryFieldOp { destination, op, lhs, rhs }
+                } else if let Some((op, bit_size)) = parse_binary_int_op(&mnemonic) {
+                    let (destination, lhs, rhs) = self.binary_operands()?;
+                    Opcode::BinaryIntOp { destination, op, bit_size, lhs, rhs }
+                } else {
+                    Opcode::BlackBox(self.black_box(&mnemonic)?)
+                }
+            }
+        };
+        self.finish()?;
+        Ok(opcode)
+    }
+
+    fn black_box(&mut self, name: &str) -> Result<BlackBoxOp, String> {
+        let op = match name {
+            "sha256" | "blake2s" | "keccak256" => {
+                let message = self.heap_vector()?;
+                self.arrow()?;
+                let output = self.heap_array()?;
+                match name {
+                    "sha256" => BlackBoxOp::Sha256 { message, output },
+                    "blake2s" => BlackBoxOp::Blake2s { message, output },
+                    _ => BlackBoxOp::Keccak256 { message, output },
+                }
+            }
+            "hash_to_field_128_security" => {
+                let message = self.heap_vector()?;
+                self.arrow()?;
+                BlackBoxOp::HashToField128Security { message, output: self.register()? }
+            }
+            "ecdsa_secp256k1" | "ecdsa_secp256r1" => {
+                let hashed_msg = self.heap_vector()?;
+                self.expect(Token::Comma)?;
+                let public_key_x = self.heap_array()?;
+                self.expect(Token::Comma)?;
+                let public_key_y = self.heap_array()?;
+                self.expect(Token::Comma)?;
+                let signature = self.heap_array()?;
+                self.arrow()?;
+                let result = self.register()?;
+                if name == "ecdsa_secp256k1" {
+                    BlackBoxOp::EcdsaSecp256k1 {
+                        hashed_msg,
+                        public_key_x,
+                        public_key_y,
+                        signature,
+                        result,
+                    }
+                } else {
+                    BlackBoxOp::EcdsaSecp256r1 {
+                        hashed_msg,
+                        public_key_x,
+                        public_key_y,
+                        signature,
+                        result,
+                    }
+                }
+            }
+            _ => return Err(format!("unknown instruction `{name}`")),
+        };
+        Ok(op)
+    }
+}
+
+fn parse_binary_field_op(mnemonic: &str) -> Option<BinaryFieldOp> {
+    [
+        BinaryFieldOp::Add,
+        BinaryFieldOp::Sub,
+        BinaryFieldOp::Mul,
+        BinaryFieldOp::Div,
+        BinaryFieldOp::Equals,
+    ]
+    .into_iter()
+    .find(|op| op.mnemonic() == mnemonic)
+}
+
+/// Parses a mnemonic of the form `<op>.<bit_size>`, e.g. `add.32`.
+fn parse_binary_int_op(mnemonic: &str) -> Option<(BinaryIntOp, u32)> {
+    let (name, bit_size) = mnemonic.split_once('.')?;
+    let op = [
+        BinaryIntOp::Add,
+        BinaryIntOp::Sub,
+        BinaryIntOp::Mul,
+        BinaryIntOp::SignedDiv,
+        BinaryIntOp::UnsignedDiv,
+        BinaryIntOp::Equals,
+        BinaryIntOp::LessThan,
+        BinaryIntOp::LessThanEquals,
+        BinaryIntOp::And,
+        BinaryIntOp::Or,
+        BinaryIntOp::Xor,
+        BinaryIntOp::Shl,
+        BinaryIntOp::Shr,
+    ]
+    .into_iter()
+    .find(|op| op.mnemonic() == name)?;
+    Some((op, bit_size.parse().ok()?))
+}
+
+/// Parses a single instruction, resolving any of the given `symbols` which it uses.
+pub(crate) fn parse_opcode(instruction: &str, symbols: &Symbols) -> Result<Opcode, String> {
+    tokenize(instruction, PUNCTUATION)
+        .and_then(|tokens| Parser { tokens: tokens.into_iter(), symbols }.opcode())
+}
+
+impl FromStr for Opcode {
+    type Err = ParseOpcodeError;
+
+    fn from_str(instruction: &str) -> Result<Self, Self::Err> {
+        parse_opcode(instruction, &Symbols::default()).map_err(|message| ParseOpcodeError {
+            instruction: instruction.trim().to_owned(),
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acir_field::FieldElement;
+
+    use crate::{
+        black_box::BlackBoxOp,
+        opcodes::{BinaryFieldOp, BinaryIntOp, HeapArray, HeapVector, RegisterOrMemory},
+        Opcode, RegisterIndex,
+    };
+
+    fn r(index: usize) -> RegisterIndex {
+        RegisterIndex::from(index)
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let opcodes = vec![
+            Opcode::BinaryFieldOp {
+                destination: r(2),
+                op: BinaryFieldOp::Div,
+                lhs: r(0),
+                rhs: r(1),
+            },
+            Opcode::BinaryIntOp {
+                destination: r(2),
+                op: BinaryIntOp::SignedDiv,
+                bit_size: 32,
+                lhs: r(0),
+                rhs: r(1),
+            },
+            Opcode::JumpIfNot { condition: r(0), location: 5 },
+            Opcode::JumpIf { condition: r(1), location: 0 },
+            Opcode::Jump { location: 3 },
+            Opcode::Call { location: 7 },
+            Opcode::Const { destination: r(0), value: (-FieldElement::from(3_i128 << 64)).into() },
+            Opcode::Return,
+            Opcode::ForeignCall {
+                function: "say \"hello\"\n".to_owned(),
+                destinations: vec![RegisterOrMemory::HeapArray(HeapArray {
+                    pointer: r(3),
+                    size: 2,
+                })],
+                inputs: vec![
+                    RegisterOrMemory::RegisterIndex(r(0)),
+                    RegisterOrMemory::HeapVector(HeapVector { pointer: r(1), size: r(2) }),
+                ],
+            },
+            Opcode::ForeignCall {
+                function: "noop".to_owned(),
+                destinations: vec![],
+                inputs: vec![],
+            },
+            Opcode::Mov { destination: r(1), source: r(0) },
+            Opcode::Load { destination: r(1), source_pointer: r(0) },
+            Opcode::Store { destination_pointer: r(1), source: r(0) },
+            Opcode::BlackBox(BlackBoxOp::Sha256 {
+                message: HeapVector { pointer: r(0), size: r(1) },
+                output: HeapArray { pointer: r(2), size: 32 },
+            }),
+            Opcode::BlackBox(BlackBoxOp::HashToField128Security {
+                message: HeapVector { pointer: r(0), size: r(1) },
+                output: r(2),
+            }),
+            Opcode::BlackBox(BlackBoxOp::EcdsaSecp256r1 {
+                hashed_msg: HeapVector { pointer: r(0), size: r(1) },
+                public_key_x: HeapArray { pointer: r(2), size: 32 },
+                public_key_y: HeapArray { pointer: r(3), size: 32 },
+                signature: HeapArray { pointer: r(4), size: 64 },
+                result: r(5),
+            }),
+            Opcode::Trap,
+            Opcode::Stop,
+        ];
+
+        for opcode in opcodes {
+            let text = opcode.to_string();
+            assert_eq!(text.parse::<Opcode>(), Ok(opcode), "{text}");
+        }
+    }
+
+    #[test]
+    fn parses_hand_written_instructions() {
+        assert_eq!(
+            "  add.8   r3,r1, r2 ".parse::<Opcode>(),
+            Ok(Opcode::BinaryIntOp {
+                destination: r(3),
+                op: BinaryIntOp::Add,
+                bit_size: 8,
+                lhs: r(1),
+                rhs: r(2)
+            })
+        );
+        assert_eq!(
+            "const r0, 0x10".parse::<Opcode>(),
+            Ok(Opcode::Const { destination: r(0), value: 16_u128.into() })
+        );
+        assert_eq!(
+            "foreign_call std::print r0 ->".parse::<Opcode>(),
+            Ok(Opcode::ForeignCall {
+                function: "std::print".to_owned(),
+                destinations: vec![],
+                inputs: vec![RegisterOrMemory::RegisterIndex(r(0))],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_instructions() {
+        for instruction in
+            ["", "fadd r0, r1", "jmp r0", "mov r0 r1", "stop r0", "nop", "add.x r0, r1, r2"]
+        {
+            assert!(instruction.parse::<Opcode>().is_err(), "{instruction}");
+        }
+    }
+}
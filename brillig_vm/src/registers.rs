@@ -1,4 +1,4 @@
-use crate::Value;
+use crate::{FailureKind, Value};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -11,9 +11,9 @@ pub struct Registers {
 /// Aims to match a reasonable max register count for a SNARK prover.
 /// As well, catches obvious erroneous use of registers.
 /// This can be revisited if it proves not enough.
-const MAX_REGISTERS: usize = 2_usize.pow(16);
+pub(crate) const MAX_REGISTERS: usize = 2_usize.pow(16);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RegisterIndex(usize);
 
 /// `RegisterIndex` refers to the index in VM register space.
@@ -23,6 +23,12 @@ impl RegisterIndex {
     }
 }
 
+impl std::fmt::Display for RegisterIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
 impl From<usize> for RegisterIndex {
     fn from(value: usize) -> Self {
         RegisterIndex(value)
@@ -38,7 +44,30 @@ impl Registers {
         Self { inner }
     }
 
+    /// Gets the value at register with address `index`, failing if it is past the maximum register.
+    pub fn try_get(&self, register_index: RegisterIndex) -> Result<Value, FailureKind> {
+        if register_index.to_usize() >= MAX_REGISTERS {
+            return Err(FailureKind::RegisterOutOfBounds(register_index));
+        }
+        Ok(self.get(register_index))
+    }
+
+    /// Sets the value at register with address `index` to `value`, failing if it is past the maximum register.
+    pub fn try_set(
+        &mut self,
+        register_index: RegisterIndex,
+        value: Value,
+    ) -> Result<(), FailureKind> {
+        if register_index.to_usize() >= MAX_REGISTERS {
+            return Err(FailureKind::RegisterOutOfBounds(register_index));
+        }
+        self.set(register_index, value);
+        Ok(())
+    }
+
     /// Gets the values at register with address `index`
+    ///
+    /// Panics: If `index` is past the maximum register.
     pub fn get(&self, register_index: RegisterIndex) -> Value {
         let index = register_index.to_usize();
         assert!(index < MAX_REGISTERS, "Reading register past maximum!");
@@ -50,6 +79,8 @@ impl Registers {
     }
 
     /// Sets the value at register with address `index` to `value`
+    ///
+    /// Panics: If `index` is past the maximum register.
     pub fn set(&mut self, RegisterIndex(index): RegisterIndex, value: Value) {
         assert!(index < MAX_REGISTERS, "Writing register past maximum!");
         // if size isn't at least index + 1, resize
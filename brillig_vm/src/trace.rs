@@ -0,0 +1,14 @@
+use crate::{Opcode, RegisterIndex, Value};
+
+/// A record of a single opcode executed by the [VM][crate::VM], collected once tracing has been
+/// enabled with [`VM::enable_tracing`][crate::VM::enable_tracing].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The program counter at which `opcode` was executed.
+    pub program_counter: usize,
+    pub opcode: Opcode,
+    /// The registers whose value was changed by the opcode, along with their new value.
+    pub register_writes: Vec<(RegisterIndex, Value)>,
+    /// The memory addresses written by the opcode, along with the value written, in the order of the writes.
+    pub memory_writes: Vec<(usize, Value)>,
+}
@@ -0,0 +1,294 @@
+//! Static checks of Brillig bytecode, which catch errors that would otherwise only be found when the
+//! bytecode is executed.
+
+use thiserror::Error;
+
+use crate::{
+    registers::MAX_REGISTERS, BlackBoxOp, HeapArray, HeapVector, Label, Opcode, RegisterIndex,
+    RegisterOrMemory,
+};
+
+/// Integer operations are performed on values of at most this many bits, so that they can be represented
+/// as a signed `i128`.
+const MAX_BIT_SIZE: u32 = 127;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("bytecode does not end with a stop, return, trap or jmp instruction")]
+    MissingTerminator,
+    #[error(
+        "instruction {program_counter} jumps to {location}, which is past the end of the bytecode"
+    )]
+    JumpOutOfBounds { program_counter: usize, location: Label },
+    #[error("instruction {program_counter} uses register {register}, which is past the maximum register index")]
+    RegisterOutOfBounds { program_counter: usize, register: RegisterIndex },
+    #[error("instruction {program_counter} operates on {bit_size} bit integers but integer operations support between 1 and {MAX_BIT_SIZE} bits")]
+    InvalidBitSize { program_counter: usize, bit_size: u32 },
+    #[error("instruction {program_counter} expects an array of {expected} values for {operand} but is given an array of {size}")]
+    ArraySizeMismatch {
+        program_counter: usize,
+        operand: &'static str,
+        expected: usize,
+        size: usize,
+    },
+    #[error("instruction {program_counter} may return when no call has been made")]
+    UnbalancedReturn { program_counter: usize },
+}
+
+/// Checks that `bytecode` is well formed, returning every problem found.
+///
+/// This checks that:
+/// - execution can't continue past the final instruction,
+/// - jumps and calls target an instruction within the bytecode,
+/// - registers are below the maximum register index,
+/// - integer operations use a supported bit size,
+/// - black box functions are given arrays of the sizes they expect, and
+/// - `return` is only reachable from within a function entered using `call`.
+pub fn validate(bytecode: &[Opcode]) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    if !matches!(
+        bytecode.last(),
+        Some(Opcode::Stop | Opcode::Return | Opcode::Trap | Opcode::Jump { .. })
+    ) {
+        errors.push(ValidationError::MissingTerminator);
+    }
+
+    for (program_counter, opcode) in bytecode.iter().enumerate() {
+        match opcode {
+            Opcode::Jump { location }
+            | Opcode::JumpIf { location, .. }
+            | Opcode::JumpIfNot { location, .. }
+            | Opcode::Call { location }
+                if *location >= bytecode.len() =>
+            {
+                errors.push(ValidationError::JumpOutOfBounds {
+                    program_counter,
+                    location: *location,
+                });
+            }
+            Opcode::BinaryIntOp { bit_size, .. } if !(1..=MAX_BIT_SIZE).contains(bit_size) => {
+                errors
+                    .push(ValidationError::InvalidBitSize { program_counter, bit_size: *bit_size });
+            }
+            Opcode::BlackBox(op) => {
+                for (operand, array, expected) in fixed_size_arrays(op) {
+                    if array.size != expected {
+                        errors.push(ValidationError::ArraySizeMismatch {
+                            program_counter,
+                            operand,
+                            expected,
+                            size: array.size,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let mut out_of_bounds = Vec::new();
+        for register in registers(opcode) {
+            if register.to_usize() >= MAX_REGISTERS && !out_of_bounds.contains(&register) {
+                out_of_bounds.push(register);
+                errors.push(ValidationError::RegisterOutOfBounds { program_counter, register });
+            }
+        }
+    }
+
+    for program_counter in unbalanced_returns(bytecode) {
+        errors.push(ValidationError::UnbalancedReturn { program_counter });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns the arrays passed to a black box function whose size is fixed, along with their expected size.
+fn fixed_size_arrays(op: &BlackBoxOp) -> Vec<(&'static str, &HeapArray, usize)> {
+    match op {
+        BlackBoxOp::Sha256 { output, .. }
+        | BlackBoxOp::Blake2s { output, .. }
+        | BlackBoxOp::Keccak256 { output, .. } => vec![("the output", output, 32)],
+        BlackBoxOp::HashToField128Security { .. } => vec![],
+        BlackBoxOp::EcdsaSecp256k1 { public_key_x, public_key_y, signature, .. }
+        | BlackBoxOp::EcdsaSecp256r1 { public_key_x, public_key_y, signature, .. } => vec![
+            ("the public key x coordinate", public_key_x, 32),
+            ("the public key y coordinate", public_key_y, 32),
+            ("the signature", signature, 64),
+        ],
+    }
+}
+
+/// Returns every register read or written by `opcode`, including those holding pointers and sizes.
+fn registers(opcode: &Opcode) -> Vec<RegisterIndex> {
+    let array = |array: &HeapArray| vec![array.pointer];
+    let vector = |vector: &HeapVector| vec![vector.pointer, vector.size];
+    let operand = |operand: &RegisterOrMemory| match operand {
+        RegisterOrMemory::RegisterIndex(register) => vec![*register],
+        RegisterOrMemory::HeapArray(heap_array) => array(heap_array),
+        RegisterOrMemory::HeapVector(heap_vector) => vector(heap_vector),
+    };
+
+    match opcode {
+        Opcode::BinaryFieldOp { destination, lhs, rhs, .. }
+        | Opcode::BinaryIntOp { destination, lhs, rhs, .. } => vec![*destination, *lhs, *rhs],
+        Opcode::JumpIfNot { condition, .. } | Opcode::JumpIf { condition, .. } => vec![*condition],
+        Opcode::Const { destination, .. } => vec![*destination],
+        Opcode::ForeignCall { destinations, inputs, .. } => {
+            destinations.iter().chain(inputs).flat_map(operand).collect()
+        }
+        Opcode::Mov { destination, source } => vec![*destination, *source],
+        Opcode::Load { destination, source_pointer } => vec![*destination, *source_pointer],
+        Opcode::Store { destination_pointer, source } => vec![*destination_pointer, *source],
+        Opcode::BlackBox(op) => match op {
+            BlackBoxOp::Sha256 { message, output }
+            | BlackBoxOp::Blake2s { message, output }
+            | BlackBoxOp::Keccak256 { message, output } => {
+                vector(message).into_iter().chain(array(output)).collect()
+            }
+            BlackBoxOp::HashToField128Security { message, output } => {
+                vector(message).into_iter().chain([*output]).collect()
+            }
+            BlackBoxOp::EcdsaSecp256k1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            }
+            | BlackBoxOp::EcdsaSecp256r1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            } => vector(hashed_msg)
+                .into_iter()
+                .chain(array(public_key_x))
+                .chain(array(public_key_y))
+                .chain(array(signature))
+                .chain([*result])
+                .collect(),
+        },
+        Opcode::Jump { .. }
+        | Opcode::Call { .. }
+        | Opcode::Return
+        | Opcode::Trap
+        | Opcode::Stop => {
+            vec![]
+        }
+    }
+}
+
+/// Returns the `return` instructions which are reachable from the start of the bytecode without entering
+/// a function using `call`, in which case the call stack would be empty.
+///
+/// Calls are assumed to return to the instruction following them.
+fn unbalanced_returns(bytecode: &[Opcode]) -> Vec<usize> {
+    let mut visited = vec![false; bytecode.len()];
+    let mut to_visit = vec![0];
+    let mut returns = Vec::new();
+    while let Some(program_counter) = to_visit.pop() {
+        match visited.get_mut(program_counter) {
+            Some(visited) if !*visited => *visited = true,
+            // Instructions past the end of the bytecode are reported separately.
+            _ => continue,
+        }
+
+        match &bytecode[program_counter] {
+            Opcode::Return => returns.push(program_counter),
+            Opcode::Stop | Opcode::Trap => {}
+            Opcode::Jump { location } => to_visit.push(*location),
+            Opcode::JumpIf { location, .. } | Opcode::JumpIfNot { location, .. } => {
+                to_visit.extend([program_counter + 1, *location]);
+            }
+            _ => to_visit.push(program_counter + 1),
+        }
+    }
+    returns.sort_unstable();
+    returns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, ValidationError};
+    use crate::{
+        registers::MAX_REGISTERS, BinaryIntOp, BlackBoxOp, HeapArray, HeapVector, Opcode,
+        RegisterIndex,
+    };
+
+    fn r(index: usize) -> RegisterIndex {
+        RegisterIndex::from(index)
+    }
+
+    #[test]
+    fn accepts_well_formed_bytecode() {
+        let bytecode = vec![
+            Opcode::Const { destination: r(0), value: 3u128.into() },
+            Opcode::Call { location: 3 },
+            Opcode::Stop,
+            // A function which decrements r0 until it is zero.
+            Opcode::JumpIfNot { condition: r(0), location: 6 },
+            Opcode::BinaryIntOp {
+                destination: r(0),
+                op: BinaryIntOp::Sub,
+                bit_size: 127,
+                lhs: r(0),
+                rhs: r(1),
+            },
+            Opcode::Jump { location: 3 },
+            Opcode::Return,
+        ];
+        assert_eq!(validate(&bytecode), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let bytecode = vec![
+            Opcode::JumpIf { condition: r(MAX_REGISTERS), location: 4 },
+            Opcode::BinaryIntOp {
+                destination: r(0),
+                op: BinaryIntOp::Add,
+                bit_size: 128,
+                lhs: r(0),
+                rhs: r(0),
+            },
+            Opcode::BlackBox(BlackBoxOp::Sha256 {
+                message: HeapVector { pointer: r(0), size: r(1) },
+                output: HeapArray { pointer: r(2), size: 31 },
+            }),
+            Opcode::Return,
+        ];
+        assert_eq!(
+            validate(&bytecode),
+            Err(vec![
+                ValidationError::JumpOutOfBounds { program_counter: 0, location: 4 },
+                ValidationError::RegisterOutOfBounds {
+                    program_counter: 0,
+                    register: r(MAX_REGISTERS)
+                },
+                ValidationError::InvalidBitSize { program_counter: 1, bit_size: 128 },
+                ValidationError::ArraySizeMismatch {
+                    program_counter: 2,
+                    operand: "the output",
+                    expected: 32,
+                    size: 31
+                },
+                ValidationError::UnbalancedReturn { program_counter: 3 },
+            ])
+        );
+    }
+
+    #[test]
+    fn requires_a_final_terminator() {
+        assert_eq!(validate(&[]), Err(vec![ValidationError::MissingTerminator]));
+        assert_eq!(
+            validate(&[Opcode::Stop, Opcode::Mov { destination: r(0), source: r(1) }]),
+            Err(vec![ValidationError::MissingTerminator])
+        );
+        assert_eq!(validate(&[Opcode::Trap]), Ok(()));
+    }
+}
@@ -1,4 +1,6 @@
 use acir_field::FieldElement;
+
+use crate::FailureKind;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
@@ -40,6 +42,14 @@ impl Value {
         usize::try_from(self.inner.try_to_u64().expect("register does not fit into u64"))
             .expect("register does not fit into usize")
     }
+
+    /// Converts `Value` into a usize, failing if it does not fit.
+    pub fn try_to_usize(&self) -> Result<usize, FailureKind> {
+        self.inner
+            .try_to_u64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or(FailureKind::ValueTooLarge(self.inner))
+    }
 }
 
 impl From<usize> for Value {